@@ -5,35 +5,106 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
 use std::mem;
 use std::rc::Rc;
 
+use gloo::dialogs::{alert, confirm, prompt};
+use gloo::events::EventListener;
 use gloo::storage::errors::StorageError;
 use gloo::storage::{LocalStorage, Storage};
+use gloo::timers::callback::Timeout;
 use log::warn;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
+use wasm_bindgen::JsCast;
 use yew::prelude::*;
 
-use satisfactory_accounting::accounting::{Group, Node};
-use satisfactory_accounting::database::Database;
+use satisfactory_accounting::accounting::{BalanceCache, Building, Group, Node, NodeKind};
+use satisfactory_accounting::database::{BuildingId, Database, ItemCategory, ItemId, RecipeId};
+use satisfactory_accounting::schema;
 
-use crate::node_display::{NodeDisplay, NodeMeta, NodeMetadata};
+use crate::checkpoints::{Checkpoint, Checkpoints, MAX_CHECKPOINTS, MAX_CHECKPOINT_BYTES};
+use crate::clipboard;
+use crate::command_palette::{Command, CommandPalette};
+use crate::flat_list::FlatList;
+use crate::node_display::{
+    balance_mismatches, collect_generators, dom_path, get_value_from_input_event, matches_search,
+    node_label, remove_child, NodeDisplay, NodeMeta, NodeMetadata,
+};
+use crate::outline::Outline;
+use crate::perf_overlay::{self, PerfOverlay};
+use crate::recipe_comparison::RecipeComparison;
+use crate::recipe_replace::RecipeReplace;
+use crate::report::{text_summary, Report};
+use crate::root_summary::RootSummary;
+use crate::side_panels::{Dock, DockSide, Panel, PanelId, PanelLayout};
+use crate::statistics::Statistics;
+use crate::tabs::TabBar;
+use crate::watch_lists::WatchLists;
+
+mod validation;
 
 /// Key that the app state is stored under.
 const DB_KEY: &str = "zstewart.satisfactorydb.state.database";
 const GRAPH_KEY: &str = "zstewart.satisfactorydb.state.graph";
 const METADATA_KEY: &str = "zstewart.satisfactorydb.state.metadata";
 const GLOBAL_METADATA_KEY: &str = "zstewart.satisfactorydb.state.globalmetadata";
+const VIEW_STATE_KEY: &str = "zstewart.satisfactorydb.state.viewstate";
+const DOCUMENT_SETTINGS_KEY: &str = "zstewart.satisfactorydb.state.documentsettings";
+const CHECKPOINTS_KEY: &str = "zstewart.satisfactorydb.state.checkpoints";
+const TABS_KEY: &str = "zstewart.satisfactorydb.state.tabs";
+/// Copy of a saved graph which couldn't be loaded, kept so it isn't lost when the empty
+/// graph replacing it is saved.
+const GRAPH_BACKUP_KEY: &str = "zstewart.satisfactorydb.state.graph.unreadable";
+
+/// Name of the tab holding a factory saved before the document had tabs.
+const DEFAULT_TAB_NAME: &str = "Factory";
+
+/// A separate top-level factory within the document, shown as a tab.
+#[derive(Debug, Clone)]
+struct FactoryTab {
+    /// Name shown on the tab.
+    name: String,
+    /// Root of the tab as of when it was last switched away from. The root of the active
+    /// tab lives in [`AppState::root`], so it is out of date here while the tab is active.
+    root: Node,
+}
+
+/// Stored form of the document's tabs.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredTabs {
+    /// Index of the active tab.
+    active: usize,
+    /// Every tab in order.
+    tabs: Vec<StoredTab>,
+}
+
+/// Stored form of a single tab.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredTab {
+    /// Name shown on the tab.
+    name: String,
+    /// Root of the tab in the versioned storage format. None for the active tab, whose
+    /// root is saved on its own so versions of the app without tabs can still load it.
+    root: Option<Value>,
+}
 
 /// Stored state of the app.
 #[derive(Debug, Clone)]
 struct AppState {
     /// Database used in the app previously.
     database: Rc<Database>,
-    /// Root node of the accounting tree.
+    /// Root node of the accounting tree of the active tab.
     root: Node,
+    /// Every tab of the document. Always has at least one tab.
+    tabs: Vec<FactoryTab>,
+    /// Index of the active tab, whose root is being edited.
+    active_tab: usize,
+    /// Settings of this factory, which override the global preferences.
+    settings: DocumentSettings,
     /// Cached value tracking whether the database is out of date, so we don't have to
     /// repeatedly compare the database.
     database_outdated: bool,
@@ -64,34 +135,644 @@ impl AppState {
                 (Rc::new(default), false)
             }
         };
-        let root = LocalStorage::get(GRAPH_KEY).unwrap_or_else(|e| {
+        let root = LocalStorage::get(GRAPH_KEY)
+            .map_err(|e| {
+                if !matches!(e, StorageError::KeyNotFound(_)) {
+                    warn!("Failed to load graph: {}", e);
+                    Self::back_up_unreadable_graph();
+                }
+            })
+            .and_then(|stored| {
+                schema::load(stored).map_err(|e| {
+                    warn!("Failed to migrate graph: {}", e);
+                    Self::back_up_unreadable_graph();
+                })
+            })
+            .unwrap_or_else(|()| Group::empty().into());
+        let (tabs, active_tab) = Self::load_tabs(&root).unwrap_or_else(|| {
+            let tab = FactoryTab {
+                name: DEFAULT_TAB_NAME.to_string(),
+                root: root.clone(),
+            };
+            (vec![tab], 0)
+        });
+        let settings = LocalStorage::get(DOCUMENT_SETTINGS_KEY).unwrap_or_else(|e| {
             if !matches!(e, StorageError::KeyNotFound(_)) {
-                warn!("Failed to load graph: {}", e);
+                warn!("Failed to load document settings: {}", e);
             }
-            Group::empty().into()
+            Default::default()
         });
         Self {
             database,
             root,
+            tabs,
+            active_tab,
+            settings,
             database_outdated,
         }
     }
 
+    /// Load the saved tabs and the index of the active one, or None if there are none
+    /// saved. The active tab gets the given root. Tabs which can't be migrated are
+    /// replaced with empty groups.
+    fn load_tabs(active_root: &Node) -> Option<(Vec<FactoryTab>, usize)> {
+        let stored: StoredTabs = LocalStorage::get(TABS_KEY)
+            .map_err(|e| {
+                if !matches!(e, StorageError::KeyNotFound(_)) {
+                    warn!("Failed to load tabs: {}", e);
+                }
+            })
+            .ok()?;
+        if stored.tabs.is_empty() {
+            return None;
+        }
+        let active = stored.active.min(stored.tabs.len() - 1);
+        let tabs = stored
+            .tabs
+            .into_iter()
+            .enumerate()
+            .map(|(idx, tab)| {
+                let root = match tab.root {
+                    _ if idx == active => Ok(active_root.clone()),
+                    Some(root) => schema::load(root).map_err(|e| e.to_string()),
+                    None => Err("no saved root".to_string()),
+                };
+                FactoryTab {
+                    root: root.unwrap_or_else(|e| {
+                        warn!("Failed to load tab {}: {}", tab.name, e);
+                        Group::empty().into()
+                    }),
+                    name: tab.name,
+                }
+            })
+            .collect();
+        Some((tabs, active))
+    }
+
+    /// Copy the saved graph to [`GRAPH_BACKUP_KEY`] before it is replaced, such as when it
+    /// was saved by a newer version of the app.
+    fn back_up_unreadable_graph() {
+        let storage = LocalStorage::raw();
+        match storage.get_item(GRAPH_KEY) {
+            Ok(Some(raw)) => match storage.set_item(GRAPH_BACKUP_KEY, &raw) {
+                Ok(()) => warn!(
+                    "Kept a copy of the unreadable graph in {}",
+                    GRAPH_BACKUP_KEY
+                ),
+                Err(e) => warn!("Failed to back up unreadable graph: {:?}", e),
+            },
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read unreadable graph: {:?}", e),
+        }
+    }
+
+    /// Get the name and current root of every tab, in order.
+    fn tab_roots(&self) -> Vec<(String, Node)> {
+        self.tabs
+            .iter()
+            .enumerate()
+            .map(|(idx, tab)| {
+                let root = if idx == self.active_tab {
+                    self.root.clone()
+                } else {
+                    tab.root.clone()
+                };
+                (tab.name.clone(), root)
+            })
+            .collect()
+    }
+
+    /// Build a group holding the roots of every tab, whose balance is the total of the
+    /// whole document.
+    fn combined_root(&self) -> Node {
+        let mut combined = Group::empty();
+        combined.name = "All Tabs".to_string();
+        combined.children = self.tab_roots().into_iter().map(|(_, root)| root).collect();
+        combined.into()
+    }
+
+    /// Replace the root of every tab, such as after rebuilding them all. The roots must be
+    /// in tab order.
+    fn set_tab_roots(&mut self, roots: impl IntoIterator<Item = Node>) {
+        for (idx, root) in roots.into_iter().enumerate() {
+            if idx == self.active_tab {
+                self.root = root.clone();
+            }
+            if let Some(tab) = self.tabs.get_mut(idx) {
+                tab.root = root;
+            }
+        }
+    }
+
+    /// Make the tab at the given index the one being edited.
+    fn switch_tab(&mut self, idx: usize) {
+        if idx == self.active_tab || idx >= self.tabs.len() {
+            return;
+        }
+        let next = self.tabs[idx].root.clone();
+        self.tabs[self.active_tab].root = mem::replace(&mut self.root, next);
+        self.active_tab = idx;
+    }
+
     /// Save the current app state.
     fn save(&self) {
         if let Err(e) = LocalStorage::set(DB_KEY, &self.database) {
             warn!("Unable to save database: {}", e);
         }
-        if let Err(e) = LocalStorage::set(GRAPH_KEY, &self.root) {
+        if let Err(e) = LocalStorage::set(GRAPH_KEY, schema::store(&self.root)) {
             warn!("Unable to save graph: {}", e);
         }
+        let tabs = StoredTabs {
+            active: self.active_tab,
+            tabs: self
+                .tabs
+                .iter()
+                .enumerate()
+                .map(|(idx, tab)| StoredTab {
+                    name: tab.name.clone(),
+                    root: (idx != self.active_tab).then(|| schema::store(&tab.root)),
+                })
+                .collect(),
+        };
+        if let Err(e) = LocalStorage::set(TABS_KEY, &tabs) {
+            warn!("Unable to save tabs: {}", e);
+        }
+        if let Err(e) = LocalStorage::set(DOCUMENT_SETTINGS_KEY, &self.settings) {
+            warn!("Unable to save document settings: {}", e);
+        }
+    }
+}
+
+/// Settings which belong to the factory being planned rather than to the app, since they
+/// depend on how far the playthrough has progressed. Saved and undone along with the tree.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentSettings {
+    /// Clock speed new buildings start at. None starts them at 100%.
+    pub default_clock_speed: Option<f32>,
+    /// Belt tier item rates are shown relative to in this factory. None uses
+    /// [`GlobalMetadata::reference_belt`].
+    pub reference_belt: Option<BeltTier>,
+    /// Whether alternate recipes are left out when choosing a recipe, for playthroughs
+    /// which haven't unlocked any yet.
+    pub hide_alternate_recipes: bool,
+}
+
+impl DocumentSettings {
+    /// Get the clock speed new buildings start at.
+    pub fn default_clock_speed(&self) -> f32 {
+        self.default_clock_speed.unwrap_or(1.0)
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GlobalMetadata {
     /// Whether empty balance values should be hidden.
-    hide_empty_balances: bool,
+    pub hide_empty_balances: bool,
+    /// Items which are pinned to the top of every balance.
+    pub pinned_items: BTreeSet<ItemId>,
+    /// Whether buildings should show the whole number of buildings needed to build them
+    /// at 100% clock speed.
+    pub show_buildable_counts: bool,
+    /// Whether groups should show an estimate of the number of foundations their
+    /// buildings cover.
+    pub show_foundation_estimates: bool,
+    /// Named sets of items whose net rates are summed across the whole factory.
+    pub watch_lists: Vec<WatchList>,
+    /// Unit to display power in.
+    pub power_unit: PowerUnit,
+    /// Time base to display item rates in.
+    pub rate_unit: RateUnit,
+    /// Items which are intentionally sunk or wasted, so surpluses of them are shown as
+    /// neutral rather than as overproduction.
+    pub sunk_items: BTreeSet<ItemId>,
+    /// Number of power shard slots unlocked per building. Buildings clocked higher than
+    /// this many shards allow are flagged. None if every slot is unlocked.
+    pub power_shard_slots: Option<u32>,
+    /// Number of power shards available to slot, or None if not tracked.
+    pub power_shards_owned: Option<u32>,
+    /// Recipes chosen most recently, newest first, offered as quick picks when choosing
+    /// a recipe.
+    pub recent_recipes: Vec<RecipeId>,
+    /// Building types chosen most recently, newest first, offered as quick-add buttons
+    /// in group footers.
+    pub recent_buildings: Vec<BuildingId>,
+    /// Number of levels of groups below the root shown expanded when the app loads.
+    /// Deeper groups with no saved expansion state start collapsed. None expands all.
+    pub initial_expand_depth: Option<u32>,
+    /// Net rates closer to zero than this, in items or MW per minute, are treated as
+    /// balanced. None uses [`DEFAULT_BALANCE_TOLERANCE`].
+    pub balance_tolerance: Option<f32>,
+    /// Whether the developer overlay with render timings is shown.
+    pub show_perf_overlay: bool,
+    /// Whether balances group their items under collapsible category headers.
+    pub group_balance_by_category: bool,
+    /// Categories which are expanded when balances are grouped by category.
+    pub expanded_item_categories: BTreeSet<ItemCategory>,
+    /// Belt tier which item rates in balances are shown as a percentage of. Fluids are
+    /// shown relative to a pipeline instead. None shows rates in items per minute. A
+    /// factory can choose its own with [`DocumentSettings::reference_belt`].
+    pub reference_belt: Option<BeltTier>,
+    /// Whether building nodes are hidden so that only groups and their balances are
+    /// shown, except in groups set to show their buildings.
+    pub groups_only: bool,
+    /// Whether power is listed after the items in balances, like an unpinned item, rather
+    /// than always first.
+    pub unpin_power: bool,
+}
+
+/// Default tolerance below which a net rate is treated as balanced. Hides floating point
+/// noise from fractional recipe rates.
+pub const DEFAULT_BALANCE_TOLERANCE: f32 = 1e-4;
+
+/// Maximum number of recently used recipes to remember.
+const MAX_RECENT_RECIPES: usize = 8;
+
+/// Number of quick-add buttons shown in group footers.
+const QUICK_ADD_BUILDINGS: usize = 4;
+
+/// Building types offered as quick-add buttons until enough have been used recently.
+const DEFAULT_QUICK_ADD_BUILDINGS: &[&str] = &[
+    "Desc_ConstructorMk1_C",
+    "Desc_AssemblerMk1_C",
+    "Desc_SmelterMk1_C",
+];
+
+/// Unit to display power in. Power is always stored in MW.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerUnit {
+    /// Use GW for large values and MW otherwise.
+    #[default]
+    Auto,
+    Megawatts,
+    Gigawatts,
+}
+
+impl PowerUnit {
+    /// Magnitude in MW at which automatic scaling switches to GW.
+    const AUTO_GW_THRESHOLD: f32 = 1000.0;
+
+    /// Get a string suitable for human display of this unit setting.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Auto => "Auto",
+            Self::Megawatts => "MW",
+            Self::Gigawatts => "GW",
+        }
+    }
+
+    /// Get the next unit setting, wrapping around.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Auto => Self::Megawatts,
+            Self::Megawatts => Self::Gigawatts,
+            Self::Gigawatts => Self::Auto,
+        }
+    }
+
+    /// Format a power value given in MW, rounded to two decimal places in the displayed
+    /// unit.
+    pub fn format(self, mw: f32) -> String {
+        let use_gw = match self {
+            Self::Auto => mw.abs() >= Self::AUTO_GW_THRESHOLD,
+            Self::Megawatts => false,
+            Self::Gigawatts => true,
+        };
+        // Adding zero turns a negative zero from rounding noise into a positive one, so it
+        // isn't shown as "-0".
+        if use_gw {
+            format!("{} GW", (mw / 10.0).round() / 100.0 + 0.0)
+        } else {
+            format!("{} MW", (mw * 100.0).round() / 100.0 + 0.0)
+        }
+    }
+}
+
+/// Time base to display item rates in. Rates are always stored per minute.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateUnit {
+    #[default]
+    PerMinute,
+    PerSecond,
+    PerHour,
+}
+
+impl RateUnit {
+    /// Get the suffix shown after rates in this unit.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PerMinute => "/min",
+            Self::PerSecond => "/s",
+            Self::PerHour => "/h",
+        }
+    }
+
+    /// Get the next unit setting, wrapping around.
+    pub fn next(self) -> Self {
+        match self {
+            Self::PerMinute => Self::PerSecond,
+            Self::PerSecond => Self::PerHour,
+            Self::PerHour => Self::PerMinute,
+        }
+    }
+
+    /// Number of decimal places rates in this unit are rounded to. Per-second rates are
+    /// small, so they get more.
+    pub fn decimals(self) -> usize {
+        match self {
+            Self::PerSecond => 4,
+            Self::PerMinute | Self::PerHour => 2,
+        }
+    }
+
+    /// Convert a rate per minute to this unit.
+    pub fn scale(self, per_minute: f32) -> f32 {
+        match self {
+            Self::PerMinute => per_minute,
+            Self::PerSecond => per_minute / 60.0,
+            Self::PerHour => per_minute * 60.0,
+        }
+    }
+
+    /// Convert a rate in this unit to a rate per minute.
+    pub fn unscale(self, rate: f32) -> f32 {
+        match self {
+            Self::PerMinute => rate,
+            Self::PerSecond => rate * 60.0,
+            Self::PerHour => rate / 60.0,
+        }
+    }
+
+    /// Convert a rate per minute to this unit, rounded to the unit's decimal places. Rates
+    /// which round to zero are always positive zero.
+    pub fn round(self, per_minute: f32) -> f32 {
+        let factor = 10f32.powi(self.decimals() as i32);
+        // Adding zero turns negative zero into positive zero.
+        (self.scale(per_minute) * factor).round() / factor + 0.0
+    }
+
+    /// Format a rate given per minute as a fixed-precision number in this unit, without
+    /// the suffix. Floating point noise around zero is shown as zero rather than "-0.00".
+    pub fn format_value(self, per_minute: f32) -> String {
+        format!("{:.*}", self.decimals(), self.round(per_minute))
+    }
+
+    /// Format a rate given per minute in this unit, with the suffix.
+    pub fn format(self, per_minute: f32) -> String {
+        format!("{}{}", self.format_value(per_minute), self.name())
+    }
+
+    /// Format a rate given per minute in this unit with the suffix, always showing the
+    /// sign.
+    pub fn format_signed(self, per_minute: f32) -> String {
+        format!(
+            "{:+.*}{}",
+            self.decimals(),
+            self.round(per_minute),
+            self.name()
+        )
+    }
+}
+
+/// Conveyor belt tier, used as a reference capacity for item rates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BeltTier {
+    Mk1,
+    Mk2,
+    Mk3,
+    Mk4,
+    Mk5,
+}
+
+impl BeltTier {
+    /// All belt tiers, slowest first.
+    pub const ALL: [BeltTier; 5] = [Self::Mk1, Self::Mk2, Self::Mk3, Self::Mk4, Self::Mk5];
+
+    /// Capacity of a Mk.2 pipeline in m³ per minute, which fluid rates are shown
+    /// relative to.
+    pub const PIPE_CAPACITY: f32 = 600.0;
+
+    /// Get a string suitable for human display of this belt tier.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mk1 => "Mk.1",
+            Self::Mk2 => "Mk.2",
+            Self::Mk3 => "Mk.3",
+            Self::Mk4 => "Mk.4",
+            Self::Mk5 => "Mk.5",
+        }
+    }
+
+    /// Capacity of this belt in items per minute.
+    pub fn capacity(self) -> f32 {
+        match self {
+            Self::Mk1 => 60.0,
+            Self::Mk2 => 120.0,
+            Self::Mk3 => 270.0,
+            Self::Mk4 => 480.0,
+            Self::Mk5 => 780.0,
+        }
+    }
+
+    /// Get a description of the capacity an item's rate is shown as a percentage of when
+    /// this is the reference belt, and that capacity per minute. Fluids are shown relative
+    /// to a pipeline.
+    pub fn capacity_for(self, item: ItemId) -> (String, f32) {
+        if item.is_fluid() {
+            ("a Mk.2 pipeline".to_string(), Self::PIPE_CAPACITY)
+        } else {
+            (format!("a {} belt", self.name()), self.capacity())
+        }
+    }
+
+    /// Format an item's rate per minute for a balance, either rounded in the given rate
+    /// unit, or as a percentage of the reference belt or pipeline if there is one.
+    pub fn format_rate(reference: Option<Self>, unit: RateUnit, item: ItemId, rate: f32) -> String {
+        match reference {
+            Some(belt) => {
+                let (_, capacity) = belt.capacity_for(item);
+                format!("{}%", (rate / capacity * 1000.0).round() / 10.0)
+            }
+            None => unit.round(rate).to_string(),
+        }
+    }
+
+    /// Get the next reference belt setting, slowest first, wrapping around through
+    /// showing plain rates.
+    pub fn next(reference: Option<Self>) -> Option<Self> {
+        match reference {
+            None => Some(Self::Mk1),
+            Some(Self::Mk1) => Some(Self::Mk2),
+            Some(Self::Mk2) => Some(Self::Mk3),
+            Some(Self::Mk3) => Some(Self::Mk4),
+            Some(Self::Mk4) => Some(Self::Mk5),
+            Some(Self::Mk5) => None,
+        }
+    }
+}
+
+/// A named set of items whose net rates are summed together.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchList {
+    /// Name of the watch list.
+    pub name: String,
+    /// Items included in the list.
+    pub items: BTreeSet<ItemId>,
+}
+
+impl GlobalMetadata {
+    /// Build a copy of this metadata with the pinned state of the given item flipped.
+    pub fn with_pin_toggled(&self, item: ItemId) -> Self {
+        let mut updated = self.clone();
+        if !updated.pinned_items.remove(&item) {
+            updated.pinned_items.insert(item);
+        }
+        updated
+    }
+
+    /// Build a copy of this metadata with the given item category expanded or collapsed.
+    pub fn with_category_toggled(&self, category: ItemCategory) -> Self {
+        let mut updated = self.clone();
+        if !updated.expanded_item_categories.remove(&category) {
+            updated.expanded_item_categories.insert(category);
+        }
+        updated
+    }
+
+    /// Build a copy of this metadata with the sunk state of the given item flipped.
+    pub fn with_sink_toggled(&self, item: ItemId) -> Self {
+        let mut updated = self.clone();
+        if !updated.sunk_items.remove(&item) {
+            updated.sunk_items.insert(item);
+        }
+        updated
+    }
+
+    /// Build a copy of this metadata with the given recipe moved to the front of the
+    /// recently used recipes.
+    pub fn with_recent_recipe(&self, recipe: RecipeId) -> Self {
+        let mut updated = self.clone();
+        updated.recent_recipes.retain(|&recent| recent != recipe);
+        updated.recent_recipes.insert(0, recipe);
+        updated.recent_recipes.truncate(MAX_RECENT_RECIPES);
+        updated
+    }
+
+    /// Build a copy of this metadata with the given building type moved to the front of
+    /// the recently used buildings.
+    pub fn with_recent_building(&self, building: BuildingId) -> Self {
+        let mut updated = self.clone();
+        updated
+            .recent_buildings
+            .retain(|&recent| recent != building);
+        updated.recent_buildings.insert(0, building);
+        updated.recent_buildings.truncate(QUICK_ADD_BUILDINGS);
+        updated
+    }
+
+    /// Get the building types offered as quick-add buttons: the recently used buildings,
+    /// topped up with common defaults.
+    pub fn quick_add_buildings(&self) -> Vec<BuildingId> {
+        let mut buildings = self.recent_buildings.clone();
+        for &default in DEFAULT_QUICK_ADD_BUILDINGS {
+            let default = BuildingId::from(default);
+            if !buildings.contains(&default) {
+                buildings.push(default);
+            }
+        }
+        buildings.truncate(QUICK_ADD_BUILDINGS);
+        buildings
+    }
+
+    /// Whether a building needing the given number of power shards exceeds the unlocked
+    /// shard slots.
+    pub fn exceeds_shard_slots(&self, shards: u32) -> bool {
+        matches!(self.power_shard_slots, Some(slots) if shards > slots)
+    }
+
+    /// Get the tolerance below which net rates are treated as balanced.
+    pub fn balance_tolerance(&self) -> f32 {
+        self.balance_tolerance.unwrap_or(DEFAULT_BALANCE_TOLERANCE)
+    }
+
+    /// Whether a net rate is a deficit larger than the balance tolerance.
+    pub fn is_deficit(&self, rate: f32) -> bool {
+        rate < -self.balance_tolerance()
+    }
+
+    /// Get the style class for a net rate, treating rates within the balance tolerance
+    /// as neutral.
+    pub fn rate_style(&self, rate: f32) -> &'static str {
+        let tolerance = self.balance_tolerance();
+        if rate < -tolerance {
+            "negative"
+        } else if rate > tolerance {
+            "positive"
+        } else {
+            "neutral"
+        }
+    }
+
+    /// Get the style class for an item's rate, treating surpluses of sunk items as
+    /// neutral.
+    pub fn item_rate_style(&self, item: ItemId, rate: f32) -> &'static str {
+        if rate > 0.0 && self.sunk_items.contains(&item) {
+            "neutral"
+        } else {
+            self.rate_style(rate)
+        }
+    }
+}
+
+/// Whether the viewport is narrow enough that components should use their compact
+/// layout. Provided as context by the [`App`], which tracks the viewport width.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CompactLayout(pub bool);
+
+/// Widest viewport, in CSS pixels, which uses the compact layout.
+const COMPACT_LAYOUT_MAX_WIDTH: f64 = 700.0;
+
+/// Largest number of node balances kept in the balance cache used for a full rebuild.
+const BALANCE_CACHE_CAPACITY: usize = 10_000;
+
+/// Largest number of mismatched nodes listed when verifying balances.
+const MAX_REPORTED_MISMATCHES: usize = 10;
+
+/// Name of the top-level group generators are collected into.
+const POWER_GROUP_NAME: &str = "Power";
+
+/// How long the toast offering to undo a deletion is shown, in milliseconds.
+const DELETE_TOAST_DURATION: u32 = 5000;
+
+/// How long nodes changed by undo or redo or matching a search stay highlighted, in
+/// milliseconds. Matches the length of the fade animations.
+const CHANGE_HIGHLIGHT_DURATION: u32 = 2000;
+
+/// State of the view which isn't part of the document, saved so that reloading returns
+/// to the same place. Which groups are collapsed is saved in [`NodeMetadata`], since it is
+/// tracked per-group.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct ViewState {
+    /// Vertical scroll position of the window.
+    scroll_y: f64,
+    /// Whether the recipe comparison is open.
+    show_recipe_comparison: bool,
+    /// Whether the recipe find-and-replace tool is open.
+    show_recipe_replace: bool,
+    /// Whether the outline is open.
+    show_outline: bool,
+    /// Whether every building is listed flat in place of the tree.
+    show_flat_list: bool,
+    /// Whether the summary shows the total of every tab rather than just the active one.
+    show_combined_summary: bool,
+    /// Widths of the side panel docks and which panels are collapsed.
+    panels: PanelLayout,
+    /// Index of the next problem to jump to. Taken modulo the number of problems, so it
+    /// is still valid if the tree has changed.
+    next_problem: usize,
 }
 
 /// Messages for communicating with App.
@@ -110,11 +791,133 @@ pub enum Msg {
     ToggleEmptyBalances {
         hide_empty_balances: bool,
     },
+    /// Replace the global metadata.
+    UpdateGlobalMetadata {
+        global_metadata: GlobalMetadata,
+    },
+    /// Hide the power deficit warning until the deficit gets worse.
+    DismissPowerWarning,
+    /// Show or hide the recipe comparison.
+    ToggleRecipeComparison,
+    /// Show or hide the recipe find-and-replace tool.
+    ToggleRecipeReplace,
+    /// Move every generator into a top-level power group, after confirming.
+    CollectGenerators,
+    /// Show or hide the outline.
+    ToggleOutline,
+    /// Switch between the tree and the flat list of buildings.
+    ToggleFlatList,
+    /// Save a resized dock or collapsed panel.
+    SetPanelLayout {
+        layout: PanelLayout,
+    },
+    /// Show the report and open the browser's print dialog.
+    PrintReport,
+    /// Copy a plain-text summary of the factory to the clipboard.
+    CopySummary,
+    /// Open or close the tree statistics.
+    ToggleStatistics,
+    /// Close the report and return to the interactive view.
+    CloseReport,
+    /// Remember the current root to show changes against.
+    SetBaseline,
+    /// Forget the baseline, hiding the changes against it.
+    ClearBaseline,
+    /// Open or close the list of checkpoints.
+    ToggleCheckpoints,
+    /// Ask for a name and save the current root as a checkpoint.
+    SaveCheckpoint,
+    /// Replace the root with the checkpoint at the given index, after confirming.
+    RestoreCheckpoint {
+        idx: usize,
+    },
+    /// Delete the checkpoint at the given index, after confirming.
+    DeleteCheckpoint {
+        idx: usize,
+    },
+    /// Scroll to the group with the given id, expanding its ancestors.
+    FocusGroup {
+        id: Uuid,
+    },
+    /// Ask for a name and create a new watch list.
+    AddWatchList,
+    /// Ask for the number of levels of groups to expand when the app loads.
+    SetInitialExpandDepth,
+    /// Ask for the tolerance below which net rates are treated as balanced.
+    SetBalanceTolerance,
+    /// Ask for the clock speed new buildings in this factory start at.
+    SetDefaultClockSpeed,
+    /// Change the belt this factory's item rates are shown relative to, cycling through
+    /// using the global setting.
+    CycleDocumentReferenceBelt,
+    /// Show or hide alternate recipes when choosing recipes in this factory.
+    ToggleAlternateRecipes,
+    /// Scroll to the next building which consumes an item that is in deficit.
+    NextProblem,
+    /// Change the text the tree is searched for.
+    SetSearchQuery {
+        query: String,
+    },
+    /// Expand and highlight every node matching the search, and scroll to the next one.
+    NextSearchMatch,
+    /// Save the current scroll position in the view state.
+    SaveScrollPosition,
+    /// Check whether the window was resized across the compact layout breakpoint.
+    Resized,
+    /// Open or close the command palette.
+    ToggleCommandPalette,
+    /// Close the command palette if it is open.
+    CloseCommandPalette,
+    /// Close the command palette and run the chosen command.
+    RunCommand {
+        command: Command,
+    },
+    /// Delete the node at the given path from the root. Non-empty groups are deleted
+    /// only after confirming, anything else is deleted immediately with a toast to undo.
+    DeleteNode {
+        path: Vec<usize>,
+    },
+    /// Undo the deletion announced by the delete toast.
+    UndoDelete,
+    /// Hide the delete toast.
+    DismissDeleteToast,
+    /// Switch to editing the tab at the given index.
+    SelectTab {
+        idx: usize,
+    },
+    /// Add a new tab with an empty factory and switch to it.
+    AddTab,
+    /// Ask for a new name for the tab at the given index.
+    RenameTab {
+        idx: usize,
+    },
+    /// Move a tab to a new position.
+    MoveTab {
+        from: usize,
+        to: usize,
+    },
+    /// Close the tab at the given index, after confirming if it isn't empty.
+    CloseTab {
+        idx: usize,
+    },
+    /// Switch the summary between the active tab and the total of all tabs.
+    ToggleCombinedSummary,
+    /// Recompute every balance from scratch and report any which differ from the balances
+    /// in the tree or the balance cache.
+    VerifyBalances,
     Undo,
     Redo,
     UpdateDb,
 }
 
+/// Notice that a node was deleted without confirmation, offering to undo it.
+struct DeleteToast {
+    /// Label of the deleted node.
+    label: String,
+    /// Timer which hides the toast. Cancelled when dropped.
+    _dismiss: Timeout,
+}
+
 pub struct App {
     state: AppState,
     /// Non-undo metadata about nodes.
@@ -123,10 +926,68 @@ pub struct App {
     global_metadata: GlobalMetadata,
     undo_stack: Vec<AppState>,
     redo_stack: Vec<AppState>,
+    /// Power deficit at the time the power warning was dismissed, if it was dismissed
+    /// this session.
+    dismissed_power_deficit: Option<f32>,
+    /// Non-undo state of the view.
+    view_state: ViewState,
+    /// Scroll position to restore after the next render.
+    pending_scroll: Option<f64>,
+    /// Listener which saves the scroll position when the window scrolls.
+    _scroll_listener: Option<EventListener>,
+    /// Path of a node to scroll to after the next render.
+    pending_focus: Option<Vec<usize>>,
+    /// Paths of nodes changed by the last undo or redo, to highlight after the next render.
+    pending_highlight: Vec<Vec<usize>>,
+    /// Text the tree is searched for. Not saved.
+    search_query: String,
+    /// Index of the next search match to jump to. Taken modulo the number of matches, so
+    /// it is still valid if the tree has changed.
+    next_search_match: usize,
+    /// Number of nodes which matched the search, if it has been run since the text was
+    /// last changed.
+    search_match_count: Option<usize>,
+    /// Paths of nodes which matched the search, to highlight after the next render.
+    pending_search_highlight: Vec<Vec<usize>>,
+    /// Listener for global keyboard shortcuts.
+    _shortcut_listener: Option<EventListener>,
+    /// Whether the command palette is open.
+    show_command_palette: bool,
+    /// Whether the tree statistics are open.
+    show_statistics: bool,
+    /// Toast offering to undo the last deletion. Since it undoes by popping the undo stack,
+    /// it is hidden by any other change to the undo history.
+    delete_toast: Option<DeleteToast>,
+    /// Whether the print-friendly report is shown in place of the interactive view.
+    show_report: bool,
+    /// Whether to open the print dialog after the next render.
+    pending_print: bool,
+    /// Snapshot of the root which the summary shows changes against. Not saved.
+    baseline: Option<Node>,
+    /// Named snapshots of the root. Saved separately from the undo history, so they
+    /// aren't affected by undo and redo.
+    checkpoints: Rc<Vec<Checkpoint>>,
+    /// Whether the list of checkpoints is open.
+    show_checkpoints: bool,
+    /// Whether the viewport is currently narrow enough for the compact layout.
+    compact_layout: CompactLayout,
+    /// Listener which tracks the viewport width when the window is resized.
+    _resize_listener: Option<EventListener>,
 }
 
 impl App {
+    /// In debug builds, check the structure of the tree, logging any problems found. Run
+    /// on every save so bugs in tree operations are noticed as soon as they happen.
+    fn check_invariants(&self) {
+        if cfg!(debug_assertions) {
+            for violation in validation::check_invariants(&self.state.root) {
+                warn!("Tree invariant violated: {}", violation);
+            }
+        }
+    }
+
     fn save(&self) {
+        self.check_invariants();
         self.state.save();
         if let Err(e) = LocalStorage::set(METADATA_KEY, &self.metadata) {
             warn!("Unable to save metadata: {}", e);
@@ -134,6 +995,21 @@ impl App {
         if let Err(e) = LocalStorage::set(GLOBAL_METADATA_KEY, &self.global_metadata) {
             warn!("Unable to save global metadata: {}", e);
         }
+        self.save_view_state();
+    }
+
+    /// Save only the checkpoints.
+    fn save_checkpoints(&self) {
+        if let Err(e) = LocalStorage::set(CHECKPOINTS_KEY, &*self.checkpoints) {
+            warn!("Unable to save checkpoints: {}", e);
+        }
+    }
+
+    /// Save only the view state.
+    fn save_view_state(&self) {
+        if let Err(e) = LocalStorage::set(VIEW_STATE_KEY, &self.view_state) {
+            warn!("Unable to save view state: {}", e);
+        }
     }
 
     /// Add a state to the Undo stack, clearing the redo stack and any history beyond 100
@@ -145,6 +1021,155 @@ impl App {
             self.undo_stack.drain(..num_to_remove);
         }
         self.redo_stack.clear();
+        self.delete_toast = None;
+    }
+
+    /// Replace the settings of the document, adding the change to the undo history.
+    fn update_document_settings(&mut self, settings: DocumentSettings) -> bool {
+        if self.state.settings == settings {
+            return false;
+        }
+        let previous = self.state.clone();
+        self.state.settings = settings;
+        self.add_undo_state(previous);
+        self.save();
+        true
+    }
+
+    /// Display the print-friendly report in place of the interactive view.
+    fn view_report(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let print = link.callback(|()| Msg::PrintReport);
+        let close = link.callback(|()| Msg::CloseReport);
+        html! {
+            <ContextProvider<Rc<Database>> context={Rc::clone(&self.state.database)}>
+                <ContextProvider<GlobalMetadata> context={self.global_metadata.clone()}>
+                    <ContextProvider<DocumentSettings> context={self.state.settings.clone()}>
+                        <div class="App report">
+                            <Report root={self.state.root.clone()} {print} {close} />
+                        </div>
+                    </ContextProvider<DocumentSettings>>
+                </ContextProvider<GlobalMetadata>>
+            </ContextProvider<Rc<Database>>>
+        }
+    }
+
+    /// Expand the ancestors of a node and scroll to it after the next render.
+    fn focus(&mut self, target: FocusTarget) {
+        let updates: HashMap<_, _> = target
+            .ancestors
+            .iter()
+            .map(|&id| (id, self.metadata.meta(id)))
+            .filter(|(_, meta)| meta.is_collapsed())
+            .map(|(id, meta)| (id, meta.expanded()))
+            .collect();
+        if !updates.is_empty() {
+            self.metadata.batch_update(updates);
+            self.save();
+        } else {
+            self.save_view_state();
+        }
+        self.pending_focus = Some(target.path);
+    }
+
+    /// Whether any item is in deficit in the overall balance.
+    fn has_deficits(&self) -> bool {
+        self.state
+            .root
+            .balance()
+            .balances
+            .values()
+            .any(|&rate| self.global_metadata.is_deficit(rate))
+    }
+
+    /// Get the commands which can currently be run from the command palette.
+    fn available_commands(&self) -> Vec<Command> {
+        Command::ALL
+            .iter()
+            .copied()
+            .filter(|command| match command {
+                Command::Undo => !self.undo_stack.is_empty(),
+                Command::Redo => !self.redo_stack.is_empty(),
+                Command::NextProblem => self.has_deficits(),
+                Command::NextSearchMatch => !self.search_query.trim().is_empty(),
+                Command::UpdateDb => self.state.database_outdated,
+                Command::ClearBaseline => self.baseline.is_some(),
+                // A diagnostic for the performance features, so only offered while
+                // profiling them.
+                Command::VerifyBalances => self.global_metadata.show_perf_overlay,
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Get the message which runs the given command.
+    fn command_msg(&self, command: Command) -> Option<Msg> {
+        let add_root_child = |child: Node| {
+            let mut root = self.state.root.group()?.clone();
+            root.children.push(child);
+            Some(Msg::ReplaceRoot {
+                replacement: root.into(),
+            })
+        };
+        let update_global_metadata = |update: &dyn Fn(&mut GlobalMetadata)| {
+            let mut global_metadata = self.global_metadata.clone();
+            update(&mut global_metadata);
+            Some(Msg::UpdateGlobalMetadata { global_metadata })
+        };
+        match command {
+            Command::AddBuilding => add_root_child(Building::empty_node()),
+            Command::AddGroup => add_root_child(Group::empty_node()),
+            Command::Undo => Some(Msg::Undo),
+            Command::Redo => Some(Msg::Redo),
+            Command::NextProblem => Some(Msg::NextProblem),
+            Command::NextSearchMatch => Some(Msg::NextSearchMatch),
+            Command::ToggleEmptyBalances => Some(Msg::ToggleEmptyBalances {
+                hide_empty_balances: !self.global_metadata.hide_empty_balances,
+            }),
+            Command::ToggleBuildableCounts => update_global_metadata(&|meta| {
+                meta.show_buildable_counts = !meta.show_buildable_counts;
+            }),
+            Command::ToggleFoundationEstimates => update_global_metadata(&|meta| {
+                meta.show_foundation_estimates = !meta.show_foundation_estimates;
+            }),
+            Command::CyclePowerUnit => update_global_metadata(&|meta| {
+                meta.power_unit = meta.power_unit.next();
+            }),
+            Command::CycleRateUnit => update_global_metadata(&|meta| {
+                meta.rate_unit = meta.rate_unit.next();
+            }),
+            Command::CycleReferenceBelt => update_global_metadata(&|meta| {
+                meta.reference_belt = BeltTier::next(meta.reference_belt);
+            }),
+            Command::AddWatchList => Some(Msg::AddWatchList),
+            Command::CompareRecipes => Some(Msg::ToggleRecipeComparison),
+            Command::ReplaceRecipe => Some(Msg::ToggleRecipeReplace),
+            Command::CollectGenerators => Some(Msg::CollectGenerators),
+            Command::ToggleOutline => Some(Msg::ToggleOutline),
+            Command::ToggleFlatList => Some(Msg::ToggleFlatList),
+            Command::PrintReport => Some(Msg::PrintReport),
+            Command::CopySummary => Some(Msg::CopySummary),
+            Command::ShowStatistics => Some(Msg::ToggleStatistics),
+            Command::SetBaseline => Some(Msg::SetBaseline),
+            Command::ClearBaseline => Some(Msg::ClearBaseline),
+            Command::ShowCheckpoints => Some(Msg::ToggleCheckpoints),
+            Command::SaveCheckpoint => Some(Msg::SaveCheckpoint),
+            Command::SetBalanceTolerance => Some(Msg::SetBalanceTolerance),
+            Command::SetDefaultClockSpeed => Some(Msg::SetDefaultClockSpeed),
+            Command::CycleDocumentReferenceBelt => Some(Msg::CycleDocumentReferenceBelt),
+            Command::ToggleAlternateRecipes => Some(Msg::ToggleAlternateRecipes),
+            Command::ToggleBalanceCategories => update_global_metadata(&|meta| {
+                meta.group_balance_by_category = !meta.group_balance_by_category;
+            }),
+            Command::ToggleGroupsOnly => update_global_metadata(&|meta| {
+                meta.groups_only = !meta.groups_only;
+            }),
+            Command::TogglePerfOverlay => update_global_metadata(&|meta| {
+                meta.show_perf_overlay = !meta.show_perf_overlay;
+            }),
+            Command::UpdateDb => Some(Msg::UpdateDb),
+            Command::VerifyBalances => Some(Msg::VerifyBalances),
+        }
     }
 }
 
@@ -152,7 +1177,7 @@ impl Component for App {
     type Message = Msg;
     type Properties = ();
 
-    fn create(_ctx: &Context<Self>) -> Self {
+    fn create(ctx: &Context<Self>) -> Self {
         let state = AppState::load_or_create();
         let mut metadata: NodeMetadata = LocalStorage::get(METADATA_KEY).unwrap_or_else(|e| {
             if !matches!(e, StorageError::KeyNotFound(_)) {
@@ -162,7 +1187,7 @@ impl Component for App {
         });
         // Remove metadata from deleted groups that are definitely no longer in the
         // undo/redo history.
-        metadata.prune(&state.root);
+        metadata.prune(&state.combined_root());
         let global_metadata: GlobalMetadata = LocalStorage::get(GLOBAL_METADATA_KEY)
             .unwrap_or_else(|e| {
                 if !matches!(e, StorageError::KeyNotFound(_)) {
@@ -170,16 +1195,57 @@ impl Component for App {
                 }
                 Default::default()
             });
+        if let Some(depth) = global_metadata.initial_expand_depth {
+            for (_, root) in state.tab_roots() {
+                metadata.collapse_below_depth(&root, depth);
+            }
+        }
+        let view_state: ViewState = LocalStorage::get(VIEW_STATE_KEY).unwrap_or_else(|e| {
+            if !matches!(e, StorageError::KeyNotFound(_)) {
+                warn!("Failed to load view state: {}", e);
+            }
+            Default::default()
+        });
+        let checkpoints: Vec<Checkpoint> = LocalStorage::get(CHECKPOINTS_KEY).unwrap_or_else(|e| {
+            if !matches!(e, StorageError::KeyNotFound(_)) {
+                warn!("Failed to load checkpoints: {}", e);
+            }
+            Default::default()
+        });
         Self {
             state,
             metadata,
             global_metadata,
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            dismissed_power_deficit: None,
+            view_state,
+            pending_scroll: None,
+            _scroll_listener: listen_for_scroll(ctx.link().callback(|()| Msg::SaveScrollPosition)),
+            pending_focus: None,
+            pending_highlight: Vec::new(),
+            search_query: String::new(),
+            next_search_match: 0,
+            search_match_count: None,
+            pending_search_highlight: Vec::new(),
+            _shortcut_listener: listen_for_shortcuts(
+                ctx.link().callback(|()| Msg::NextProblem),
+                ctx.link().callback(|()| Msg::ToggleCommandPalette),
+            ),
+            show_command_palette: false,
+            show_statistics: false,
+            delete_toast: None,
+            show_report: false,
+            pending_print: false,
+            baseline: None,
+            checkpoints: Rc::new(checkpoints),
+            show_checkpoints: false,
+            compact_layout: compact_layout(),
+            _resize_listener: listen_for_resize(ctx.link().callback(|()| Msg::Resized)),
         }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::ReplaceRoot { replacement } => {
                 let previous = self.state.update_root(replacement);
@@ -208,11 +1274,409 @@ impl Component for App {
                 self.save();
                 true
             }
+            Msg::UpdateGlobalMetadata { global_metadata } => {
+                if self.global_metadata != global_metadata {
+                    self.global_metadata = global_metadata;
+                    self.save();
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::DismissPowerWarning => {
+                self.dismissed_power_deficit = Some(-self.state.root.balance().power);
+                true
+            }
+            Msg::ToggleRecipeComparison => {
+                self.view_state.show_recipe_comparison = !self.view_state.show_recipe_comparison;
+                self.save_view_state();
+                true
+            }
+            Msg::ToggleRecipeReplace => {
+                self.view_state.show_recipe_replace = !self.view_state.show_recipe_replace;
+                self.save_view_state();
+                true
+            }
+            Msg::CollectGenerators => {
+                let root = match self.state.root.group() {
+                    Some(root) => root,
+                    None => {
+                        warn!("Root is not a group");
+                        return false;
+                    }
+                };
+                let (new_root, moved) =
+                    match collect_generators(root, POWER_GROUP_NAME, &self.state.database) {
+                        Some(collected) => collected,
+                        None => {
+                            alert("There are no generators outside of the power group to move.");
+                            return false;
+                        }
+                    };
+                if !confirm(&format!(
+                    "Move {} generator {} into the \"{}\" group? You can undo this.",
+                    moved,
+                    if moved == 1 { "node" } else { "nodes" },
+                    POWER_GROUP_NAME,
+                )) {
+                    return false;
+                }
+                let previous = self.state.update_root(new_root.into());
+                self.add_undo_state(previous);
+                self.save();
+                true
+            }
+            Msg::ToggleOutline => {
+                self.view_state.show_outline = !self.view_state.show_outline;
+                self.save_view_state();
+                true
+            }
+            Msg::ToggleFlatList => {
+                self.view_state.show_flat_list = !self.view_state.show_flat_list;
+                self.save_view_state();
+                true
+            }
+            Msg::SetPanelLayout { layout } => {
+                if self.view_state.panels == layout {
+                    false
+                } else {
+                    self.view_state.panels = layout;
+                    self.save_view_state();
+                    true
+                }
+            }
+            Msg::PrintReport => {
+                self.show_report = true;
+                self.pending_print = true;
+                true
+            }
+            Msg::CloseReport => {
+                self.show_report = false;
+                true
+            }
+            Msg::CopySummary => {
+                let summary = text_summary(
+                    &self.state.root,
+                    &self.state.database,
+                    &self.global_metadata,
+                );
+                clipboard::copy_text(&summary);
+                false
+            }
+            Msg::ToggleStatistics => {
+                self.show_statistics = !self.show_statistics;
+                true
+            }
+            Msg::SetBaseline => {
+                self.baseline = Some(self.state.root.clone());
+                true
+            }
+            Msg::ClearBaseline => {
+                self.baseline = None;
+                true
+            }
+            Msg::ToggleCheckpoints => {
+                self.show_checkpoints = !self.show_checkpoints;
+                true
+            }
+            Msg::SaveCheckpoint => {
+                if self.checkpoints.len() >= MAX_CHECKPOINTS {
+                    alert(&format!(
+                        "There are already {} checkpoints. Delete one to save another.",
+                        MAX_CHECKPOINTS
+                    ));
+                    return false;
+                }
+                let name = match prompt("Name of the checkpoint:", None) {
+                    Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+                    _ => return false,
+                };
+                let checkpoint = Checkpoint {
+                    name,
+                    power: self.state.root.balance().power,
+                    root: schema::store(&self.state.root),
+                };
+                let stored: usize = self.checkpoints.iter().map(Checkpoint::stored_size).sum();
+                if stored + checkpoint.stored_size() > MAX_CHECKPOINT_BYTES {
+                    alert("There isn't enough room for another checkpoint. Delete an old one to save another.");
+                    return false;
+                }
+                Rc::make_mut(&mut self.checkpoints).push(checkpoint);
+                self.save_checkpoints();
+                self.show_checkpoints = true;
+                true
+            }
+            Msg::RestoreCheckpoint { idx } => {
+                let checkpoint = match self.checkpoints.get(idx) {
+                    Some(checkpoint) => checkpoint,
+                    None => {
+                        warn!("Checkpoint {} does not exist", idx);
+                        return false;
+                    }
+                };
+                if !confirm(&format!(
+                    "Replace the factory with the checkpoint \"{}\"? You can undo this.",
+                    checkpoint.name
+                )) {
+                    return false;
+                }
+                match schema::load(checkpoint.root.clone()) {
+                    Ok(root) => {
+                        let previous = self.state.update_root(root);
+                        self.add_undo_state(previous);
+                        self.save();
+                        true
+                    }
+                    Err(e) => {
+                        warn!("Failed to load checkpoint: {}", e);
+                        alert(&format!("Unable to restore the checkpoint: {}", e));
+                        false
+                    }
+                }
+            }
+            Msg::DeleteCheckpoint { idx } => {
+                let name = match self.checkpoints.get(idx) {
+                    Some(checkpoint) => &checkpoint.name,
+                    None => {
+                        warn!("Checkpoint {} does not exist", idx);
+                        return false;
+                    }
+                };
+                if !confirm(&format!("Delete the checkpoint \"{}\"?", name)) {
+                    return false;
+                }
+                Rc::make_mut(&mut self.checkpoints).remove(idx);
+                self.save_checkpoints();
+                true
+            }
+            Msg::FocusGroup { id } => match find_group(&self.state.root, id) {
+                Some(target) => {
+                    self.focus(target);
+                    true
+                }
+                None => {
+                    warn!("Group {} is not in the tree", id);
+                    false
+                }
+            },
+            Msg::AddWatchList => match prompt("Name of the new watch list:", None) {
+                Some(name) => {
+                    self.global_metadata.watch_lists.push(WatchList {
+                        name,
+                        ..Default::default()
+                    });
+                    self.save();
+                    true
+                }
+                None => false,
+            },
+            Msg::SetInitialExpandDepth => {
+                let current = self
+                    .global_metadata
+                    .initial_expand_depth
+                    .map(|depth| depth.to_string());
+                let text = match prompt(
+                    "Levels of groups to expand on load (leave empty to expand all):",
+                    current.as_deref(),
+                ) {
+                    Some(text) => text,
+                    None => return false,
+                };
+                let depth = if text.trim().is_empty() {
+                    None
+                } else {
+                    match text.trim().parse() {
+                        Ok(depth) => Some(depth),
+                        Err(_) => {
+                            warn!("Invalid expansion depth {:?}", text);
+                            return false;
+                        }
+                    }
+                };
+                self.global_metadata.initial_expand_depth = depth;
+                self.save();
+                true
+            }
+            Msg::SetBalanceTolerance => {
+                let current = self.global_metadata.balance_tolerance().to_string();
+                let text = match prompt(
+                    "Net rates smaller than this are treated as balanced (leave empty for the default):",
+                    Some(&current),
+                ) {
+                    Some(text) => text,
+                    None => return false,
+                };
+                let tolerance = if text.trim().is_empty() {
+                    None
+                } else {
+                    match text.trim().parse::<f32>() {
+                        Ok(tolerance) if tolerance.is_finite() && tolerance >= 0.0 => {
+                            Some(tolerance)
+                        }
+                        _ => {
+                            warn!("Invalid balance tolerance {:?}", text);
+                            return false;
+                        }
+                    }
+                };
+                self.global_metadata.balance_tolerance = tolerance;
+                self.save();
+                true
+            }
+            Msg::SetDefaultClockSpeed => {
+                let current = (self.state.settings.default_clock_speed() * 100.0).to_string();
+                let text = match prompt(
+                    "Clock speed new buildings in this factory start at, in percent (leave empty for 100%):",
+                    Some(&current),
+                ) {
+                    Some(text) => text,
+                    None => return false,
+                };
+                let clock_speed = if text.trim().is_empty() {
+                    None
+                } else {
+                    match text.trim().trim_end_matches('%').trim().parse::<f32>() {
+                        Ok(percent) if (1.0..=250.0).contains(&percent) => Some(percent / 100.0),
+                        _ => {
+                            warn!("Invalid default clock speed {:?}", text);
+                            return false;
+                        }
+                    }
+                };
+                self.update_document_settings(DocumentSettings {
+                    default_clock_speed: clock_speed,
+                    ..self.state.settings.clone()
+                })
+            }
+            Msg::CycleDocumentReferenceBelt => self.update_document_settings(DocumentSettings {
+                reference_belt: BeltTier::next(self.state.settings.reference_belt),
+                ..self.state.settings.clone()
+            }),
+            Msg::ToggleAlternateRecipes => self.update_document_settings(DocumentSettings {
+                hide_alternate_recipes: !self.state.settings.hide_alternate_recipes,
+                ..self.state.settings.clone()
+            }),
+            Msg::NextProblem => {
+                let mut problems = find_problems(&self.state.root, &self.global_metadata);
+                if problems.is_empty() {
+                    warn!("No problems to jump to");
+                    return false;
+                }
+                let idx = self.view_state.next_problem % problems.len();
+                self.view_state.next_problem = idx + 1;
+                self.focus(problems.swap_remove(idx));
+                true
+            }
+            Msg::SetSearchQuery { query } => {
+                self.search_query = query;
+                self.next_search_match = 0;
+                self.search_match_count = None;
+                true
+            }
+            Msg::NextSearchMatch => {
+                let query = self.search_query.trim().to_lowercase();
+                if query.is_empty() {
+                    return false;
+                }
+                let mut matches =
+                    find_search_matches(&self.state.root, &self.state.database, &query);
+                self.search_match_count = Some(matches.len());
+                if matches.is_empty() {
+                    return true;
+                }
+                self.pending_search_highlight =
+                    matches.iter().map(|target| target.path.clone()).collect();
+                // Expand the ancestors of every match, not just the one scrolled to, so
+                // they can all be seen.
+                let ancestors = matches
+                    .iter()
+                    .flat_map(|target| target.ancestors.iter().copied())
+                    .collect();
+                let idx = self.next_search_match % matches.len();
+                self.next_search_match = idx + 1;
+                let target = matches.swap_remove(idx);
+                self.focus(FocusTarget {
+                    path: target.path,
+                    ancestors,
+                });
+                true
+            }
+            Msg::SaveScrollPosition => {
+                self.view_state.scroll_y = scroll_position();
+                self.save_view_state();
+                false
+            }
+            Msg::Resized => {
+                let compact_layout = compact_layout();
+                let changed = compact_layout != self.compact_layout;
+                self.compact_layout = compact_layout;
+                changed
+            }
+            Msg::ToggleCommandPalette => {
+                self.show_command_palette = !self.show_command_palette;
+                true
+            }
+            Msg::CloseCommandPalette => mem::take(&mut self.show_command_palette),
+            Msg::RunCommand { command } => {
+                self.show_command_palette = false;
+                match self.command_msg(command) {
+                    Some(msg) => {
+                        self.update(ctx, msg);
+                    }
+                    None => warn!("Unable to run command {:?}", command),
+                }
+                true
+            }
+            Msg::DeleteNode { path } => {
+                let (new_root, removed) = match remove_child(&self.state.root, &path) {
+                    Some(removed) => removed,
+                    None => {
+                        warn!("Unable to delete node at {:?}", path);
+                        return false;
+                    }
+                };
+                let (quick, fallback) = match removed.kind() {
+                    NodeKind::Group(group) => (group.children.is_empty(), "Unnamed Group"),
+                    NodeKind::Building(_) => (true, "Unassigned Building"),
+                };
+                let mut label = node_label(&removed, &self.state.database);
+                if label.is_empty() {
+                    label = fallback.to_string();
+                }
+                if !quick && !confirm(&format!("Delete \"{}\" and everything in it?", label)) {
+                    return false;
+                }
+                let previous = self.state.update_root(new_root);
+                self.add_undo_state(previous);
+                self.save();
+                if quick {
+                    let link = ctx.link().clone();
+                    self.delete_toast = Some(DeleteToast {
+                        label,
+                        _dismiss: Timeout::new(DELETE_TOAST_DURATION, move || {
+                            link.send_message(Msg::DismissDeleteToast)
+                        }),
+                    });
+                }
+                true
+            }
+            Msg::UndoDelete => {
+                if self.delete_toast.take().is_some() {
+                    self.update(ctx, Msg::Undo);
+                    true
+                } else {
+                    false
+                }
+            }
+            Msg::DismissDeleteToast => self.delete_toast.take().is_some(),
             Msg::Undo => match self.undo_stack.pop() {
                 Some(previous) => {
+                    self.delete_toast = None;
                     let next = mem::replace(&mut self.state, previous);
+                    self.pending_highlight = changed_paths(&next.root, &self.state.root);
                     self.redo_stack.push(next);
                     self.save();
+                    self.pending_scroll = Some(scroll_position());
                     true
                 }
                 None => {
@@ -222,9 +1686,12 @@ impl Component for App {
             },
             Msg::Redo => match self.redo_stack.pop() {
                 Some(next) => {
+                    self.delete_toast = None;
                     let previous = mem::replace(&mut self.state, next);
+                    self.pending_highlight = changed_paths(&previous.root, &self.state.root);
                     self.undo_stack.push(previous);
                     self.save();
+                    self.pending_scroll = Some(scroll_position());
                     true
                 }
                 None => {
@@ -232,11 +1699,207 @@ impl Component for App {
                     false
                 }
             },
+            Msg::SelectTab { idx } => {
+                if idx == self.state.active_tab {
+                    return false;
+                }
+                // Switching isn't an edit, so it is saved but not added to the undo
+                // history. Undoing past it switches back along with the undone change.
+                self.state.switch_tab(idx);
+                self.pending_scroll = Some(0.0);
+                self.save();
+                true
+            }
+            Msg::AddTab => {
+                let previous = self.state.clone();
+                let name = format!("Factory {}", self.state.tabs.len() + 1);
+                self.state.tabs.push(FactoryTab {
+                    name,
+                    root: Group::empty().into(),
+                });
+                self.state.switch_tab(self.state.tabs.len() - 1);
+                self.add_undo_state(previous);
+                self.save();
+                true
+            }
+            Msg::RenameTab { idx } => {
+                let current = match self.state.tabs.get(idx) {
+                    Some(tab) => tab.name.clone(),
+                    None => {
+                        warn!("Tab {} does not exist", idx);
+                        return false;
+                    }
+                };
+                let name = match prompt("Name of the tab:", Some(&current)) {
+                    Some(name) if name.trim() != current => name.trim().to_string(),
+                    _ => return false,
+                };
+                let previous = self.state.clone();
+                self.state.tabs[idx].name = name;
+                self.add_undo_state(previous);
+                self.save();
+                true
+            }
+            Msg::MoveTab { from, to } => {
+                let len = self.state.tabs.len();
+                if from == to || from >= len || to >= len {
+                    return false;
+                }
+                let previous = self.state.clone();
+                let active = self.state.active_tab;
+                let tab = self.state.tabs.remove(from);
+                self.state.tabs.insert(to, tab);
+                // Keep the same tab active as the others shift around it.
+                self.state.active_tab = if active == from {
+                    to
+                } else if from < active && active <= to {
+                    active - 1
+                } else if to <= active && active < from {
+                    active + 1
+                } else {
+                    active
+                };
+                self.add_undo_state(previous);
+                self.save();
+                true
+            }
+            Msg::CloseTab { idx } => {
+                if self.state.tabs.len() <= 1 || idx >= self.state.tabs.len() {
+                    warn!("Cannot close tab {}", idx);
+                    return false;
+                }
+                let roots = self.state.tab_roots();
+                let (name, root) = &roots[idx];
+                let empty = root.group().is_none_or(|group| group.children.is_empty());
+                if !empty
+                    && !confirm(&format!(
+                        "Close the tab \"{}\" and delete its factory? You can undo this.",
+                        name
+                    ))
+                {
+                    return false;
+                }
+                let previous = self.state.clone();
+                let active = self.state.active_tab;
+                if idx == active {
+                    // Move to a neighbouring tab first so the closed tab's root isn't the
+                    // one being edited.
+                    self.state.switch_tab(if idx + 1 < roots.len() {
+                        idx + 1
+                    } else {
+                        idx - 1
+                    });
+                }
+                self.state.tabs.remove(idx);
+                if idx < self.state.active_tab {
+                    self.state.active_tab -= 1;
+                }
+                self.add_undo_state(previous);
+                self.save();
+                true
+            }
+            Msg::ToggleCombinedSummary => {
+                self.view_state.show_combined_summary = !self.view_state.show_combined_summary;
+                self.save_view_state();
+                true
+            }
+            Msg::VerifyBalances => {
+                let db = &self.state.database;
+                let root = &self.state.root;
+                let fresh = root.rebuild(db);
+                let stale = balance_mismatches(root, &fresh);
+                let cached = BalanceCache::new(BALANCE_CACHE_CAPACITY).rebuild(root, db);
+                let wrong_in_cache = balance_mismatches(&cached, &fresh);
+                if stale.is_empty() && wrong_in_cache.is_empty() {
+                    alert(&format!(
+                        "All {} balances match a full recomputation.",
+                        fresh.iter().count()
+                    ));
+                    return false;
+                }
+                let describe = |paths: &[Vec<usize>]| {
+                    paths
+                        .iter()
+                        .take(MAX_REPORTED_MISMATCHES)
+                        .map(|path| {
+                            let node = path.iter().try_fold(root.clone(), |node, &idx| {
+                                node.group()?.get_child(idx).cloned()
+                            });
+                            let label = node.map(|node| node_label(&node, db)).unwrap_or_default();
+                            if label.is_empty() {
+                                "- (unnamed)".to_string()
+                            } else {
+                                format!("- {}", label)
+                            }
+                        })
+                        .chain((paths.len() > MAX_REPORTED_MISMATCHES).then(|| {
+                            format!("- and {} more", paths.len() - MAX_REPORTED_MISMATCHES)
+                        }))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                let mut report = String::new();
+                if !stale.is_empty() {
+                    report.push_str(&format!(
+                        "{} node(s) in the tree differ from a full recomputation:\n{}\n\n",
+                        stale.len(),
+                        describe(&stale)
+                    ));
+                }
+                if !wrong_in_cache.is_empty() {
+                    report.push_str(&format!(
+                        "{} node(s) rebuilt from the balance cache differ from a full \
+                        recomputation:\n{}\n\n",
+                        wrong_in_cache.len(),
+                        describe(&wrong_in_cache)
+                    ));
+                }
+                let report = report.trim_end();
+                warn!("{}", report);
+                alert(report);
+                if !stale.is_empty() {
+                    self.pending_highlight = stale;
+                    true
+                } else {
+                    false
+                }
+            }
             Msg::UpdateDb => {
                 let mut new_state = self.state.clone();
                 new_state.database = Rc::new(Database::load_default());
                 new_state.database_outdated = false;
-                new_state.root = self.state.root.rebuild(&*new_state.database);
+                // Edits only rebuild the nodes they touch, so the database changing is the
+                // only time the whole tree is rebuilt. The cache lets identical subtrees in
+                // any tab share one computation, and is dropped afterwards since its
+                // balances are only valid until the next database change.
+                let mut cache = BalanceCache::new(BALANCE_CACHE_CAPACITY);
+                let rebuilt: Vec<_> = self
+                    .state
+                    .tab_roots()
+                    .into_iter()
+                    .map(|(_, root)| cache.rebuild(&root, &new_state.database))
+                    .collect();
+                new_state.set_tab_roots(rebuilt);
+                let unknown = validation::find_unknown_ids(&new_state.combined_root());
+                if !unknown.is_empty() {
+                    let summary = unknown
+                        .iter()
+                        .map(|(id, count)| format!("{}: {} node(s)", id, count))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    if confirm(&format!(
+                        "Some parts of your factory aren't in the new database:\n\n{}\n\n\
+                        Press OK to remove the affected nodes, or Cancel to keep them.",
+                        summary
+                    )) {
+                        let cleaned: Vec<_> = new_state
+                            .tab_roots()
+                            .into_iter()
+                            .map(|(_, root)| validation::remove_unknown(&root))
+                            .collect();
+                        new_state.set_tab_roots(cleaned);
+                    }
+                }
                 let previous = mem::replace(&mut self.state, new_state);
                 self.add_undo_state(previous);
                 self.save();
@@ -246,6 +1909,10 @@ impl Component for App {
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
+        if self.show_report {
+            return self.view_report(ctx);
+        }
+        perf_overlay::start_render();
         let link = ctx.link();
         let replace = link.callback(|(idx, replacement)| {
             assert!(idx == 0, "Attempting to replace index {} at the root", idx);
@@ -253,71 +1920,769 @@ impl Component for App {
         });
         let set_metadata = link.callback(|(id, meta)| Msg::UpdateMetadata { id, meta });
         let batch_set_metadata = link.callback(|updates| Msg::BatchUpdateMetadata { updates });
+        let set_global_metadata =
+            link.callback(|global_metadata| Msg::UpdateGlobalMetadata { global_metadata });
         let undo = link.callback(|_| Msg::Undo);
         let redo = link.callback(|_| Msg::Redo);
         let update_db = link.callback(|_| Msg::UpdateDb);
         let move_node =
             Callback::from(|_| warn!("Root node tried to ask parent to move one of its children"));
+        let delete_node = link.callback(|path| Msg::DeleteNode { path });
 
         let hide_empty_balances = self.global_metadata.hide_empty_balances;
         let toggle_empty_balances = link.callback(move |_| Msg::ToggleEmptyBalances {
             hide_empty_balances: !hide_empty_balances,
         });
-        let hidden_balances = hide_empty_balances.then(|| "hide-empty-balances");
+        let show_buildable_counts = self.global_metadata.show_buildable_counts;
+        let toggle_buildable_counts = {
+            let global_metadata = GlobalMetadata {
+                show_buildable_counts: !show_buildable_counts,
+                ..self.global_metadata.clone()
+            };
+            link.callback(move |_| Msg::UpdateGlobalMetadata {
+                global_metadata: global_metadata.clone(),
+            })
+        };
+        let group_balance_by_category = self.global_metadata.group_balance_by_category;
+        let toggle_balance_categories = {
+            let global_metadata = GlobalMetadata {
+                group_balance_by_category: !group_balance_by_category,
+                ..self.global_metadata.clone()
+            };
+            link.callback(move |_| Msg::UpdateGlobalMetadata {
+                global_metadata: global_metadata.clone(),
+            })
+        };
+        let groups_only = self.global_metadata.groups_only;
+        let toggle_groups_only = {
+            let global_metadata = GlobalMetadata {
+                groups_only: !groups_only,
+                ..self.global_metadata.clone()
+            };
+            link.callback(move |_| Msg::UpdateGlobalMetadata {
+                global_metadata: global_metadata.clone(),
+            })
+        };
+        let power_deficit = -self.state.root.balance().power;
+        let combined_root = self.state.combined_root();
+        // The grand total is only worth showing once there is more than one tab.
+        let show_combined_summary =
+            self.view_state.show_combined_summary && self.state.tabs.len() > 1;
+        let show_power_warning = power_deficit
+            > self
+                .dismissed_power_deficit
+                .unwrap_or(0.0)
+                .max(self.global_metadata.balance_tolerance());
+        let dismiss_power_warning = link.callback(|_| Msg::DismissPowerWarning);
+        let toggle_recipe_comparison = link.callback(|_| Msg::ToggleRecipeComparison);
+        let close_recipe_comparison = link.callback(|()| Msg::ToggleRecipeComparison);
+        let toggle_recipe_replace = link.callback(|_| Msg::ToggleRecipeReplace);
+        let close_recipe_replace = link.callback(|()| Msg::ToggleRecipeReplace);
+        let toggle_outline = link.callback(|_| Msg::ToggleOutline);
+        let close_outline = link.callback(|()| Msg::ToggleOutline);
+        let focus_group = link.callback(|id| Msg::FocusGroup { id });
+        let set_panel_layout = link.callback(|layout| Msg::SetPanelLayout { layout });
+        let mut left_panels = Vec::new();
+        if self.view_state.show_outline {
+            left_panels.push(Panel {
+                id: PanelId::Outline,
+                title: "Outline",
+                icon: "account_tree",
+                content: html! {
+                    <Outline root={self.state.root.clone()} focus={focus_group} />
+                },
+                close: Some(close_outline),
+            });
+        }
+        let mut right_panels = Vec::new();
+        if !self.global_metadata.watch_lists.is_empty() {
+            right_panels.push(Panel {
+                id: PanelId::WatchLists,
+                title: "Watch Lists",
+                icon: "visibility",
+                content: html! {
+                    <WatchLists root={self.state.root.clone()}
+                        set_global_metadata={set_global_metadata.clone()} />
+                },
+                close: None,
+            });
+        }
+        let next_problem = link.callback(|_| Msg::NextProblem);
+        let search_oninput = link.callback(|input| Msg::SetSearchQuery {
+            query: get_value_from_input_event(input),
+        });
+        let search_onkeyup = link.batch_callback(|e: KeyboardEvent| match &*e.key() {
+            "Esc" | "Escape" => Some(Msg::SetSearchQuery {
+                query: String::new(),
+            }),
+            _ => None,
+        });
+        let search_onsubmit = link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::NextSearchMatch
+        });
+        let search_status = match self.search_match_count {
+            Some(0) => Some("No matches".to_string()),
+            Some(count) => Some(format!("{}/{}", self.next_search_match, count)),
+            None => None,
+        };
+        let add_watch_list = link.callback(|_| Msg::AddWatchList);
+        let power_unit = self.global_metadata.power_unit;
+        let next_power_unit = {
+            let global_metadata = GlobalMetadata {
+                power_unit: power_unit.next(),
+                ..self.global_metadata.clone()
+            };
+            link.callback(move |_| Msg::UpdateGlobalMetadata {
+                global_metadata: global_metadata.clone(),
+            })
+        };
+        let rate_unit = self.global_metadata.rate_unit;
+        let next_rate_unit = {
+            let global_metadata = GlobalMetadata {
+                rate_unit: rate_unit.next(),
+                ..self.global_metadata.clone()
+            };
+            link.callback(move |_| Msg::UpdateGlobalMetadata {
+                global_metadata: global_metadata.clone(),
+            })
+        };
+        let reference_belt = self.global_metadata.reference_belt;
+        let reference_belt_title = match self.state.settings.reference_belt {
+            Some(belt) => format!(
+                "Item Rates: Plain Rates or Percentage of a Belt (this factory uses {})",
+                belt.name()
+            ),
+            None => "Item Rates: Plain Rates or Percentage of a Belt".to_string(),
+        };
+        let next_reference_belt = {
+            let global_metadata = GlobalMetadata {
+                reference_belt: BeltTier::next(reference_belt),
+                ..self.global_metadata.clone()
+            };
+            link.callback(move |_| Msg::UpdateGlobalMetadata {
+                global_metadata: global_metadata.clone(),
+            })
+        };
+        let has_deficits = self.has_deficits();
+        let run_command = link.callback(|command| Msg::RunCommand { command });
+        let close_command_palette = link.callback(|()| Msg::CloseCommandPalette);
         html! {
             <ContextProvider<Rc<Database>> context={Rc::clone(&self.state.database)}>
                 <ContextProvider<NodeMetadata> context={self.metadata.clone()}>
-                    <div class="App">
-                        <div class="navbar">
-                            <div class="appheader">{"SATISFACTORY ACCOUNTING"}</div>
-                        </div>
-                        <div class="menubar">
-                            <span class="section">
-                                <button class="unredo" title="Undo"
-                                    onclick={undo}
-                                    disabled={self.undo_stack.is_empty()}>
-                                    <span class="material-icons">{"undo"}</span>
-                                </button>
-                                <button class="unredo" title="Redo"
-                                    onclick={redo}
-                                    disabled={self.redo_stack.is_empty()}>
-                                    <span class="material-icons">{"redo"}</span>
-                                </button>
-                                <label class="empty-balance-toggle" title="Show/Hide Zero Balances">
-                                    <input type="checkbox" checked={hide_empty_balances}
-                                        onchange={toggle_empty_balances} />
-                                    <span class="material-icons">{"exposure_zero"}</span>
-                                    if hide_empty_balances {
-                                        <span class="material-icons">{"visibility_off"}</span>
+                    <ContextProvider<GlobalMetadata> context={self.global_metadata.clone()}>
+                        <ContextProvider<CompactLayout> context={self.compact_layout}>
+                            <ContextProvider<DocumentSettings> context={self.state.settings.clone()}>
+                                <div class={classes!("App", self.compact_layout.0.then(|| "compact"))}>
+                                    <div class="navbar">
+                                        <div class="appheader">{"SATISFACTORY ACCOUNTING"}</div>
+                                    </div>
+                                    <div class="menubar">
+                                        <span class="section">
+                                            <button class="unredo" title="Undo"
+                                                onclick={undo}
+                                                disabled={self.undo_stack.is_empty()}>
+                                                <span class="material-icons">{"undo"}</span>
+                                            </button>
+                                            <button class="unredo" title="Redo"
+                                                onclick={redo}
+                                                disabled={self.redo_stack.is_empty()}>
+                                                <span class="material-icons">{"redo"}</span>
+                                            </button>
+                                            <label class="empty-balance-toggle" title="Show/Hide Zero Balances">
+                                                <input type="checkbox" checked={hide_empty_balances}
+                                                    onchange={toggle_empty_balances} />
+                                                <span class="material-icons">{"exposure_zero"}</span>
+                                                if hide_empty_balances {
+                                                    <span class="material-icons">{"visibility_off"}</span>
+                                                } else {
+                                                    <span class="material-icons">{"visibility"}</span>
+                                                }
+                                            </label>
+                                            <label class="buildable-count-toggle"
+                                                title="Show/Hide Whole Building Counts">
+                                                <input type="checkbox" checked={show_buildable_counts}
+                                                    onchange={toggle_buildable_counts} />
+                                                <span class="material-icons">{"foundation"}</span>
+                                                if show_buildable_counts {
+                                                    <span class="material-icons">{"visibility"}</span>
+                                                } else {
+                                                    <span class="material-icons">{"visibility_off"}</span>
+                                                }
+                                            </label>
+                                            <label class="balance-category-toggle"
+                                                title="Group/Ungroup Balance by Category">
+                                                <input type="checkbox" checked={group_balance_by_category}
+                                                    onchange={toggle_balance_categories} />
+                                                <span class="material-icons">{"category"}</span>
+                                            </label>
+                                            <label class="groups-only-toggle"
+                                                title="Show Only Groups, Hiding Buildings">
+                                                <input type="checkbox" checked={groups_only}
+                                                    onchange={toggle_groups_only} />
+                                                <span class="material-icons">{"folder"}</span>
+                                                if groups_only {
+                                                    <span class="material-icons">{"visibility_off"}</span>
+                                                }
+                                            </label>
+                                            <form class="node-search" onsubmit={search_onsubmit}
+                                                title="Find groups by name, or buildings by recipe or item (Enter for next match)">
+                                                <span class="material-icons">{"search"}</span>
+                                                <input type="search" placeholder="Find"
+                                                    value={self.search_query.clone()}
+                                                    oninput={search_oninput} onkeyup={search_onkeyup} />
+                                                if let Some(status) = search_status {
+                                                    <span class="search-status">{status}</span>
+                                                }
+                                            </form>
+                                            <button class="next-problem"
+                                                title="Jump to Next Deficit (Alt+N)"
+                                                onclick={next_problem} disabled={!has_deficits}>
+                                                <span class="material-icons">{"report_problem"}</span>
+                                            </button>
+                                            <button class="power-unit" title="Power Unit"
+                                                onclick={next_power_unit}>
+                                                <span class="material-icons">{"bolt"}</span>
+                                                <span>{power_unit.name()}</span>
+                                            </button>
+                                            <button class="rate-unit" title="Time Base of Item Rates"
+                                                onclick={next_rate_unit}>
+                                                <span class="material-icons">{"schedule"}</span>
+                                                <span>{rate_unit.name()}</span>
+                                            </button>
+                                            <button class={classes!("reference-belt",
+                                                    reference_belt.is_some().then(|| "active"))}
+                                                title={reference_belt_title}
+                                                onclick={next_reference_belt}>
+                                                <span class="material-icons">{"linear_scale"}</span>
+                                                <span>{reference_belt.map_or(rate_unit.name(), BeltTier::name)}</span>
+                                            </button>
+                                            <button class="open-command-palette"
+                                                title="Command Palette (Ctrl+K)"
+                                                onclick={link.callback(|_| Msg::ToggleCommandPalette)}>
+                                                <span class="material-icons">{"keyboard_command_key"}</span>
+                                            </button>
+                                            <button class="add-watch-list" title="Add Watch List"
+                                                onclick={add_watch_list}>
+                                                <span class="material-icons">{"playlist_add"}</span>
+                                            </button>
+                                            <button class="show-outline" title="Outline"
+                                                onclick={toggle_outline}>
+                                                <span class="material-icons">{"account_tree"}</span>
+                                            </button>
+                                            <button class="set-expand-depth"
+                                                title="Levels of Groups Expanded on Load"
+                                                onclick={link.callback(|_| Msg::SetInitialExpandDepth)}>
+                                                <span class="material-icons">{"unfold_less"}</span>
+                                                if let Some(depth) = self.global_metadata.initial_expand_depth {
+                                                    <span>{depth}</span>
+                                                }
+                                            </button>
+                                            <button class={classes!("show-flat-list",
+                                                    self.view_state.show_flat_list.then(|| "active"))}
+                                                title="List All Buildings"
+                                                onclick={link.callback(|_| Msg::ToggleFlatList)}>
+                                                <span class="material-icons">{"view_list"}</span>
+                                            </button>
+                                            <button class="print-report" title="Print Report"
+                                                onclick={link.callback(|_| Msg::PrintReport)}>
+                                                <span class="material-icons">{"print"}</span>
+                                            </button>
+                                            <button class="copy-summary" title="Copy Summary to Clipboard"
+                                                onclick={link.callback(|_| Msg::CopySummary)}>
+                                                <span class="material-icons">{"content_copy"}</span>
+                                            </button>
+                                            <button class="show-statistics" title="Statistics"
+                                                onclick={link.callback(|_| Msg::ToggleStatistics)}>
+                                                <span class="material-icons">{"insights"}</span>
+                                            </button>
+                                            <button class="compare-recipes" title="Compare Recipes"
+                                                onclick={toggle_recipe_comparison}>
+                                                <span class="material-icons">{"compare_arrows"}</span>
+                                            </button>
+                                            <button class="replace-recipe" title="Replace Recipe Everywhere"
+                                                onclick={toggle_recipe_replace}>
+                                                <span class="material-icons">{"find_replace"}</span>
+                                            </button>
+                                            <button class="set-baseline"
+                                                title="Set Baseline (show changes from the current factory)"
+                                                onclick={link.callback(|_| Msg::SetBaseline)}>
+                                                <span class="material-icons">{"flag"}</span>
+                                            </button>
+                                            if self.baseline.is_some() {
+                                                <button class="clear-baseline" title="Clear Baseline"
+                                                    onclick={link.callback(|_| Msg::ClearBaseline)}>
+                                                    <span class="material-icons">{"outlined_flag"}</span>
+                                                </button>
+                                            }
+                                            <button class="show-checkpoints" title="Checkpoints"
+                                                onclick={link.callback(|_| Msg::ToggleCheckpoints)}>
+                                                <span class="material-icons">{"bookmarks"}</span>
+                                            </button>
+                                            if self.state.database_outdated {
+                                                <button class="update-db" onclick={update_db}
+                                                    title="Update the database of structures and recipes. This could break existing buildings (but you *can* undo this).">
+                                                    <span class="material-icons">
+                                                        {"browser_updated"}
+                                                    </span>
+                                                </button>
+                                            }
+                                        </span>
+                                        <a class="bug-report" target="_blank"
+                                            href="https://github.com/satisfactory-accounting/satisfactory-accounting/issues">
+                                            <span class="material-icons">
+                                                {"bug_report"}
+                                            </span>
+                                        </a>
+                                    </div>
+                                    if self.show_command_palette {
+                                        <CommandPalette commands={self.available_commands()}
+                                            run={run_command} close={close_command_palette} />
+                                    }
+                                    if self.show_statistics {
+                                        <Statistics root={self.state.root.clone()}
+                                            close={link.callback(|()| Msg::ToggleStatistics)} />
+                                    }
+                                    if self.show_checkpoints {
+                                        <Checkpoints checkpoints={Rc::clone(&self.checkpoints)}
+                                            save={link.callback(|()| Msg::SaveCheckpoint)}
+                                            restore={link.callback(|idx| Msg::RestoreCheckpoint { idx })}
+                                            delete={link.callback(|idx| Msg::DeleteCheckpoint { idx })}
+                                            close={link.callback(|()| Msg::ToggleCheckpoints)} />
+                                    }
+                                    <TabBar tabs={self.state.tab_roots()}
+                                        active={self.state.active_tab}
+                                        combined={combined_root.clone()}
+                                        show_combined={show_combined_summary}
+                                        select={link.callback(|idx| Msg::SelectTab { idx })}
+                                        add={link.callback(|()| Msg::AddTab)}
+                                        rename={link.callback(|idx| Msg::RenameTab { idx })}
+                                        move_tab={link.callback(|(from, to)| Msg::MoveTab { from, to })}
+                                        close={link.callback(|idx| Msg::CloseTab { idx })}
+                                        toggle_combined={link.callback(|()| Msg::ToggleCombinedSummary)} />
+                                    if show_combined_summary {
+                                        <RootSummary root={combined_root} />
                                     } else {
-                                        <span class="material-icons">{"visibility"}</span>
+                                        <RootSummary root={self.state.root.clone()}
+                                            baseline={self.baseline.clone()}
+                                            set_global_metadata={set_global_metadata.clone()} />
                                     }
-                                </label>
-                                if self.state.database_outdated {
-                                    <button class="update-db" onclick={update_db}
-                                        title="Update the database of structures and recipes. This could break existing buildings (but you *can* undo this).">
-                                        <span class="material-icons">
-                                            {"browser_updated"}
-                                        </span>
-                                    </button>
-                                }
-                            </span>
-                            <a class="bug-report" target="_blank"
-                                href="https://github.com/satisfactory-accounting/satisfactory-accounting/issues">
-                                <span class="material-icons">
-                                    {"bug_report"}
-                                </span>
-                            </a>
-                        </div>
-                        <div class={classes!("appbody", hidden_balances)}>
-                            <NodeDisplay node={self.state.root.clone()}
-                                path={Vec::new()}
-                                {replace} {set_metadata} {batch_set_metadata}
-                                {move_node} />
-                        </div>
-                    </div>
+                                    if show_power_warning {
+                                        <div class="power-warning">
+                                            <span class="material-icons">{"power_off"}</span>
+                                            <span class="message">
+                                                {format!("Power deficit of {}", power_unit.format(power_deficit))}
+                                            </span>
+                                            <button class="dismiss" title="Dismiss"
+                                                onclick={dismiss_power_warning}>
+                                                <span class="material-icons">{"close"}</span>
+                                            </button>
+                                        </div>
+                                    }
+                                    if self.view_state.show_recipe_comparison {
+                                        <RecipeComparison close={close_recipe_comparison} />
+                                    }
+                                    if self.view_state.show_recipe_replace {
+                                        <RecipeReplace root={self.state.root.clone()}
+                                            replace_root={link.callback(|replacement| Msg::ReplaceRoot { replacement })}
+                                            close={close_recipe_replace} />
+                                    }
+                                    <div class="workspace">
+                                        <Dock side={DockSide::Left} panels={left_panels}
+                                            layout={self.view_state.panels.clone()}
+                                            set_layout={set_panel_layout.clone()} />
+                                        <div class="appbody">
+                                            if self.view_state.show_flat_list {
+                                                <FlatList root={self.state.root.clone()}
+                                                    replace_root={link.callback(|replacement| Msg::ReplaceRoot { replacement })}
+                                                    {delete_node} {set_metadata} {batch_set_metadata}
+                                                    {set_global_metadata} />
+                                            } else {
+                                                <NodeDisplay node={self.state.root.clone()}
+                                                    path={Vec::new()}
+                                                    {replace} {delete_node} {set_metadata} {batch_set_metadata}
+                                                    {set_global_metadata} {move_node} />
+                                            }
+                                        </div>
+                                        <Dock side={DockSide::Right} panels={right_panels}
+                                            layout={self.view_state.panels.clone()}
+                                            set_layout={set_panel_layout} />
+                                    </div>
+                                    if let Some(toast) = &self.delete_toast {
+                                        <div class="delete-toast" role="status">
+                                            <span class="message">
+                                                {format!("Deleted {} \u{2014}", toast.label)}
+                                            </span>
+                                            <button class="undo-delete"
+                                                onclick={link.callback(|_| Msg::UndoDelete)}>
+                                                {"Undo"}
+                                            </button>
+                                        </div>
+                                    }
+                                    if self.global_metadata.show_perf_overlay {
+                                        <PerfOverlay close={link.callback(|()| Msg::RunCommand {
+                                            command: Command::TogglePerfOverlay,
+                                        })} />
+                                    }
+                                </div>
+                            </ContextProvider<DocumentSettings>>
+                        </ContextProvider<CompactLayout>>
+                    </ContextProvider<GlobalMetadata>>
                 </ContextProvider<NodeMetadata>>
             </ContextProvider<Rc<Database>>>
         }
     }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        perf_overlay::finish_render();
+        if first_render {
+            set_scroll_position(self.view_state.scroll_y);
+        }
+        if let Some(y) = self.pending_scroll.take() {
+            set_scroll_position(y);
+        }
+        if let Some(path) = self.pending_focus.take() {
+            focus_node(&path);
+        }
+        let changed = mem::take(&mut self.pending_highlight);
+        if !changed.is_empty() {
+            highlight_nodes(&changed, "changed", true);
+        }
+        let matches = mem::take(&mut self.pending_search_highlight);
+        if !matches.is_empty() {
+            highlight_nodes(&matches, "search-match", false);
+        }
+        if mem::take(&mut self.pending_print) {
+            if let Some(window) = web_sys::window() {
+                if let Err(e) = window.print() {
+                    warn!("Unable to open the print dialog: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+/// A node to focus, such as a building which consumes an item that is in deficit in the
+/// overall balance.
+struct FocusTarget {
+    /// Path to the node.
+    path: Vec<usize>,
+    /// Ids of the groups containing the node, which must be expanded to show it.
+    ancestors: Vec<Uuid>,
+}
+
+/// Find the path to the group with the given id, and the ids of its ancestors.
+fn find_group(root: &Node, id: Uuid) -> Option<FocusTarget> {
+    fn search(node: &Node, id: Uuid, path: &mut Vec<usize>, ancestors: &mut Vec<Uuid>) -> bool {
+        let group = match node.group() {
+            Some(group) => group,
+            None => return false,
+        };
+        if group.id == id {
+            return true;
+        }
+        ancestors.push(group.id);
+        for (idx, child) in group.children.iter().enumerate() {
+            path.push(idx);
+            if search(child, id, path, ancestors) {
+                return true;
+            }
+            path.pop();
+        }
+        ancestors.pop();
+        false
+    }
+    let mut path = Vec::new();
+    let mut ancestors = Vec::new();
+    if search(root, id, &mut path, &mut ancestors) {
+        Some(FocusTarget { path, ancestors })
+    } else {
+        None
+    }
+}
+
+/// Find the paths of the nodes in `after` which differ from `before`. Groups are matched
+/// by id and buildings by their position among their siblings. A group whose children were
+/// added, removed, or reordered is reported rather than its children, as is a group whose
+/// own settings changed without any of its children changing.
+fn changed_paths(before: &Node, after: &Node) -> Vec<Vec<usize>> {
+    fn visit(before: &Node, after: &Node, path: &mut Vec<usize>, changed: &mut Vec<Vec<usize>>) {
+        if before == after {
+            return;
+        }
+        let (before, after) = match (before.group(), after.group()) {
+            (Some(before), Some(after)) if before.id == after.id => (before, after),
+            _ => {
+                changed.push(path.clone());
+                return;
+            }
+        };
+        let same_children = before.children.len() == after.children.len()
+            && before.children.iter().zip(&after.children).all(|(b, a)| {
+                match (b.group(), a.group()) {
+                    (Some(b), Some(a)) => b.id == a.id,
+                    (None, None) => true,
+                    _ => false,
+                }
+            });
+        if !same_children {
+            changed.push(path.clone());
+            return;
+        }
+        let found = changed.len();
+        for (idx, (b, a)) in before.children.iter().zip(&after.children).enumerate() {
+            path.push(idx);
+            visit(b, a, path, changed);
+            path.pop();
+        }
+        if changed.len() == found {
+            changed.push(path.clone());
+        }
+    }
+    let mut changed = Vec::new();
+    visit(before, after, &mut Vec::new(), &mut changed);
+    changed
+}
+
+/// Find all nodes below the root which match a search for the given lowercase text, in
+/// display order.
+fn find_search_matches(root: &Node, db: &Database, query: &str) -> Vec<FocusTarget> {
+    fn collect(
+        node: &Node,
+        db: &Database,
+        query: &str,
+        path: &mut Vec<usize>,
+        ancestors: &mut Vec<Uuid>,
+        matches: &mut Vec<FocusTarget>,
+    ) {
+        if !path.is_empty() && matches_search(node, query, db) {
+            matches.push(FocusTarget {
+                path: path.clone(),
+                ancestors: ancestors.clone(),
+            });
+        }
+        if let Some(group) = node.group() {
+            ancestors.push(group.id);
+            for (idx, child) in group.children.iter().enumerate() {
+                path.push(idx);
+                collect(child, db, query, path, ancestors, matches);
+                path.pop();
+            }
+            ancestors.pop();
+        }
+    }
+    let mut matches = Vec::new();
+    collect(
+        root,
+        db,
+        query,
+        &mut Vec::new(),
+        &mut Vec::new(),
+        &mut matches,
+    );
+    matches
+}
+
+/// Find all buildings which consume an item that is in deficit overall, in display
+/// order.
+fn find_problems(root: &Node, global_metadata: &GlobalMetadata) -> Vec<FocusTarget> {
+    let deficits: Vec<ItemId> = root
+        .balance()
+        .balances
+        .iter()
+        .filter(|(_, &rate)| global_metadata.is_deficit(rate))
+        .map(|(&item, _)| item)
+        .collect();
+    let mut problems = Vec::new();
+    if !deficits.is_empty() {
+        collect_problems(
+            root,
+            &deficits,
+            &mut Vec::new(),
+            &mut Vec::new(),
+            &mut problems,
+        );
+    }
+    problems
+}
+
+/// Recursively collect the problems from the given node.
+fn collect_problems(
+    node: &Node,
+    deficits: &[ItemId],
+    path: &mut Vec<usize>,
+    ancestors: &mut Vec<Uuid>,
+    problems: &mut Vec<FocusTarget>,
+) {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            ancestors.push(group.id);
+            for (idx, child) in group.children.iter().enumerate() {
+                path.push(idx);
+                collect_problems(child, deficits, path, ancestors, problems);
+                path.pop();
+            }
+            ancestors.pop();
+        }
+        NodeKind::Building(_) => {
+            let balances = &node.balance().balances;
+            let consumes_deficit = deficits
+                .iter()
+                .any(|item| balances.get(item).is_some_and(|&rate| rate < 0.0));
+            if consumes_deficit {
+                problems.push(FocusTarget {
+                    path: path.clone(),
+                    ancestors: ancestors.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Scroll the node at the given path into view and briefly highlight it.
+fn focus_node(path: &[usize]) {
+    let selector = format!("[data-path=\"{}\"]", dom_path(path));
+    let element = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.query_selector(&selector).ok().flatten());
+    match element {
+        Some(element) => {
+            element.scroll_into_view();
+            if let Err(e) = element.class_list().add_1("focused") {
+                warn!("Unable to highlight node: {:?}", e);
+            }
+            Timeout::new(1500, move || {
+                if let Err(e) = element.class_list().remove_1("focused") {
+                    warn!("Unable to un-highlight node: {:?}", e);
+                }
+            })
+            .forget();
+        }
+        None => warn!("Unable to find node at {:?}", path),
+    }
+}
+
+/// Briefly highlight the nodes at the given paths by adding `class`, optionally scrolling
+/// the first into view if it is off screen. Nodes which aren't shown, such as those in
+/// collapsed groups, highlight their nearest shown ancestor instead.
+fn highlight_nodes(paths: &[Vec<usize>], class: &'static str, scroll: bool) {
+    let window = match web_sys::window() {
+        Some(window) => window,
+        None => return,
+    };
+    let document = match window.document() {
+        Some(document) => document,
+        None => return,
+    };
+    let window_height = window
+        .inner_height()
+        .ok()
+        .and_then(|height| height.as_f64())
+        .unwrap_or_default();
+    let mut scrolled = !scroll;
+    for path in paths {
+        let mut path = path.as_slice();
+        let element = loop {
+            let selector = format!("[data-path=\"{}\"]", dom_path(path));
+            match document.query_selector(&selector).ok().flatten() {
+                Some(element) => break element,
+                None if path.is_empty() => {
+                    warn!("Unable to find highlighted node or any of its ancestors");
+                    return;
+                }
+                None => path = &path[..path.len() - 1],
+            }
+        };
+        if !mem::replace(&mut scrolled, true) {
+            let rect = element.get_bounding_client_rect();
+            if rect.bottom() < 0.0 || rect.top() > window_height {
+                element.scroll_into_view();
+            }
+        }
+        if let Err(e) = element.class_list().add_1(class) {
+            warn!("Unable to highlight node: {:?}", e);
+        }
+        Timeout::new(CHANGE_HIGHLIGHT_DURATION, move || {
+            if let Err(e) = element.class_list().remove_1(class) {
+                warn!("Unable to un-highlight node: {:?}", e);
+            }
+        })
+        .forget();
+    }
+}
+
+/// Listen for global keyboard shortcuts.
+fn listen_for_shortcuts(
+    next_problem: Callback<()>,
+    command_palette: Callback<()>,
+) -> Option<EventListener> {
+    let window = web_sys::window()?;
+    Some(EventListener::new(&window, "keydown", move |e| {
+        if let Some(e) = e.dyn_ref::<KeyboardEvent>() {
+            if e.alt_key() && e.code() == "KeyN" {
+                e.prevent_default();
+                next_problem.emit(());
+            } else if (e.ctrl_key() || e.meta_key()) && e.code() == "KeyK" {
+                e.prevent_default();
+                command_palette.emit(());
+            }
+        }
+    }))
+}
+
+/// Get the current vertical scroll position of the window.
+fn scroll_position() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.scroll_y().ok())
+        .unwrap_or_default()
+}
+
+/// Check whether the viewport is narrow enough for the compact layout.
+fn compact_layout() -> CompactLayout {
+    let width = web_sys::window()
+        .and_then(|window| window.inner_width().ok())
+        .and_then(|width| width.as_f64());
+    CompactLayout(matches!(width, Some(width) if width <= COMPACT_LAYOUT_MAX_WIDTH))
+}
+
+/// Listen for the window being resized.
+fn listen_for_resize(resized: Callback<()>) -> Option<EventListener> {
+    let window = web_sys::window()?;
+    Some(EventListener::new(&window, "resize", move |_| {
+        resized.emit(())
+    }))
+}
+
+/// Scroll the window to the given vertical position.
+fn set_scroll_position(y: f64) {
+    if let Some(window) = web_sys::window() {
+        window.scroll_to_with_x_and_y(0.0, y);
+    }
+}
+
+/// Start listening for scroll events, calling `save` when scrolling stops.
+fn listen_for_scroll(save: Callback<()>) -> Option<EventListener> {
+    let window = web_sys::window()?;
+    let pending_save: RefCell<Option<Timeout>> = Default::default();
+    Some(EventListener::new(&window, "scroll", move |_| {
+        // Replacing the timeout cancels the previous one, so we only save once scrolling
+        // has stopped.
+        let save = save.clone();
+        *pending_save.borrow_mut() = Some(Timeout::new(250, move || save.emit(())));
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding_noise_is_not_negative() {
+        assert_eq!(RateUnit::PerMinute.format_value(-0.0001), "0.00");
+        assert_eq!(RateUnit::PerMinute.format_signed(-0.0001), "+0.00/min");
+        assert_eq!(RateUnit::PerSecond.format_value(-1e-6), "0.0000");
+        assert_eq!(RateUnit::PerMinute.format_value(-0.5), "-0.50");
+        assert_eq!(PowerUnit::Megawatts.format(-0.001), "0 MW");
+        assert_eq!(PowerUnit::Megawatts.format(-1.5), "-1.5 MW");
+    }
 }
@@ -0,0 +1,232 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Validation of a tree against the active database, and of the tree's structure.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use satisfactory_accounting::accounting::{Balance, BuildError, Node, NodeKind};
+use satisfactory_accounting::database::{BuildingId, ItemId, RecipeId};
+use uuid::Uuid;
+
+/// Largest difference between a group's cached balance and the sum of its children which
+/// is treated as rounding rather than a stale balance.
+const BALANCE_TOLERANCE: f32 = 1e-3;
+
+/// An id referenced from the tree which isn't in the database.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnknownId {
+    Building(BuildingId),
+    Recipe(RecipeId),
+    Item(ItemId),
+}
+
+impl UnknownId {
+    /// Get the unknown id which caused a build error, if it was caused by one.
+    fn from_error(err: BuildError) -> Option<Self> {
+        match err {
+            BuildError::UnknownBuilding(id) => Some(Self::Building(id)),
+            BuildError::UnknownRecipe(id) => Some(Self::Recipe(id)),
+            BuildError::UnknownItem(id) => Some(Self::Item(id)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UnknownId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Building(id) => write!(f, "Building {}", id),
+            Self::Recipe(id) => write!(f, "Recipe {}", id),
+            Self::Item(id) => write!(f, "Item {}", id),
+        }
+    }
+}
+
+/// Find all ids referenced from the tree which aren't in the database, along with the
+/// number of nodes which reference each.
+pub fn find_unknown_ids(root: &Node) -> BTreeMap<UnknownId, usize> {
+    let mut unknown = BTreeMap::new();
+    for id in root
+        .iter()
+        .filter_map(|node| node.warning().and_then(UnknownId::from_error))
+    {
+        *unknown.entry(id).or_default() += 1;
+    }
+    unknown
+}
+
+/// Build a copy of the tree with all nodes which reference unknown ids removed.
+pub fn remove_unknown(node: &Node) -> Node {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            let mut new_group = group.clone();
+            new_group.children = group
+                .children
+                .iter()
+                .filter(|child| child.warning().and_then(UnknownId::from_error).is_none())
+                .map(remove_unknown)
+                .collect();
+            new_group.into()
+        }
+        NodeKind::Building(_) => node.clone(),
+    }
+}
+
+/// A structural problem in the tree. These should never happen, so finding one indicates
+/// a bug in one of the operations which edit the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvariantViolation {
+    /// The root node is a building rather than a group.
+    RootNotGroup,
+    /// A group's id is also used by a group elsewhere in the tree.
+    DuplicateId { id: Uuid, path: Vec<usize> },
+    /// A group's id is used by one of its own descendants, so it contains itself.
+    OwnAncestor { id: Uuid, path: Vec<usize> },
+    /// A group's cached balance doesn't match the balances of its children.
+    StaleBalance { path: Vec<usize> },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RootNotGroup => write!(f, "root node is not a group"),
+            Self::DuplicateId { id, path } => {
+                write!(f, "group {} at {:?} appears more than once", id, path)
+            }
+            Self::OwnAncestor { id, path } => {
+                write!(f, "group {} at {:?} is its own ancestor", id, path)
+            }
+            Self::StaleBalance { path } => {
+                write!(f, "group at {:?} has a stale balance", path)
+            }
+        }
+    }
+}
+
+/// Check the structure of the tree, returning every violation found. This walks the tree
+/// once, so it is cheap enough to run after every edit.
+pub fn check_invariants(root: &Node) -> Vec<InvariantViolation> {
+    let mut violations = Vec::new();
+    if root.group().is_none() {
+        violations.push(InvariantViolation::RootNotGroup);
+    }
+    let mut checker = InvariantChecker {
+        seen: HashSet::new(),
+        ancestors: Vec::new(),
+        path: Vec::new(),
+        violations,
+    };
+    checker.check(root);
+    checker.violations
+}
+
+/// State of an in-progress invariant check.
+struct InvariantChecker {
+    /// Ids of all groups visited so far.
+    seen: HashSet<Uuid>,
+    /// Ids of the groups containing the current node.
+    ancestors: Vec<Uuid>,
+    /// Path to the current node.
+    path: Vec<usize>,
+    /// Violations found so far.
+    violations: Vec<InvariantViolation>,
+}
+
+impl InvariantChecker {
+    fn check(&mut self, node: &Node) {
+        let group = match node.group() {
+            Some(group) => group,
+            None => return,
+        };
+        if self.ancestors.contains(&group.id) {
+            self.violations.push(InvariantViolation::OwnAncestor {
+                id: group.id,
+                path: self.path.clone(),
+            });
+            // Don't recurse, since the rest of the subtree repeats what was checked.
+            return;
+        }
+        if !self.seen.insert(group.id) {
+            self.violations.push(InvariantViolation::DuplicateId {
+                id: group.id,
+                path: self.path.clone(),
+            });
+        }
+        let mut expected: Balance = group.children.iter().map(Node::balance).sum();
+        expected *= group.copies as f32;
+        group.apply_assumed_supply(&mut expected);
+        if !balances_match(node.balance(), &expected) {
+            self.violations.push(InvariantViolation::StaleBalance {
+                path: self.path.clone(),
+            });
+        }
+        self.ancestors.push(group.id);
+        for (idx, child) in group.children.iter().enumerate() {
+            self.path.push(idx);
+            self.check(child);
+            self.path.pop();
+        }
+        self.ancestors.pop();
+    }
+}
+
+/// Check whether two balances are equal, allowing for rounding.
+fn balances_match(actual: &Balance, expected: &Balance) -> bool {
+    let diff = actual.clone() - expected;
+    diff.power.abs() <= BALANCE_TOLERANCE
+        && diff
+            .balances
+            .values()
+            .all(|rate| rate.abs() <= BALANCE_TOLERANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::{Building, Group};
+
+    use super::*;
+
+    #[test]
+    fn valid_tree_has_no_violations() {
+        let mut inner = Group::empty();
+        inner.children.push(Building::empty_node());
+        let mut root = Group::empty();
+        root.children.push(inner.into());
+        root.children.push(Group::empty_node());
+        assert_eq!(check_invariants(&root.into()), vec![]);
+    }
+
+    #[test]
+    fn finds_duplicate_and_self_containing_groups() {
+        let shared = Group::empty();
+        let mut root = Group::empty();
+        root.children.push(shared.clone().into());
+        root.children.push(shared.clone().into());
+        let mut own_ancestor = Group::empty();
+        own_ancestor.children.push(own_ancestor.clone().into());
+        root.children.push(own_ancestor.clone().into());
+        assert_eq!(
+            check_invariants(&root.into()),
+            vec![
+                InvariantViolation::DuplicateId {
+                    id: shared.id,
+                    path: vec![1],
+                },
+                InvariantViolation::OwnAncestor {
+                    id: own_ancestor.id,
+                    path: vec![2, 0],
+                },
+            ]
+        );
+        assert_eq!(
+            check_invariants(&Building::empty_node()),
+            vec![InvariantViolation::RootNotGroup]
+        );
+    }
+}
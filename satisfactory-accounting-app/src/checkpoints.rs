@@ -0,0 +1,116 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Named snapshots of the factory which can be restored later.
+
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use yew::prelude::*;
+
+use crate::modal::Modal;
+use crate::CtxHelper;
+
+/// Largest number of checkpoints kept for a factory.
+pub const MAX_CHECKPOINTS: usize = 20;
+
+/// Largest total size of the stored checkpoints, in bytes of JSON. LocalStorage is usually
+/// limited to around 5 MB shared with everything else the app saves.
+pub const MAX_CHECKPOINT_BYTES: usize = 2_000_000;
+
+/// A named snapshot of the root of the factory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Name given to the checkpoint when it was saved.
+    pub name: String,
+    /// Net power of the factory when the checkpoint was saved, in MW.
+    pub power: f32,
+    /// Root node, in the versioned storage format so that checkpoints saved by older
+    /// versions of the app are migrated when restored.
+    pub root: Value,
+}
+
+impl Checkpoint {
+    /// Approximate number of bytes the checkpoint takes up in storage.
+    pub fn stored_size(&self) -> usize {
+        serde_json::to_string(self).map_or(0, |json| json.len())
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Saved checkpoints, oldest first.
+    pub checkpoints: Rc<Vec<Checkpoint>>,
+    /// Callback to save the current factory as a new checkpoint.
+    pub save: Callback<()>,
+    /// Callback to restore the checkpoint at the given index.
+    pub restore: Callback<usize>,
+    /// Callback to delete the checkpoint at the given index.
+    pub delete: Callback<usize>,
+    /// Callback to close the checkpoints.
+    pub close: Callback<()>,
+}
+
+/// Lists the saved checkpoints, newest first, with buttons to restore or delete each.
+pub struct Checkpoints;
+
+impl Component for Checkpoints {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let power_unit = ctx.global_meta().power_unit;
+        let checkpoints = &ctx.props().checkpoints;
+        let save = ctx.props().save.reform(|_| ());
+        let close = ctx.props().close.reform(|_| ());
+        html! {
+            <Modal class="Checkpoints" label="Checkpoints" close={ctx.props().close.clone()}>
+                <div class="header">
+                    <span class="title">{"Checkpoints"}</span>
+                    <span class="count">
+                        {format!("{}/{}", checkpoints.len(), MAX_CHECKPOINTS)}
+                    </span>
+                    <button class="save" title="Save Checkpoint" onclick={save}>
+                        <span class="material-icons">{"bookmark_add"}</span>
+                    </button>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                if checkpoints.is_empty() {
+                    <div class="empty">{"No checkpoints saved"}</div>
+                } else {
+                    <ul>
+                        { for checkpoints.iter().enumerate().rev().map(|(idx, checkpoint)| {
+                            let restore = ctx.props().restore.reform(move |_| idx);
+                            let delete = ctx.props().delete.reform(move |_| idx);
+                            html! {
+                                <li class="checkpoint">
+                                    <span class="name">{&checkpoint.name}</span>
+                                    <span class="power">{power_unit.format(checkpoint.power)}</span>
+                                    <button class="restore" title="Restore Checkpoint"
+                                        onclick={restore}>
+                                        <span class="material-icons">{"restore"}</span>
+                                    </button>
+                                    <button class="delete" title="Delete Checkpoint"
+                                        onclick={delete}>
+                                        <span class="material-icons">{"delete"}</span>
+                                    </button>
+                                </li>
+                            }
+                        }) }
+                    </ul>
+                }
+            </Modal>
+        }
+    }
+}
@@ -0,0 +1,29 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Copying text to the system clipboard.
+
+use gloo::dialogs::prompt;
+use log::warn;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Start writing text to the clipboard. Throws if the clipboard API is unavailable,
+    /// such as on pages not served over https.
+    #[wasm_bindgen(catch, js_namespace = ["navigator", "clipboard"], js_name = writeText)]
+    fn write_text(text: &str) -> Result<JsValue, JsValue>;
+}
+
+/// Copy text to the clipboard. If the clipboard can't be used, the text is shown in a
+/// prompt so it can be copied by hand.
+pub fn copy_text(text: &str) {
+    if let Err(e) = write_text(text) {
+        warn!("Unable to write to the clipboard: {:?}", e);
+        prompt("Copy the text below:", Some(text));
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Keyboard-driven palette for running app actions by name.
+
+use yew::prelude::*;
+
+use crate::modal::Modal;
+use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
+
+/// An action which can be run from the command palette.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+    AddBuilding,
+    AddGroup,
+    Undo,
+    Redo,
+    NextProblem,
+    NextSearchMatch,
+    ToggleEmptyBalances,
+    ToggleBuildableCounts,
+    ToggleFoundationEstimates,
+    ToggleBalanceCategories,
+    ToggleGroupsOnly,
+    CyclePowerUnit,
+    CycleRateUnit,
+    CycleReferenceBelt,
+    AddWatchList,
+    CompareRecipes,
+    ReplaceRecipe,
+    CollectGenerators,
+    ToggleOutline,
+    ToggleFlatList,
+    PrintReport,
+    CopySummary,
+    ShowStatistics,
+    SetBaseline,
+    ClearBaseline,
+    ShowCheckpoints,
+    SaveCheckpoint,
+    SetBalanceTolerance,
+    SetDefaultClockSpeed,
+    CycleDocumentReferenceBelt,
+    ToggleAlternateRecipes,
+    TogglePerfOverlay,
+    VerifyBalances,
+    UpdateDb,
+}
+
+impl Command {
+    /// All commands.
+    pub const ALL: &'static [Command] = &[
+        Command::AddBuilding,
+        Command::AddGroup,
+        Command::Undo,
+        Command::Redo,
+        Command::NextProblem,
+        Command::NextSearchMatch,
+        Command::ToggleEmptyBalances,
+        Command::ToggleBuildableCounts,
+        Command::ToggleFoundationEstimates,
+        Command::ToggleBalanceCategories,
+        Command::ToggleGroupsOnly,
+        Command::CyclePowerUnit,
+        Command::CycleRateUnit,
+        Command::CycleReferenceBelt,
+        Command::AddWatchList,
+        Command::CompareRecipes,
+        Command::ReplaceRecipe,
+        Command::CollectGenerators,
+        Command::ToggleOutline,
+        Command::ToggleFlatList,
+        Command::PrintReport,
+        Command::CopySummary,
+        Command::ShowStatistics,
+        Command::SetBaseline,
+        Command::ClearBaseline,
+        Command::ShowCheckpoints,
+        Command::SaveCheckpoint,
+        Command::SetBalanceTolerance,
+        Command::SetDefaultClockSpeed,
+        Command::CycleDocumentReferenceBelt,
+        Command::ToggleAlternateRecipes,
+        Command::TogglePerfOverlay,
+        Command::VerifyBalances,
+        Command::UpdateDb,
+    ];
+
+    /// Get the name the command is listed and searched by.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::AddBuilding => "Add Building",
+            Self::AddGroup => "Add Group",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::NextProblem => "Jump to Next Deficit",
+            Self::NextSearchMatch => "Jump to Next Search Match",
+            Self::ToggleEmptyBalances => "Show/Hide Zero Balances",
+            Self::ToggleBuildableCounts => "Show/Hide Whole Building Counts",
+            Self::ToggleFoundationEstimates => "Show/Hide Foundation Estimates",
+            Self::ToggleBalanceCategories => "Group/Ungroup Balance by Category",
+            Self::ToggleGroupsOnly => "Show/Hide Buildings (Groups Only View)",
+            Self::CyclePowerUnit => "Change Power Unit",
+            Self::CycleRateUnit => "Change Time Base of Item Rates",
+            Self::CycleReferenceBelt => "Change Belt Item Rates Are Shown Relative To",
+            Self::AddWatchList => "Add Watch List",
+            Self::CompareRecipes => "Compare Recipes",
+            Self::ReplaceRecipe => "Replace Recipe Everywhere",
+            Self::CollectGenerators => "Move All Generators into a Power Group",
+            Self::ToggleOutline => "Show/Hide Outline",
+            Self::ToggleFlatList => "Switch Tree/Flat Building List",
+            Self::PrintReport => "Print Report",
+            Self::CopySummary => "Copy Summary to Clipboard",
+            Self::ShowStatistics => "Show Statistics",
+            Self::SetBaseline => "Set Baseline",
+            Self::ClearBaseline => "Clear Baseline",
+            Self::ShowCheckpoints => "Show Checkpoints",
+            Self::SaveCheckpoint => "Save Checkpoint",
+            Self::SetBalanceTolerance => "Set Balanced Rate Tolerance",
+            Self::SetDefaultClockSpeed => "Set Default Clock Speed for This Factory",
+            Self::CycleDocumentReferenceBelt => "Change Reference Belt for This Factory",
+            Self::ToggleAlternateRecipes => "Show/Hide Alternate Recipes for This Factory",
+            Self::TogglePerfOverlay => "Show/Hide Performance Overlay",
+            Self::VerifyBalances => "Recompute All Balances and Compare",
+            Self::UpdateDb => "Update Database",
+        }
+    }
+
+    /// Get the material icon shown next to the command.
+    fn icon(self) -> &'static str {
+        match self {
+            Self::AddBuilding => "add",
+            Self::AddGroup => "create_new_folder",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::NextProblem => "report_problem",
+            Self::NextSearchMatch => "search",
+            Self::ToggleEmptyBalances => "exposure_zero",
+            Self::ToggleBuildableCounts => "foundation",
+            Self::ToggleFoundationEstimates => "grid_on",
+            Self::ToggleBalanceCategories => "category",
+            Self::ToggleGroupsOnly => "folder",
+            Self::CyclePowerUnit => "bolt",
+            Self::CycleRateUnit => "schedule",
+            Self::CycleReferenceBelt => "linear_scale",
+            Self::AddWatchList => "playlist_add",
+            Self::CompareRecipes => "compare_arrows",
+            Self::ReplaceRecipe => "find_replace",
+            Self::CollectGenerators => "electrical_services",
+            Self::ToggleOutline => "account_tree",
+            Self::ToggleFlatList => "view_list",
+            Self::PrintReport => "print",
+            Self::CopySummary => "content_copy",
+            Self::ShowStatistics => "insights",
+            Self::SetBaseline => "flag",
+            Self::ClearBaseline => "outlined_flag",
+            Self::ShowCheckpoints => "bookmarks",
+            Self::SaveCheckpoint => "bookmark_add",
+            Self::SetBalanceTolerance => "tune",
+            Self::SetDefaultClockSpeed => "speed",
+            Self::CycleDocumentReferenceBelt => "linear_scale",
+            Self::ToggleAlternateRecipes => "alt_route",
+            Self::TogglePerfOverlay => "speed",
+            Self::VerifyBalances => "fact_check",
+            Self::UpdateDb => "browser_updated",
+        }
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Commands which are currently available.
+    pub commands: Vec<Command>,
+    /// Callback to run the chosen command.
+    pub run: Callback<Command>,
+    /// Callback to close the palette without running anything.
+    pub close: Callback<()>,
+}
+
+/// Lists the available commands with fuzzy search and runs the chosen one.
+pub struct CommandPalette;
+
+impl Component for CommandPalette {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let choices: Vec<_> = ctx
+            .props()
+            .commands
+            .iter()
+            .map(|&command| Choice {
+                id: command,
+                name: command.name().into(),
+                image: html! {
+                    <span class="material-icons">{command.icon()}</span>
+                },
+                detail: None,
+            })
+            .collect();
+        let selected = ctx.props().run.clone();
+        let cancelled = ctx.props().close.clone();
+        html! {
+            <Modal class="CommandPalette" label="Command Palette" close={cancelled.clone()}>
+                <ChooseFromList<Command> {choices} {selected} {cancelled} />
+            </Modal>
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Flat list of every building in the factory, ignoring grouping.
+
+use std::collections::HashMap;
+
+use log::warn;
+use satisfactory_accounting::accounting::{Node, NodeKind};
+use uuid::Uuid;
+use yew::prelude::*;
+
+use crate::app::GlobalMetadata;
+use crate::node_display::{node_label, replace_child, NodeDisplay, NodeMeta};
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Root node of the factory.
+    pub root: Node,
+    /// Callback to replace the root after editing one of its buildings.
+    pub replace_root: Callback<Node>,
+    /// Callback to delete the node at a path from the root.
+    pub delete_node: Callback<Vec<usize>>,
+    /// Callback to set the metadata of a node.
+    pub set_metadata: Callback<(Uuid, NodeMeta)>,
+    /// Callback to set the metadata of many nodes at once.
+    pub batch_set_metadata: Callback<HashMap<Uuid, NodeMeta>>,
+    /// Callback to replace the global metadata.
+    pub set_global_metadata: Callback<GlobalMetadata>,
+}
+
+/// Shows every building in the tree in one list, each with the names of the groups
+/// containing it. Edits are applied to the building in its place in the real tree.
+pub struct FlatList;
+
+impl Component for FlatList {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let db = ctx.db();
+        let mut buildings = Vec::new();
+        collect_buildings(
+            &ctx.props().root,
+            &mut Vec::new(),
+            &mut Vec::new(),
+            &mut buildings,
+        );
+        if buildings.is_empty() {
+            return html! {
+                <div class="FlatList empty">{"No buildings"}</div>
+            };
+        }
+        let move_node =
+            Callback::from(|_| warn!("Buildings cannot be moved in the flat building list"));
+        html! {
+            <div class="FlatList">
+                { for buildings.into_iter().map(|entry| {
+                    let replace = {
+                        let root = ctx.props().root.clone();
+                        let replace_root = ctx.props().replace_root.clone();
+                        let path = entry.path.clone();
+                        Callback::from(move |(_, replacement): (usize, Node)| {
+                            match replace_child(&root, &path, replacement) {
+                                Some(new_root) => replace_root.emit(new_root),
+                                None => warn!("Unable to replace building at {:?}", path),
+                            }
+                        })
+                    };
+                    let delete = {
+                        let path = entry.path.clone();
+                        ctx.props().delete_node.reform(move |_: usize| path.clone())
+                    };
+                    let label = node_label(&entry.node, &db);
+                    html! {
+                        <div class="entry">
+                            <div class="breadcrumb">
+                                { for entry.ancestors.iter().map(|name| html! {
+                                    <span class="crumb">{name}</span>
+                                }) }
+                                <span class="label">{label}</span>
+                            </div>
+                            <NodeDisplay node={entry.node} path={entry.path}
+                                {replace} {delete} move_node={move_node.clone()}
+                                delete_node={ctx.props().delete_node.clone()}
+                                set_metadata={ctx.props().set_metadata.clone()}
+                                batch_set_metadata={ctx.props().batch_set_metadata.clone()}
+                                set_global_metadata={ctx.props().set_global_metadata.clone()} />
+                        </div>
+                    }
+                }) }
+            </div>
+        }
+    }
+}
+
+/// A building found in the tree.
+struct Entry {
+    /// The building node.
+    node: Node,
+    /// Path to the building from the root.
+    path: Vec<usize>,
+    /// Names of the groups containing the building, outermost first, excluding the root.
+    ancestors: Vec<String>,
+}
+
+/// Collect every building below `node` in tree order.
+fn collect_buildings(
+    node: &Node,
+    path: &mut Vec<usize>,
+    ancestors: &mut Vec<String>,
+    result: &mut Vec<Entry>,
+) {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            let is_root = path.is_empty();
+            if !is_root {
+                ancestors.push(if group.name.is_empty() {
+                    "Unnamed Group".to_string()
+                } else {
+                    group.name.clone()
+                });
+            }
+            for (idx, child) in group.children.iter().enumerate() {
+                path.push(idx);
+                collect_buildings(child, path, ancestors, result);
+                path.pop();
+            }
+            if !is_root {
+                ancestors.pop();
+            }
+        }
+        NodeKind::Building(_) => result.push(Entry {
+            node: node.clone(),
+            path: path.clone(),
+            ancestors: ancestors.clone(),
+        }),
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Fuzzy matching of typed filters against the names shown in selectors.
+
+/// Score for each matched character.
+const MATCH: i64 = 10;
+/// Bonus for a match at the start of a word, so "rip" prefers "Reinforced Iron Plate".
+const WORD_START: i64 = 20;
+/// Bonus for a match directly following the previous matched character.
+const CONSECUTIVE: i64 = 15;
+/// Penalty for skipping characters between two matches.
+const GAP: i64 = 3;
+/// Maximum penalty for characters skipped before the first match.
+const MAX_LEADING: i64 = 10;
+
+/// Score how well `pattern` matches `candidate`, or None if it does not match at all.
+///
+/// The pattern matches if its characters appear in order in the candidate, ignoring case
+/// and any spaces in the pattern. Higher scores are better matches. Matches at the start
+/// of words and runs of consecutive characters score highest, so abbreviations and
+/// prefixes rank above scattered matches. An empty pattern matches everything with a
+/// score of 0.
+pub fn score(pattern: &str, candidate: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let bonus: Vec<i64> = (0..chars.len())
+        .map(|j| {
+            if is_word_start(&chars, j) {
+                WORD_START
+            } else {
+                0
+            }
+        })
+        .collect();
+
+    // best[j] is the best score for the pattern so far with its last character matched
+    // at candidate position j.
+    let mut best: Vec<Option<i64>> = (0..chars.len())
+        .map(|j| (lower[j] == pattern[0]).then(|| MATCH + bonus[j] - (j as i64).min(MAX_LEADING)))
+        .collect();
+    for &pc in &pattern[1..] {
+        let mut next = vec![None; chars.len()];
+        // Best score among positions at least two before j, which need a gap.
+        let mut gapped: Option<i64> = None;
+        for j in 1..chars.len() {
+            if j >= 2 {
+                gapped = gapped.max(best[j - 2]);
+            }
+            if lower[j] != pc {
+                continue;
+            }
+            let from_consecutive = best[j - 1].map(|s| s + CONSECUTIVE);
+            let from_gap = gapped.map(|s| s - GAP);
+            next[j] = from_consecutive.max(from_gap).map(|s| s + MATCH + bonus[j]);
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Whether the character at `idx` begins a word, either because it follows a
+/// non-alphanumeric character or because it is an uppercase letter after a lowercase one.
+fn is_word_start(chars: &[char], idx: usize) -> bool {
+    match idx.checked_sub(1).map(|prev| chars[prev]) {
+        None => true,
+        Some(prev) => !prev.is_alphanumeric() || (prev.is_lowercase() && chars[idx].is_uppercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_ignoring_case_and_spaces() {
+        assert!(score("rip", "Reinforced Iron Plate").is_some());
+        assert!(score("IRON plate", "Iron Plate").is_some());
+        assert!(score("ironp", "Iron Plate").is_some());
+        assert_eq!(score("pir", "Reinforced Iron Plate"), None);
+        assert_eq!(score("rod", "Iron Plate"), None);
+        assert_eq!(score("", "Iron Plate"), Some(0));
+    }
+
+    #[test]
+    fn word_starts_rank_above_scattered_matches() {
+        let abbreviation = score("rip", "Reinforced Iron Plate").unwrap();
+        let scattered = score("rip", "Stripped Wire").unwrap();
+        assert!(abbreviation > scattered);
+        let prefix = score("iron", "Iron Rod").unwrap();
+        let inside = score("iron", "Reinforced Iron Plate").unwrap();
+        assert!(prefix > inside);
+    }
+}
@@ -7,6 +7,8 @@
 //       http://www.apache.org/licenses/LICENSE-2.0
 use std::rc::Rc;
 
+use app::{BeltTier, CompactLayout, DocumentSettings, GlobalMetadata};
+use log::warn;
 use node_display::{NodeMeta, NodeMetadata};
 use uuid::Uuid;
 use yew::prelude::*;
@@ -14,7 +16,23 @@ use yew::prelude::*;
 use satisfactory_accounting::database::Database;
 
 mod app;
+mod checkpoints;
+mod clipboard;
+mod command_palette;
+mod flat_list;
+mod fuzzy;
+mod modal;
 mod node_display;
+mod outline;
+mod perf_overlay;
+mod recipe_comparison;
+mod recipe_replace;
+mod report;
+mod root_summary;
+mod side_panels;
+mod statistics;
+mod tabs;
+mod watch_lists;
 
 fn main() {
     console_log::init_with_level(log::Level::Debug).expect("Unable to init logger");
@@ -26,25 +44,79 @@ trait CtxHelper {
     /// Get the database from context, throw if context is missing.
     fn db(&self) -> Rc<Database>;
 
-    /// Get the metadata from context, throw if context is missing (gets default metadat
-    /// if not set).
+    /// Get the database from context, or None if the context is missing.
+    fn try_db(&self) -> Option<Rc<Database>>;
+
+    /// Get the metadata from context (gets default metadata if not set or if the context
+    /// is missing).
     fn meta(&self, id: Uuid) -> NodeMeta;
+
+    /// Get the global metadata from context, or the default if the context is missing.
+    fn global_meta(&self) -> GlobalMetadata;
+
+    /// Whether components should use their compact layout for narrow viewports. False if
+    /// the context is missing.
+    fn compact_layout(&self) -> bool;
+
+    /// Get the settings of the document from context, or the default if the context is
+    /// missing.
+    fn document_settings(&self) -> DocumentSettings;
+
+    /// Get the belt item rates are shown relative to: the document's own choice if it has
+    /// one, otherwise the global preference.
+    fn reference_belt(&self) -> Option<BeltTier>;
 }
 
 impl<T: Component> CtxHelper for Context<T> {
     fn db(&self) -> Rc<Database> {
-        let (db, _) = self
-            .link()
-            .context::<Rc<Database>>(Callback::noop())
-            .expect("database context to be set");
-        db
+        self.try_db().expect("database context to be set")
+    }
+
+    fn try_db(&self) -> Option<Rc<Database>> {
+        let (db, _) = self.link().context::<Rc<Database>>(Callback::noop())?;
+        Some(db)
     }
 
     fn meta(&self, id: Uuid) -> NodeMeta {
-        let (meta, _) = self
-            .link()
-            .context::<NodeMetadata>(Callback::noop())
-            .expect("metadata context to be set");
-        meta.meta(id)
+        match self.link().context::<NodeMetadata>(Callback::noop()) {
+            Some((meta, _)) => meta.meta(id),
+            None => {
+                warn!("Metadata context is not set");
+                Default::default()
+            }
+        }
+    }
+
+    fn global_meta(&self) -> GlobalMetadata {
+        match self.link().context::<GlobalMetadata>(Callback::noop()) {
+            Some((global_meta, _)) => global_meta,
+            None => {
+                warn!("Global metadata context is not set");
+                Default::default()
+            }
+        }
+    }
+
+    fn compact_layout(&self) -> bool {
+        matches!(
+            self.link().context::<CompactLayout>(Callback::noop()),
+            Some((CompactLayout(true), _))
+        )
+    }
+
+    fn document_settings(&self) -> DocumentSettings {
+        match self.link().context::<DocumentSettings>(Callback::noop()) {
+            Some((settings, _)) => settings,
+            None => {
+                warn!("Document settings context is not set");
+                Default::default()
+            }
+        }
+    }
+
+    fn reference_belt(&self) -> Option<BeltTier> {
+        self.document_settings()
+            .reference_belt
+            .or(self.global_meta().reference_belt)
     }
 }
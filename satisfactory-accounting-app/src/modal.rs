@@ -0,0 +1,148 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Dialog wrapper which keeps keyboard focus inside until it is closed.
+
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement};
+use yew::prelude::*;
+
+/// Selector for elements which can receive focus with Tab.
+const FOCUSABLE: &str = "a[href], button:not([disabled]), input:not([disabled]), \
+    select:not([disabled]), textarea:not([disabled]), [tabindex]:not([tabindex=\"-1\"])";
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Contents of the dialog.
+    pub children: Children,
+    /// Callback to close the dialog, on Escape or clicking outside of it.
+    pub close: Callback<()>,
+    /// Name of the dialog read by screen readers.
+    pub label: String,
+    /// Extra classes for the dialog element, used to position and style it.
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+/// Shows its children as a modal dialog. While open, Tab and Shift+Tab cycle through the
+/// dialog's controls, Escape closes it, and when it closes focus returns to whatever was
+/// focused when it opened.
+pub struct Modal {
+    /// The dialog element.
+    container: NodeRef,
+    /// Element which had focus when the dialog opened.
+    previous_focus: Option<HtmlElement>,
+}
+
+impl Component for Modal {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            container: NodeRef::default(),
+            previous_focus: active_element().and_then(|e| e.dyn_into().ok()),
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let onkeydown = {
+            let container = self.container.clone();
+            let close = ctx.props().close.clone();
+            Callback::from(move |e: KeyboardEvent| match &*e.key() {
+                "Esc" | "Escape" => {
+                    e.prevent_default();
+                    e.stop_propagation();
+                    close.emit(());
+                }
+                "Tab" => trap_tab(&container, &e),
+                _ => {}
+            })
+        };
+        let close = ctx.props().close.reform(|_| ());
+        html! {
+            <>
+                <div class="modal-backdrop" onclick={close} />
+                <div class={classes!("Modal", ctx.props().class.clone())} role="dialog"
+                    aria-modal="true" aria-label={ctx.props().label.clone()} tabindex="-1"
+                    ref={self.container.clone()} {onkeydown}>
+                    { for ctx.props().children.iter() }
+                </div>
+            </>
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        if !first_render {
+            return;
+        }
+        // Children may have focused one of their own inputs already.
+        let focusable = focusable_elements(&self.container);
+        if focused_index(&focusable).is_some() {
+            return;
+        }
+        let target = focusable
+            .into_iter()
+            .next()
+            .or_else(|| self.container.cast::<HtmlElement>());
+        if let Some(target) = target {
+            let _ = target.focus();
+        }
+    }
+
+    fn destroy(&mut self, _ctx: &Context<Self>) {
+        if let Some(previous) = self.previous_focus.take() {
+            let _ = previous.focus();
+        }
+    }
+}
+
+/// Get the currently focused element.
+fn active_element() -> Option<Element> {
+    web_sys::window()?.document()?.active_element()
+}
+
+/// Find the elements inside the dialog which can be tabbed to, in document order.
+fn focusable_elements(container: &NodeRef) -> Vec<HtmlElement> {
+    let list = match container
+        .cast::<Element>()
+        .and_then(|container| container.query_selector_all(FOCUSABLE).ok())
+    {
+        Some(list) => list,
+        None => return Vec::new(),
+    };
+    (0..list.length())
+        .filter_map(|i| list.get(i)?.dyn_into().ok())
+        .collect()
+}
+
+/// Get the index of the focused element in `focusable`, if it is one of them.
+fn focused_index(focusable: &[HtmlElement]) -> Option<usize> {
+    let active = active_element()?;
+    focusable
+        .iter()
+        .position(|element| AsRef::<Element>::as_ref(element) == &active)
+}
+
+/// Wrap Tab from the last control to the first, and Shift+Tab from the first to the
+/// last, so focus can't leave the dialog.
+fn trap_tab(container: &NodeRef, e: &KeyboardEvent) {
+    let focusable = focusable_elements(container);
+    if focusable.is_empty() {
+        e.prevent_default();
+        return;
+    }
+    let last = focusable.len() - 1;
+    let target = match (focused_index(&focusable), e.shift_key()) {
+        (Some(0) | None, true) => last,
+        (Some(i), false) if i == last => 0,
+        (None, false) => 0,
+        _ => return,
+    };
+    e.prevent_default();
+    let _ = focusable[target].focus();
+}
@@ -6,9 +6,12 @@
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::mem;
 use std::rc::Rc;
 
+use gloo::dialogs::{alert, confirm, prompt};
+use gloo::file::callbacks::{read_as_text, FileReader};
 use log::warn;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -17,23 +20,37 @@ use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
 use satisfactory_accounting::accounting::{
-    BuildNode, Building, BuildingSettings, GeneratorSettings, GeothermalSettings, Group,
+    BuildNode, Building, BuildingSettings, ColorTag, GeneratorSettings, GeothermalSettings, Group,
     ManufacturerSettings, MinerSettings, Node, NodeKind, PumpSettings, ResourcePurity,
     StationSettings,
 };
 use satisfactory_accounting::database::{
     BuildingId, BuildingKind, BuildingKindId, BuildingType, ItemId, RecipeId,
 };
+use satisfactory_accounting::io;
+use satisfactory_accounting::solver::{self, RecipeStrategy};
 
+use crate::app::GlobalMetadata;
+use crate::perf_overlay;
 use crate::CtxHelper;
 
+use graph_manipulation::{DemandScale, SortKey};
+
+pub(crate) use graph_manipulation::{
+    balance_mismatches, collect_generators, matches_search, node_label, remove_child,
+    replace_child, replace_recipe, RecipeReplacement,
+};
+
 mod balance;
-mod building;
+pub(crate) mod building;
 mod copies;
 mod drag;
 mod graph_manipulation;
 mod group;
-mod icon;
+pub(crate) mod icon;
+mod producers;
+mod tag;
+mod wheel;
 
 /// Mapping of node medatata by node id.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -67,14 +84,139 @@ impl NodeMetadata {
             .collect();
         Rc::make_mut(&mut self.0).retain(|k, _| used_uuids.contains(k));
     }
+
+    /// Collapse every group more than `depth` levels below the root which has no saved
+    /// metadata, leaving groups the user has already expanded or collapsed as they were.
+    pub fn collapse_below_depth(&mut self, root: &Node, depth: u32) {
+        fn visit(metadata: &mut HashMap<Uuid, NodeMeta>, node: &Node, level: u32, depth: u32) {
+            if let Some(group) = node.group() {
+                if level > depth {
+                    metadata.entry(group.id).or_insert_with(|| NodeMeta {
+                        collapsed: true,
+                        ..Default::default()
+                    });
+                }
+                for child in &group.children {
+                    visit(metadata, child, level + 1, depth);
+                }
+            }
+        }
+        visit(Rc::make_mut(&mut self.0), root, 0, depth);
+    }
 }
 
 /// Metadata about a node which isn't stored in the tree and isn't available for
 /// undo/redo.
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NodeMeta {
     /// Whether the node should be shown collapsed or expanded.
     collapsed: bool,
+    /// If non-empty, only these items are shown in the node's balance.
+    shown_items: BTreeSet<ItemId>,
+    /// Items which are not shown in the node's balance.
+    hidden_items: BTreeSet<ItemId>,
+    /// Whether power is one of the entries shown exclusively, alongside `shown_items`.
+    power_shown: bool,
+    /// Whether power is not shown in the node's balance.
+    power_hidden: bool,
+    /// Whether a group shows its buildings even when only groups are being shown.
+    show_buildings: bool,
+    /// Whether a group warns when its children make items other than its primary product.
+    pure_line: bool,
+}
+
+impl NodeMeta {
+    /// Whether the node is shown collapsed.
+    pub fn is_collapsed(&self) -> bool {
+        self.collapsed
+    }
+
+    /// Build a copy of this metadata with the node expanded.
+    pub fn expanded(&self) -> Self {
+        Self {
+            collapsed: false,
+            ..self.clone()
+        }
+    }
+
+    /// Whether any entries are set to be shown exclusively.
+    fn has_exclusive_entries(&self) -> bool {
+        !self.shown_items.is_empty() || self.power_shown
+    }
+
+    /// Whether the given item passes this node's balance filters.
+    pub fn shows_item(&self, item: ItemId) -> bool {
+        (!self.has_exclusive_entries() || self.shown_items.contains(&item))
+            && !self.hidden_items.contains(&item)
+    }
+
+    /// Whether power passes this node's balance filters.
+    pub fn shows_power(&self) -> bool {
+        (!self.has_exclusive_entries() || self.power_shown) && !self.power_hidden
+    }
+
+    /// Whether this node has any balance filters set.
+    pub fn has_filters(&self) -> bool {
+        self.has_exclusive_entries() || !self.hidden_items.is_empty() || self.power_hidden
+    }
+
+    /// Build a copy of this metadata with the item added to or removed from the hidden
+    /// items.
+    fn with_hidden_toggled(&self, item: ItemId) -> Self {
+        let mut updated = self.clone();
+        if !updated.hidden_items.remove(&item) {
+            updated.hidden_items.insert(item);
+        }
+        updated
+    }
+
+    /// Build a copy of this metadata with the item added to or removed from the items
+    /// to show exclusively.
+    fn with_shown_toggled(&self, item: ItemId) -> Self {
+        let mut updated = self.clone();
+        if !updated.shown_items.remove(&item) {
+            updated.shown_items.insert(item);
+        }
+        updated
+    }
+
+    /// Build a copy of this metadata with power hidden or shown.
+    fn with_power_hidden_toggled(&self) -> Self {
+        Self {
+            power_hidden: !self.power_hidden,
+            ..self.clone()
+        }
+    }
+
+    /// Build a copy of this metadata with power added to or removed from the entries to
+    /// show exclusively.
+    fn with_power_shown_toggled(&self) -> Self {
+        Self {
+            power_shown: !self.power_shown,
+            ..self.clone()
+        }
+    }
+
+    /// Build a copy of this metadata with all balance filters removed.
+    fn without_filters(&self) -> Self {
+        Self {
+            shown_items: Default::default(),
+            hidden_items: Default::default(),
+            power_shown: false,
+            power_hidden: false,
+            ..self.clone()
+        }
+    }
+}
+
+/// Format a node path for use in the `data-path` attribute, which allows the node to be
+/// found in the DOM.
+pub fn dom_path(path: &[usize]) -> String {
+    path.iter()
+        .map(|idx| idx.to_string())
+        .collect::<Vec<_>>()
+        .join("-")
 }
 
 #[derive(Debug, PartialEq, Properties)]
@@ -89,14 +231,30 @@ pub struct Props {
     /// Callback to tell the parent to copy this node.
     #[prop_or_default]
     pub copy: Option<Callback<usize>>,
+    /// Callback to tell the parent to copy this node at a different scale.
+    #[prop_or_default]
+    pub copy_scaled: Option<Callback<usize>>,
+    /// Callback to tell the parent to split this building into several by proportion.
+    #[prop_or_default]
+    pub split: Option<Callback<usize>>,
+    /// Callback to tell the parent to scale this building to meet its siblings' demand.
+    #[prop_or_default]
+    pub match_demand: Option<Callback<usize>>,
+    /// Callback to tell the parent to merge this group with the group after it.
+    #[prop_or_default]
+    pub merge_next: Option<Callback<usize>>,
     /// Callback to tell the parent to replace this node.
     pub replace: Callback<(usize, Node)>,
     /// Callback to tell the parent to move a node.
     pub move_node: Callback<(Vec<usize>, Vec<usize>)>,
+    /// Callback to delete the node at a path from the root.
+    pub delete_node: Callback<Vec<usize>>,
     /// Callback to set the metadata of a node.
     pub set_metadata: Callback<(Uuid, NodeMeta)>,
     /// Callback to set the metadata of many nodes at once.
     pub batch_set_metadata: Callback<HashMap<Uuid, NodeMeta>>,
+    /// Callback to replace the global metadata.
+    pub set_global_metadata: Callback<GlobalMetadata>,
 }
 
 /// Messages which can be sent to a Node.
@@ -104,6 +262,10 @@ pub enum Msg {
     // Shared messages:
     /// Set the number of virtual copies of this building or group.
     SetCopyCount { copies: u32 },
+    /// Replace this node with a new group containing it.
+    WrapInGroup,
+    /// Replace this building with a new group containing it and producers of its inputs.
+    ExpandIngredients,
 
     // Messages for groups:
     /// Replace the child at the given index with the specified node.
@@ -112,8 +274,20 @@ pub enum Msg {
     DeleteChild { idx: usize },
     /// Copy the child at the specified index.
     CopyChild { idx: usize },
+    /// Ask for a multiplier and copy the child at the specified index at that scale.
+    CopyChildScaled { idx: usize },
+    /// Ask for proportions and split the building at the specified index into one
+    /// building per proportion.
+    SplitChild { idx: usize },
+    /// Scale the building at the specified index so its main output meets the net demand
+    /// of the other children.
+    MatchSiblingDemand { idx: usize },
+    /// Merge the group at the specified index with the group after it.
+    MergeWithNext { idx: usize },
     /// Add the given node as a child at the end of the list.
     AddChild { child: Node },
+    /// Add a new building of the given type to this group.
+    QuickAddBuilding { id: BuildingId },
     /// Rename this node.
     Rename { name: String },
     /// When another node starts being dragged over this one.
@@ -127,18 +301,70 @@ pub enum Msg {
         src_path: Vec<usize>,
         dest_path: Vec<usize>,
     },
+    /// Adjust the clocks of this group's generators to balance its power.
+    BalanceGeneratorClocks,
+    /// Switch in or out of editing the balance filters for this group.
+    ToggleFilterEditing,
+    /// Show or hide the gross production and consumption next to each net rate.
+    ToggleGross,
+    /// Switch a building's balance between the total for all copies and one copy.
+    TogglePerBuilding,
+    /// In the compact layout, expand or collapse the balance summary.
+    ToggleCompactBalance,
+    /// Replace this group with its only child.
+    Flatten,
+    /// Set this group to one copy, dividing its children by its copies.
+    Normalize,
+    /// Set the copies of this group to the most that fit within a power budget.
+    ScaleToPowerBudget,
+    /// Reorder the children of this group by the given key.
+    SortChildren { key: SortKey },
+    /// Remove all unnamed empty groups below this group.
+    RemoveEmptyGroups,
+    /// Show or hide the summary of items with several producers.
+    ToggleCombinedProducers,
+    /// In the compact layout, open or close the menu of ways to add children.
+    ToggleAddMenu,
+    /// Start or stop choosing an item to solve for.
+    ToggleChoosingTarget { choosing: bool },
+    /// Start or stop choosing the primary product of this group.
+    ToggleChoosingPrimary { choosing: bool },
+    /// Set or clear the designated primary product of this group.
+    SetPrimaryProduct { item: Option<ItemId> },
+    /// Toggle whether this group assumes an item is supplied externally.
+    ToggleAssumedSupply { item: ItemId },
+    /// Ask for a rate and add a group of buildings which produces the item at that rate.
+    SolveFor { id: ItemId },
+    /// Download this group as a standalone factory file.
+    ExportGroup,
+    /// Download this group as a Satisfactory Tools factory, after confirming anything
+    /// which can't be represented.
+    ExportPlanner,
+    /// Start reading a factory file to add to this group.
+    ImportFile { file: gloo::file::File },
+    /// Add the contents of a factory file which finished loading to this group.
+    ImportLoaded {
+        result: Result<String, gloo::file::FileReadError>,
+    },
 
     // Messages for buildings:
     /// Change the building type of this node.
     ChangeType { id: BuildingId },
     /// Change the recipe for the building, if a manufacturer.
     ChangeRecipe { id: RecipeId },
+    /// Forget the recently used recipes offered as quick picks.
+    ClearRecentRecipes,
     /// Change the item for the building, if a Generator, Miner, or Pump.
     ChangeItem { id: ItemId },
     /// Change the clock speed for the building.
     ChangeClockSpeed { clock_speed: f32 },
     /// Change the resource purity for the node the building is on.
     ChangePurity { purity: ResourcePurity },
+    /// Switch a miner to the given building and node purity at 100% clock speed.
+    ApplyExtractionPreset {
+        building: BuildingId,
+        purity: ResourcePurity,
+    },
     /// Change the number of nodes of a particular purity for a pump.
     ChangePumpPurity {
         /// Purity kind to modify.
@@ -148,6 +374,19 @@ pub enum Msg {
     },
     /// Change the consumption of a Station.
     ChangeConsumption { consumption: f32 },
+    /// Lock or unlock this building against bulk operations.
+    ToggleLocked,
+    /// Switch in or out of choosing the color tag of this node.
+    ToggleChoosingTag {
+        /// The new choosing state.
+        choosing: bool,
+    },
+    /// Set or clear the color tag of this node.
+    SetTag { tag: Option<ColorTag> },
+    /// Ask for the size of a fuel stockpile to estimate a generator's runtime with.
+    SetFuelStockpile,
+    /// Ask for a manual power value to use in place of the computed power, or clear it.
+    SetPowerOverride,
 }
 
 /// Display for a single AccountingGraph node.
@@ -161,6 +400,29 @@ pub struct NodeDisplay {
     /// Number of virtual insert markers requested. Used to prevent flicker, since
     /// dragenter happens for a new element before dragleave for the prior element.
     insert_count: usize,
+    /// Whether the balance filters of this group are being edited.
+    editing_filters: bool,
+    /// Whether the balance shows gross production and consumption as well as net rates.
+    show_gross: bool,
+    /// Whether a building's balance is shown for a single copy rather than all of its
+    /// copies. Not saved.
+    per_building: bool,
+    /// Whether the full balance is shown in the compact layout, rather than a summary.
+    compact_balance_expanded: bool,
+    /// Whether an item to solve for is being chosen.
+    choosing_target: bool,
+    /// Whether the primary product of this group is being chosen.
+    choosing_primary: bool,
+    /// Whether the color tag of this node is being chosen.
+    choosing_tag: bool,
+    /// Amount of fuel a generator's runtime is estimated for. Not saved.
+    fuel_stockpile: Option<f32>,
+    /// Whether the summary of items with several producers is shown.
+    show_combined_producers: bool,
+    /// Whether the menu of ways to add children is open in the compact layout.
+    add_menu_open: bool,
+    /// Reader for a factory file being imported into this group.
+    import_reader: Option<FileReader>,
 }
 
 impl Component for NodeDisplay {
@@ -168,9 +430,14 @@ impl Component for NodeDisplay {
     type Properties = Props;
 
     fn create(_: &Context<Self>) -> Self {
+        perf_overlay::node_mounted();
         Default::default()
     }
 
+    fn destroy(&mut self, _: &Context<Self>) {
+        perf_overlay::node_unmounted();
+    }
+
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         let our_idx = ctx.props().path.last().copied().unwrap_or_default();
         let db = ctx.db();
@@ -193,6 +460,30 @@ impl Component for NodeDisplay {
                 }
                 false
             }
+            Msg::WrapInGroup => {
+                if ctx.props().path.is_empty() {
+                    warn!("Cannot wrap the root node in a group");
+                } else {
+                    ctx.props().replace.emit((
+                        our_idx,
+                        graph_manipulation::wrap_in_group(&ctx.props().node),
+                    ));
+                }
+                false
+            }
+            Msg::ExpandIngredients => {
+                if ctx.props().node.building().is_none() {
+                    warn!("Cannot expand the ingredients of a non-building");
+                } else {
+                    match graph_manipulation::expand_ingredients(&ctx.props().node, &db) {
+                        Some(expanded) => ctx.props().replace.emit((our_idx, expanded)),
+                        None => {
+                            alert("None of this building's inputs can be produced by a recipe.")
+                        }
+                    }
+                }
+                false
+            }
             Msg::ReplaceChild { idx, replacement } => {
                 if let NodeKind::Group(group) = ctx.props().node.kind() {
                     if idx < group.children.len() {
@@ -211,20 +502,10 @@ impl Component for NodeDisplay {
                 false
             }
             Msg::DeleteChild { idx } => {
-                if let NodeKind::Group(group) = ctx.props().node.kind() {
-                    if idx < group.children.len() {
-                        let mut new_group = group.clone();
-                        new_group.children.remove(idx);
-                        ctx.props().replace.emit((our_idx, new_group.into()));
-                    } else {
-                        warn!(
-                            "Cannot delete child index {}; out of range for this group",
-                            idx
-                        );
-                    }
-                } else {
-                    warn!("Cannot delete child of a non-group");
-                }
+                // Deleted by the app, which confirms or offers to undo the deletion.
+                let mut path = ctx.props().path.clone();
+                path.push(idx);
+                ctx.props().delete_node.emit(path);
                 false
             }
             Msg::CopyChild { idx } => {
@@ -238,6 +519,8 @@ impl Component for NodeDisplay {
                                 new_meta.borrow_mut().insert(new.id, meta);
                             },
                         );
+                        let copied =
+                            graph_manipulation::with_unique_name(copied, &new_group.children);
                         new_group.children.insert(idx + 1, copied);
                         ctx.props().batch_set_metadata.emit(new_meta.into_inner());
                         ctx.props().replace.emit((our_idx, new_group.into()));
@@ -252,6 +535,89 @@ impl Component for NodeDisplay {
                 }
                 false
             }
+            Msg::ExportGroup => {
+                match ctx.props().node.group() {
+                    Some(group) => group::download_json(&group.name, group::export_group(group)),
+                    None => warn!("Cannot export a non-group"),
+                }
+                false
+            }
+            Msg::ExportPlanner => {
+                match ctx.props().node.group() {
+                    Some(group) => {
+                        let export = io::export(&ctx.props().node, &group.name, &db);
+                        if export.lost.is_empty()
+                            || confirm(&format!(
+                                "Satisfactory Tools can't represent everything in this group:\n{}\n\nExport anyway?",
+                                group::describe_lost(&export.lost)
+                            ))
+                        {
+                            let name = format!("{} satisfactory-tools", group.name);
+                            group::download_json(&name, export.to_json().to_string());
+                        }
+                    }
+                    None => warn!("Cannot export a non-group"),
+                }
+                false
+            }
+            Msg::CopyChildScaled { idx } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    self.copy_child_scaled(ctx, group, idx);
+                } else {
+                    warn!("Cannot copy child of a non-group");
+                }
+                false
+            }
+            Msg::SplitChild { idx } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    self.split_child(ctx, group, idx);
+                } else {
+                    warn!("Cannot split child of a non-group");
+                }
+                false
+            }
+            Msg::MatchSiblingDemand { idx } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    self.match_sibling_demand(ctx, group, idx);
+                } else {
+                    warn!("Cannot match demand in a non-group");
+                }
+                false
+            }
+            Msg::MergeWithNext { idx } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    self.merge_with_next(ctx, group, idx);
+                } else {
+                    warn!("Cannot merge children of a non-group");
+                }
+                false
+            }
+            Msg::ImportFile { file } => {
+                let loaded = ctx.link().callback(|result| Msg::ImportLoaded { result });
+                self.import_reader = Some(read_as_text(&file, move |result| loaded.emit(result)));
+                false
+            }
+            Msg::ImportLoaded { result } => {
+                self.import_reader = None;
+                let imported = result
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| group::load_import(&text, &db));
+                match (imported, ctx.props().node.kind()) {
+                    (Ok(node), NodeKind::Group(group)) => {
+                        // Fresh ids keep a file imported twice from sharing groups.
+                        let mut new_group = group.clone();
+                        let node = graph_manipulation::with_unique_name(
+                            node.rebuild(&db).create_copy(),
+                            &new_group.children,
+                        );
+                        new_group.children.push(node);
+                        ctx.props().replace.emit((our_idx, new_group.into()));
+                    }
+                    (Ok(_), NodeKind::Building(_)) => warn!("Cannot import into a non-group"),
+                    (Err(e), _) => alert(&format!("Unable to import the file: {}", e)),
+                }
+                false
+            }
             Msg::AddChild { child } => {
                 if let NodeKind::Group(group) = ctx.props().node.kind() {
                     let mut new_group = group.clone();
@@ -260,6 +626,29 @@ impl Component for NodeDisplay {
                 } else {
                     warn!("Cannot add child to a non-group");
                 }
+                // Adding from the add menu closes it.
+                mem::take(&mut self.add_menu_open)
+            }
+            Msg::QuickAddBuilding { id } => {
+                let building = match db.get(id) {
+                    Some(building) => {
+                        let mut settings = building.get_default_settings();
+                        settings.set_clock_speed(ctx.document_settings().default_clock_speed());
+                        Building {
+                            building: Some(id),
+                            settings,
+                            ..Building::empty()
+                        }
+                    }
+                    None => {
+                        warn!("Cannot quick-add unknown building {:?}", id);
+                        return false;
+                    }
+                };
+                match building.build_node(&db) {
+                    Ok(child) => ctx.link().send_message(Msg::AddChild { child }),
+                    Err(e) => warn!("Unable to build node: {}", e),
+                }
                 false
             }
             Msg::Rename { name } => {
@@ -342,20 +731,222 @@ impl Component for NodeDisplay {
                     false
                 }
             }
+            Msg::BalanceGeneratorClocks => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    match graph_manipulation::balance_generator_clocks(group, &db) {
+                        Some(balanced) => {
+                            ctx.props().replace.emit((our_idx, balanced.group.into()));
+                            if let Some(needed) = balanced.generators_needed {
+                                alert(&format!(
+                                    "Generators cannot cover demand even at 250% clock \
+                                    speed. {} generators are needed.",
+                                    needed
+                                ));
+                            }
+                        }
+                        None => warn!("No generators to balance in this group"),
+                    }
+                } else {
+                    warn!("Cannot balance generators of a non-group");
+                }
+                false
+            }
+            Msg::Flatten => {
+                if ctx.props().path.is_empty() {
+                    warn!("Cannot flatten the root node");
+                } else if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let keep_going = group.name.is_empty()
+                        || confirm(&format!(
+                            "Ungrouping will discard the group name \"{}\". Continue?",
+                            group.name
+                        ));
+                    if keep_going {
+                        match graph_manipulation::flatten_group(group, &db) {
+                            Some(child) => ctx.props().replace.emit((our_idx, child)),
+                            None => warn!("Can only flatten a group with exactly one child"),
+                        }
+                    }
+                } else {
+                    warn!("Cannot flatten a non-group");
+                }
+                false
+            }
+            Msg::ScaleToPowerBudget => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    self.scale_to_power_budget(ctx, group);
+                } else {
+                    warn!("Cannot scale a non-group");
+                }
+                false
+            }
+            Msg::Normalize => {
+                if graph_manipulation::contains_locked(&ctx.props().node) {
+                    alert("This group contains locked buildings, so it can't be normalized.");
+                } else if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    match graph_manipulation::normalize_group(group, &db) {
+                        Some(normalized) => {
+                            ctx.props().replace.emit((our_idx, normalized.group.into()));
+                            if normalized.adjusted_clocks > 0 || normalized.inexact > 0 {
+                                let mut message =
+                                    String::from("Some buildings could not be divided evenly.");
+                                if normalized.adjusted_clocks > 0 {
+                                    message.push_str(&format!(
+                                        "\n{} building(s) had their clock speed reduced instead.",
+                                        normalized.adjusted_clocks
+                                    ));
+                                }
+                                if normalized.inexact > 0 {
+                                    message.push_str(&format!(
+                                        "\n{} building(s) could not be scaled exactly.",
+                                        normalized.inexact
+                                    ));
+                                }
+                                alert(&message);
+                            }
+                        }
+                        None => warn!("Group already has a single copy"),
+                    }
+                } else {
+                    warn!("Cannot normalize a non-group");
+                }
+                false
+            }
+            Msg::SortChildren { key } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let new_group = graph_manipulation::sort_children(group, key, &db);
+                    ctx.props().replace.emit((our_idx, new_group.into()));
+                } else {
+                    warn!("Cannot sort children of a non-group");
+                }
+                false
+            }
+            Msg::RemoveEmptyGroups => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    match graph_manipulation::remove_empty_groups(group) {
+                        Some((new_group, removed)) => {
+                            ctx.props().replace.emit((our_idx, new_group.into()));
+                            alert(&format!("Removed {} empty group(s).", removed));
+                        }
+                        None => warn!("No empty groups to remove"),
+                    }
+                } else {
+                    warn!("Cannot remove empty groups from a non-group");
+                }
+                false
+            }
+            Msg::ToggleCombinedProducers => {
+                self.show_combined_producers = !self.show_combined_producers;
+                true
+            }
+            Msg::ToggleAddMenu => {
+                self.add_menu_open = !self.add_menu_open;
+                true
+            }
+            Msg::ToggleChoosingTarget { choosing } => {
+                self.choosing_target = choosing;
+                true
+            }
+            Msg::ToggleChoosingPrimary { choosing } => {
+                self.choosing_primary = choosing;
+                true
+            }
+            Msg::SetPrimaryProduct { item } => {
+                self.choosing_primary = false;
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    if group.primary_product != item {
+                        let mut new_group = group.clone();
+                        new_group.primary_product = item;
+                        ctx.props().replace.emit((our_idx, new_group.into()));
+                    }
+                } else {
+                    warn!("Cannot set the primary product of a non-group");
+                }
+                true
+            }
+            Msg::ToggleAssumedSupply { item } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let mut new_group = group.clone();
+                    if !new_group.assumed_supplied.remove(&item) {
+                        new_group.assumed_supplied.insert(item);
+                    }
+                    ctx.props().replace.emit((our_idx, new_group.into()));
+                } else {
+                    warn!("Cannot assume supply for a non-group");
+                }
+                false
+            }
+            Msg::SolveFor { id } => {
+                self.choosing_target = false;
+                let rate_unit = ctx.global_meta().rate_unit;
+                let rate = prompt(&format!("Target rate (items{}):", rate_unit.name()), None)
+                    .and_then(|rate| parse_number(&rate))
+                    .filter(|&rate| rate > 0.0)
+                    .map(|rate| rate_unit.unscale(rate));
+                match rate {
+                    Some(rate) => {
+                        let strategy = if confirm(
+                            "Prefer alternate recipes? Press Cancel to use standard recipes.",
+                        ) {
+                            RecipeStrategy::PreferAlternates
+                        } else {
+                            RecipeStrategy::Standard
+                        };
+                        let solution = solver::solve(&db, &[(id, rate)], strategy);
+                        if !solution.converged {
+                            alert(
+                                "Could not fully balance the production chain, possibly \
+                                because of cyclic recipes.",
+                            );
+                        }
+                        ctx.link().send_message(Msg::AddChild {
+                            child: solution.group.into(),
+                        });
+                    }
+                    None => warn!("No valid target rate entered"),
+                }
+                true
+            }
+            Msg::ToggleFilterEditing => {
+                self.editing_filters = !self.editing_filters;
+                true
+            }
+            Msg::ToggleGross => {
+                self.show_gross = !self.show_gross;
+                true
+            }
+            Msg::TogglePerBuilding => {
+                self.per_building = !self.per_building;
+                true
+            }
+            Msg::ToggleCompactBalance => {
+                self.compact_balance_expanded = !self.compact_balance_expanded;
+                true
+            }
             Msg::ChangeType { id } => {
                 if let NodeKind::Building(building) = ctx.props().node.kind() {
                     if building.building != Some(id) {
                         let mut new_bldg = building.clone();
                         new_bldg.building = Some(id);
                         match db.get(id) {
-                            Some(building) => {
+                            Some(building_type) => {
                                 new_bldg.settings =
-                                    new_bldg.settings.build_new_settings(&building.kind);
+                                    new_bldg.settings.build_new_settings(&building_type.kind);
+                                // Choosing the type of a new building is when it's added.
+                                if building.building.is_none() {
+                                    new_bldg.settings.set_clock_speed(
+                                        ctx.document_settings().default_clock_speed(),
+                                    );
+                                }
                             }
                             None => warn!("New building ID is unknown."),
                         }
                         match new_bldg.build_node(&db) {
-                            Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
+                            Ok(new_node) => {
+                                ctx.props().replace.emit((our_idx, new_node));
+                                ctx.props()
+                                    .set_global_metadata
+                                    .emit(ctx.global_meta().with_recent_building(id));
+                            }
                             Err(e) => warn!("Unable to build node: {}", e),
                         }
                     }
@@ -417,11 +1008,22 @@ impl Component for NodeDisplay {
                     ..building.clone()
                 };
                 match new_bldg.build_node(&db) {
-                    Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
+                    Ok(new_node) => {
+                        ctx.props().replace.emit((our_idx, new_node));
+                        ctx.props()
+                            .set_global_metadata
+                            .emit(ctx.global_meta().with_recent_recipe(id));
+                    }
                     Err(e) => warn!("Unable to build node: {}", e),
                 }
                 false
             }
+            Msg::ClearRecentRecipes => {
+                let mut global_meta = ctx.global_meta();
+                global_meta.recent_recipes.clear();
+                ctx.props().set_global_metadata.emit(global_meta);
+                false
+            }
             Msg::ChangeItem { id } => {
                 let building = match ctx.props().node.kind() {
                     NodeKind::Building(building) => building,
@@ -621,6 +1223,47 @@ impl Component for NodeDisplay {
 
                 false
             }
+            Msg::ApplyExtractionPreset {
+                building: id,
+                purity,
+            } => {
+                let building = match ctx.props().node.kind() {
+                    NodeKind::Building(building) => building,
+                    _ => {
+                        warn!("Cannot apply an extraction preset to a non-building");
+                        return false;
+                    }
+                };
+                let kind = match db.get(id) {
+                    Some(building_type) => &building_type.kind,
+                    None => {
+                        warn!("Extraction preset building is unknown");
+                        return false;
+                    }
+                };
+                let settings = match building.settings.build_new_settings(kind) {
+                    BuildingSettings::Miner(ms) => MinerSettings {
+                        purity,
+                        clock_speed: 1.0,
+                        ..ms
+                    }
+                    .into(),
+                    _ => {
+                        warn!("Extraction preset building is not a miner");
+                        return false;
+                    }
+                };
+                let new_bldg = Building {
+                    building: Some(id),
+                    settings,
+                    ..building.clone()
+                };
+                match new_bldg.build_node(&db) {
+                    Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
+                    Err(e) => warn!("Unable to build node: {}", e),
+                }
+                false
+            }
             Msg::ChangePumpPurity { purity, num_pads } => {
                 let building = match ctx.props().node.kind() {
                     NodeKind::Building(building) => building,
@@ -699,6 +1342,103 @@ impl Component for NodeDisplay {
 
                 false
             }
+            Msg::ToggleLocked => {
+                if let NodeKind::Building(building) = ctx.props().node.kind() {
+                    let new_bldg = Building {
+                        locked: !building.locked,
+                        ..building.clone()
+                    };
+                    match new_bldg.clone().build_node(&db) {
+                        Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
+                        Err(e) => ctx
+                            .props()
+                            .replace
+                            .emit((our_idx, e.into_warning_node(new_bldg))),
+                    }
+                } else {
+                    warn!("Cannot lock a non-building");
+                }
+                false
+            }
+            Msg::ToggleChoosingTag { choosing } => {
+                self.choosing_tag = choosing;
+                true
+            }
+            Msg::SetTag { tag } => {
+                self.choosing_tag = false;
+                match ctx.props().node.kind() {
+                    NodeKind::Group(group) => {
+                        let mut new_group = group.clone();
+                        new_group.tag = tag;
+                        ctx.props().replace.emit((our_idx, new_group.into()));
+                    }
+                    NodeKind::Building(building) => {
+                        let new_bldg = Building {
+                            tag,
+                            ..building.clone()
+                        };
+                        match new_bldg.clone().build_node(&db) {
+                            Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
+                            Err(e) => ctx
+                                .props()
+                                .replace
+                                .emit((our_idx, e.into_warning_node(new_bldg))),
+                        }
+                    }
+                }
+                true
+            }
+            Msg::SetFuelStockpile => {
+                let current = self.fuel_stockpile.map(|amount| amount.to_string());
+                match prompt("Fuel stockpile (leave empty to clear):", current.as_deref()) {
+                    Some(text) if text.trim().is_empty() => self.fuel_stockpile = None,
+                    Some(text) => match parse_number(&text).filter(|&amount| amount > 0.0) {
+                        Some(amount) => self.fuel_stockpile = Some(amount),
+                        None => warn!("Invalid fuel stockpile {:?}", text),
+                    },
+                    None => return false,
+                }
+                true
+            }
+            Msg::SetPowerOverride => {
+                let building = match ctx.props().node.kind() {
+                    NodeKind::Building(building) => building,
+                    _ => {
+                        warn!("Cannot override the power of a non-building");
+                        return false;
+                    }
+                };
+                let current = building.power_override.map(|power| power.to_string());
+                let power_override = match prompt(
+                    "Power per building in MW, negative for consumption (leave empty to use \
+                    the computed power):",
+                    current.as_deref(),
+                ) {
+                    Some(text) if text.trim().is_empty() => None,
+                    Some(text) => match parse_number(&text) {
+                        Some(power) => Some(power),
+                        None => {
+                            warn!("Invalid power override {:?}", text);
+                            return false;
+                        }
+                    },
+                    None => return false,
+                };
+                if power_override != building.power_override {
+                    let new_bldg = Building {
+                        power_override,
+                        ..building.clone()
+                    };
+                    match new_bldg.clone().build_node(&db) {
+                        Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
+                        Err(e) => ctx
+                            .props()
+                            .replace
+                            .emit((our_idx, e.into_warning_node(new_bldg))),
+                    }
+                }
+                false
+            }
         }
     }
 
@@ -738,6 +1478,82 @@ impl NodeDisplay {
         }
     }
 
+    /// Creates the button to wrap this node in a new group, if it isn't the root.
+    fn wrap_button(&self, ctx: &Context<Self>) -> Html {
+        if ctx.props().path.is_empty() {
+            html! {}
+        } else {
+            let onclick = ctx.link().callback(|_| Msg::WrapInGroup);
+            html! {
+                <button {onclick} class="wrap" title="Wrap in Group">
+                    <span class="material-icons">{"folder"}</span>
+                </button>
+            }
+        }
+    }
+
+    /// Creates the button to scale this building to its siblings' demand, if the parent
+    /// allows it.
+    fn match_demand_button(&self, ctx: &Context<Self>) -> Html {
+        match (ctx.props().match_demand.clone(), ctx.props().path.last()) {
+            (Some(match_demand), Some(&idx)) => {
+                let onclick = Callback::from(move |_| match_demand.emit(idx));
+                html! {
+                    <button {onclick} class="match-demand"
+                        title="Scale to Meet the Rest of the Group's Demand">
+                        <span class="material-icons">{"balance"}</span>
+                    </button>
+                }
+            }
+            _ => html! {},
+        }
+    }
+
+    /// Creates the button to copy this node at a different scale, if the parent allows it.
+    fn copy_scaled_button(&self, ctx: &Context<Self>) -> Html {
+        match (ctx.props().copy_scaled.clone(), ctx.props().path.last()) {
+            (Some(copy_scaled), Some(&idx)) => {
+                let onclick = Callback::from(move |_| copy_scaled.emit(idx));
+                html! {
+                    <button {onclick} class="copy-scaled" title="Copy at a Different Scale">
+                        <span class="material-icons">{"control_point_duplicate"}</span>
+                    </button>
+                }
+            }
+            _ => html! {},
+        }
+    }
+
+    /// Creates the button to split this building by proportion, if the parent allows it.
+    fn split_button(&self, ctx: &Context<Self>) -> Html {
+        match (ctx.props().split.clone(), ctx.props().path.last()) {
+            (Some(split), Some(&idx)) => {
+                let onclick = Callback::from(move |_| split.emit(idx));
+                html! {
+                    <button {onclick} class="split-building" title="Split Into Several Buildings">
+                        <span class="material-icons">{"alt_route"}</span>
+                    </button>
+                }
+            }
+            _ => html! {},
+        }
+    }
+
+    /// Creates the button to merge this group with the next one, if the parent allows it.
+    fn merge_button(&self, ctx: &Context<Self>) -> Html {
+        match (ctx.props().merge_next.clone(), ctx.props().path.last()) {
+            (Some(merge_next), Some(&idx)) => {
+                let onclick = Callback::from(move |_| merge_next.emit(idx));
+                html! {
+                    <button {onclick} class="merge-groups" title="Merge With the Group Below">
+                        <span class="material-icons">{"merge"}</span>
+                    </button>
+                }
+            }
+            _ => html! {},
+        }
+    }
+
     /// Creates the copy button, if the parent allows this node to be copied.
     fn copy_button(&self, ctx: &Context<Self>) -> Html {
         match ctx.props().copy.clone() {
@@ -760,9 +1576,446 @@ impl NodeDisplay {
     }
 }
 
-fn get_value_from_input_event(e: InputEvent) -> String {
+/// Parse a number entered by the user. Accepts plain decimals like `13.333` and fractions
+/// like `40/3`. Returns None if the text isn't a valid finite number.
+fn parse_number(text: &str) -> Option<f32> {
+    let value = match text.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator: f32 = numerator.trim().parse().ok()?;
+            let denominator: f32 = denominator.trim().parse().ok()?;
+            if denominator == 0.0 {
+                return None;
+            }
+            numerator / denominator
+        }
+        None => text.trim().parse().ok()?,
+    };
+    value.is_finite().then(|| value)
+}
+
+impl NodeDisplay {
+    /// Scale the building at `idx` so its main output meets what the rest of the group
+    /// consumes of that item, after confirming the new count and clock speed.
+    fn match_sibling_demand(&self, ctx: &Context<Self>, group: &Group, idx: usize) {
+        let building = match group.get_child(idx).and_then(Node::building) {
+            Some(building) => building,
+            None => {
+                warn!("Cannot match demand for child {}; not a building", idx);
+                return;
+            }
+        };
+        if building.locked {
+            alert("This building is locked, so it can't be scaled.");
+            return;
+        }
+        let db = ctx.db();
+        let rate_unit = ctx.global_meta().rate_unit;
+        let balance = group.children[idx].balance();
+        let (item, rate) = match balance
+            .balances
+            .iter()
+            .filter(|(_, &rate)| rate > 0.0)
+            .max_by(|(_, r1), (_, r2)| r1.total_cmp(r2))
+        {
+            Some((&item, &rate)) => (item, rate),
+            None => {
+                alert("This building doesn't produce anything.");
+                return;
+            }
+        };
+        let name = db
+            .get(item)
+            .map_or("Unknown Item", |item| item.name.as_ref());
+        let demand = -group
+            .children
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != idx)
+            .filter_map(|(_, sibling)| sibling.balance().balances.get(&item))
+            .sum::<f32>();
+        if demand <= ctx.global_meta().balance_tolerance() {
+            alert(&format!(
+                "Nothing else in this group has a net demand for {}.",
+                name
+            ));
+            return;
+        }
+        let clock_speed = building.settings.clock_speed();
+        let scaled = graph_manipulation::scale_to_rate(building.copies, clock_speed, rate, demand);
+        let DemandScale {
+            copies,
+            clock_speed,
+            needed,
+        } = match scaled {
+            Some(scaled) => scaled,
+            None => {
+                alert(&format!(
+                    "{} of {} is too little for one building at the minimum clock speed.",
+                    rate_unit.format(demand),
+                    name
+                ));
+                return;
+            }
+        };
+        let mut settings = building.settings.clone();
+        settings.set_clock_speed(clock_speed);
+        let mut message = format!(
+            "Meeting the demand of {} {} needs {:.3} buildings at 100%.\n\
+            Set to {}x at {:.2}% (from {}x at {:.2}%)?",
+            rate_unit.format(demand),
+            name,
+            needed,
+            copies,
+            clock_speed * 100.0,
+            building.copies,
+            building.settings.clock_speed() * 100.0,
+        );
+        if (needed - needed.round()).abs() > 1e-3 {
+            message.push_str("\nThis isn't a whole number of buildings at 100%.");
+        }
+        if settings.power_shards() > 0 {
+            message.push_str(&format!(
+                "\nThis needs {} power shards per building.",
+                settings.power_shards()
+            ));
+        }
+        if !confirm(&message) {
+            return;
+        }
+        let new_bldg = Building {
+            settings,
+            copies,
+            ..building.clone()
+        };
+        let replacement = match new_bldg.clone().build_node(&db) {
+            Ok(node) => node,
+            Err(e) => e.into_warning_node(new_bldg),
+        };
+        let our_idx = ctx.props().path.last().copied().unwrap_or_default();
+        let mut new_group = group.clone();
+        new_group.children[idx] = replacement;
+        ctx.props().replace.emit((our_idx, new_group.into()));
+    }
+
+    /// Merge the group at `idx` with the group after it, asking for the name of the merged
+    /// group if they have different names.
+    fn merge_with_next(&self, ctx: &Context<Self>, group: &Group, idx: usize) {
+        let (first, second) = match (
+            group.get_child(idx).and_then(Node::group),
+            group.get_child(idx + 1).and_then(Node::group),
+        ) {
+            (Some(first), Some(second)) => (first, second),
+            _ => {
+                warn!(
+                    "Cannot merge child {}; it and the next child must be groups",
+                    idx
+                );
+                return;
+            }
+        };
+        // Children of a group with more copies than the merged group are multiplied, which
+        // locked buildings are excluded from.
+        let copies = graph_manipulation::gcd(first.copies, second.copies);
+        let rescaled_locked = [first, second].into_iter().any(|g| {
+            g.copies != copies && g.children.iter().any(graph_manipulation::contains_locked)
+        });
+        if rescaled_locked {
+            alert(
+                "These groups have different copies and contain locked buildings, so they \
+                can't be merged.",
+            );
+            return;
+        }
+        let name = if first.name == second.name || second.name.is_empty() {
+            first.name.clone()
+        } else if first.name.is_empty() {
+            second.name.clone()
+        } else {
+            let combined = format!("{} + {}", first.name, second.name);
+            match prompt("Name of the merged group:", Some(&combined)) {
+                Some(name) => name.trim().to_string(),
+                None => return,
+            }
+        };
+        let merged = graph_manipulation::merge_groups(first, second, name, &ctx.db());
+        let our_idx = ctx.props().path.last().copied().unwrap_or_default();
+        let mut new_group = group.clone();
+        new_group.children[idx] = merged.into();
+        new_group.children.remove(idx + 1);
+        ctx.props().replace.emit((our_idx, new_group.into()));
+    }
+
+    /// Ask for a multiplier and insert a copy of the child at `idx` after it, scaled by
+    /// that multiplier, then report the copy's primary output.
+    fn copy_child_scaled(&self, ctx: &Context<Self>, group: &Group, idx: usize) {
+        let child = match group.children.get(idx) {
+            Some(child) => child,
+            None => {
+                warn!(
+                    "Cannot copy child index {}; out of range for this group",
+                    idx
+                );
+                return;
+            }
+        };
+        if graph_manipulation::contains_locked(child) {
+            alert("This contains locked buildings, so it can't be copied at a different scale.");
+            return;
+        }
+        let factor = prompt("Multiplier for the copy, like 2, 0.5, or 3/2:", Some("2"))
+            .and_then(|text| parse_number(text.trim().trim_end_matches(['x', 'X'])))
+            .filter(|&factor| factor > 0.0);
+        let factor = match factor {
+            Some(factor) => factor,
+            None => {
+                warn!("No valid multiplier entered");
+                return;
+            }
+        };
+        let db = ctx.db();
+        let new_meta = RefCell::new(HashMap::new());
+        let copied = child.create_copy_with_visitor(&|old: &Group, new: &mut Group| {
+            let meta = ctx.meta(old.id);
+            new_meta.borrow_mut().insert(new.id, meta);
+        });
+        let scaled = graph_manipulation::scale_node(&copied, factor, &db);
+        let mut new_group = group.clone();
+        let copied = graph_manipulation::with_unique_name(scaled.node.clone(), &new_group.children);
+        new_group.children.insert(idx + 1, copied);
+        ctx.props().batch_set_metadata.emit(new_meta.into_inner());
+        let our_idx = ctx.props().path.last().copied().unwrap_or_default();
+        ctx.props().replace.emit((our_idx, new_group.into()));
+
+        let rate_unit = ctx.global_meta().rate_unit;
+        let balances = &scaled.node.balance().balances;
+        let primary = scaled.node.primary_product().or_else(|| {
+            balances
+                .iter()
+                .filter(|(_, &rate)| rate > 0.0)
+                .max_by(|(_, r1), (_, r2)| r1.total_cmp(r2))
+                .map(|(&item, _)| item)
+        });
+        let mut message = format!("Copied at {}x.", (factor * 1000.0).round() / 1000.0);
+        match primary {
+            Some(item) => {
+                let name = db
+                    .get(item)
+                    .map_or("Unknown Item", |item| item.name.as_ref());
+                let rate = balances.get(&item).copied().unwrap_or_default();
+                message.push_str(&format!("\n{}: {}", name, rate_unit.format(rate)));
+            }
+            None => message.push_str("\nThe copy doesn't produce anything."),
+        }
+        if scaled.adjusted_clocks > 0 {
+            message.push_str(&format!(
+                "\n{} building(s) came out fractional, so their clock speed was reduced.",
+                scaled.adjusted_clocks
+            ));
+        }
+        if scaled.inexact > 0 {
+            message.push_str(&format!(
+                "\n{} building(s) could not be scaled exactly.",
+                scaled.inexact
+            ));
+        }
+        alert(&message);
+    }
+
+    /// Ask for proportions and replace the building at the given index with one building
+    /// per proportion, which together produce what it did. This models a manifold feeding
+    /// several consumers, each with a separate building.
+    fn split_child(&self, ctx: &Context<Self>, group: &Group, idx: usize) {
+        let building = match group.get_child(idx).and_then(Node::building) {
+            Some(building) => building,
+            None => {
+                warn!(
+                    "Cannot split child index {}; not a building in this group",
+                    idx
+                );
+                return;
+            }
+        };
+        if building.locked {
+            alert("This building is locked, so it can't be split.");
+            return;
+        }
+        let proportions = prompt(
+            "Proportions to split the building into, separated by colons, like 1:1 or 2:1:1:",
+            Some("1:1"),
+        )
+        .and_then(|text| {
+            text.split(':')
+                .map(parse_number)
+                .collect::<Option<Vec<_>>>()
+        });
+        let db = ctx.db();
+        let split = match proportions
+            .and_then(|proportions| graph_manipulation::split_building(building, &proportions, &db))
+        {
+            Some(split) => split,
+            None => {
+                warn!("No valid proportions entered");
+                return;
+            }
+        };
+        let mut new_group = group.clone();
+        new_group.children.splice(idx..=idx, split.nodes);
+        let our_idx = ctx.props().path.last().copied().unwrap_or_default();
+        ctx.props().replace.emit((our_idx, new_group.into()));
+        if split.inexact > 0 {
+            alert(&format!(
+                "{} of the new buildings couldn't be set exactly, so the total output changed.",
+                split.inexact
+            ));
+        }
+    }
+
+    /// Ask for a power budget and set the copies of the group to the most that fit, after
+    /// previewing the resulting outputs.
+    fn scale_to_power_budget(&self, ctx: &Context<Self>, group: &Group) {
+        if graph_manipulation::contains_locked(&ctx.props().node) {
+            alert("This group contains locked buildings, so it can't be scaled.");
+            return;
+        }
+        let global_meta = ctx.global_meta();
+        let power_unit = global_meta.power_unit;
+        let balance = ctx.props().node.balance();
+        let copies = group.copies.max(1);
+        let per_copy = -balance.power / copies as f32;
+        let budget = prompt(
+            &format!(
+                "Maximum power for this group in MW. Each copy uses {}.",
+                power_unit.format(per_copy)
+            ),
+            Some(&format!("{:.2}", -balance.power)),
+        )
+        .and_then(|budget| parse_number(&budget))
+        .filter(|&budget| budget >= 0.0);
+        let budget = match budget {
+            Some(budget) => budget,
+            None => {
+                warn!("No valid power budget entered");
+                return;
+            }
+        };
+        let new_copies =
+            match graph_manipulation::copies_within_power_budget(balance.power, copies, budget) {
+                Some(new_copies) => new_copies,
+                None => {
+                    alert("This group doesn't consume power.");
+                    return;
+                }
+            };
+        if new_copies == 0 {
+            alert(&format!(
+                "The budget is less than the {} used by a single copy.",
+                power_unit.format(per_copy)
+            ));
+            return;
+        }
+        if new_copies == copies {
+            alert(&format!(
+                "The group already uses as much of the budget as it can at {}x.",
+                copies
+            ));
+            return;
+        }
+        if new_copies < copies
+            && !confirm(&format!(
+                "The budget only allows {}x, fewer than the current {}x. Scale down?",
+                new_copies, copies
+            ))
+        {
+            return;
+        }
+        let db = ctx.db();
+        let factor = new_copies as f32 / copies as f32;
+        let mut preview = format!(
+            "Scale to {}x (from {}x)?\nPower: {}",
+            new_copies,
+            copies,
+            power_unit.format(balance.power * factor)
+        );
+        for (&item, &rate) in &balance.balances {
+            if global_meta.rate_style(rate) == "positive" {
+                let name = db
+                    .get(item)
+                    .map_or("Unknown Item", |item| item.name.as_ref());
+                let rate = global_meta.rate_unit.format(rate * factor);
+                preview.push_str(&format!("\n{}: {}", name, rate));
+            }
+        }
+        if confirm(&preview) {
+            ctx.link()
+                .send_message(Msg::SetCopyCount { copies: new_copies });
+        }
+    }
+}
+
+pub(crate) fn get_value_from_input_event(e: InputEvent) -> String {
     let event: Event = e.dyn_into().unwrap();
     let event_target = event.target().unwrap();
     let target: HtmlInputElement = event_target.dyn_into().unwrap();
     target.value()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_number_decimals() {
+        assert_eq!(parse_number("1.5"), Some(1.5));
+        assert_eq!(parse_number(" 2 "), Some(2.0));
+        assert_eq!(parse_number("abc"), None);
+        assert_eq!(parse_number(""), None);
+        assert_eq!(parse_number("inf"), None);
+    }
+
+    #[test]
+    fn power_filters_like_an_item() {
+        let item = ItemId::from("Desc_IronIngot_C");
+        let meta = NodeMeta::default();
+        assert!(meta.shows_power());
+        assert!(!meta.with_power_hidden_toggled().shows_power());
+        assert!(meta.with_power_hidden_toggled().has_filters());
+        // Showing only an item hides power, unless power is also shown.
+        let only_item = meta.with_shown_toggled(item);
+        assert!(!only_item.shows_power());
+        assert!(only_item.with_power_shown_toggled().shows_power());
+        // Showing only power hides the items.
+        let only_power = meta.with_power_shown_toggled();
+        assert!(!only_power.shows_item(item));
+        assert!(!only_power.without_filters().has_filters());
+    }
+
+    #[test]
+    fn parse_number_fractions() {
+        assert_eq!(parse_number("40/3"), Some(40.0 / 3.0));
+        assert_eq!(parse_number(" 1.5 / 2 "), Some(0.75));
+        assert_eq!(parse_number("1/0"), None);
+        assert_eq!(parse_number("1/"), None);
+        assert_eq!(parse_number("1/2/3"), None);
+    }
+
+    #[test]
+    fn collapse_below_depth_keeps_saved_state() {
+        use satisfactory_accounting::accounting::Group;
+
+        let deep = Group::empty();
+        let saved = Group::empty();
+        let mut middle = Group::empty();
+        middle.children.push(deep.clone().into());
+        middle.children.push(saved.clone().into());
+        let mut root = Group::empty();
+        root.children.push(middle.clone().into());
+        let root: Node = root.into();
+
+        let mut metadata = NodeMetadata::default();
+        metadata.set_meta(saved.id, NodeMeta::default());
+        metadata.collapse_below_depth(&root, 1);
+        assert!(!metadata.meta(middle.id).is_collapsed());
+        assert!(metadata.meta(deep.id).is_collapsed());
+        assert!(!metadata.meta(saved.id).is_collapsed());
+    }
+}
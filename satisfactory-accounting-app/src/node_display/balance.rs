@@ -5,52 +5,505 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
+use std::collections::BTreeMap;
 use std::rc::Rc;
 
+use log::warn;
+use satisfactory_accounting::database::{Database, ItemCategory, ItemId};
+use uuid::Uuid;
 use yew::prelude::*;
 
-use super::NodeDisplay;
+use super::{Msg, NodeDisplay, NodeMeta};
+use crate::app::{BeltTier, GlobalMetadata, RateUnit};
 use crate::node_display::icon::Icon;
 use crate::CtxHelper;
 
 impl NodeDisplay {
     /// Build the display for a node's balance.
     pub(super) fn view_balance(&self, ctx: &Context<Self>, vertical: bool) -> Html {
-        thread_local! {
-            static POWER_LINE: Rc<str> = "power-line".into();
-        }
-
         let balance = ctx.props().node.balance();
-        let db = ctx.db();
-        html! {
-            <div class={classes!("balance", balance_block_style(vertical))} title="Power">
-                <div class={classes!("entry-row", "power-entry", balance_style(balance.power))}>
-                    <Icon icon={POWER_LINE.with(Clone::clone)}/>
-                    <div class="balance-value">{rounded(balance.power)}</div>
-                </div>
-                { for balance.balances.iter().map(|(&itemid, &rate)| match db.get(itemid) {
-                    Some(item) => html! {
-                        <div class={classes!("entry-row", balance_style(rate))}
-                            title={Some(item.name.clone())}>
-                            <Icon icon={item.image.clone()}/>
-                            <div class="balance-value">{rounded(rate)}</div>
+        let per_building = self.per_building_copies(ctx);
+        let shown_rate = |rate: f32| per_building.map_or(rate, |copies| rate / copies as f32);
+        let db = match ctx.try_db() {
+            Some(db) => db,
+            None => {
+                warn!("Database context is not set; unable to show balance");
+                return html! {
+                    <div class={classes!("balance", "unavailable", balance_block_style(vertical))}>
+                        {"Database unavailable"}
+                    </div>
+                };
+            }
+        };
+        let global_meta = ctx.global_meta();
+        let rate_unit = global_meta.rate_unit;
+        let reference_belt = ctx.reference_belt();
+        // Only groups have metadata, so only groups can filter their balance.
+        let group_meta = ctx
+            .props()
+            .node
+            .group()
+            .map(|group| (group.id, ctx.meta(group.id)));
+        let editing_filters = self.editing_filters && group_meta.is_some();
+        let assumed_supplied = ctx
+            .props()
+            .node
+            .group()
+            .map(|group| &group.assumed_supplied);
+        let is_assumed =
+            |itemid: ItemId| assumed_supplied.is_some_and(|items| items.contains(&itemid));
+        let compact = ctx.compact_layout();
+        if compact && !self.compact_balance_expanded && !editing_filters {
+            return self.view_balance_summary(ctx, vertical);
+        }
+        // Groups show how much of each item's demand is met, from the gross balance.
+        let supply = group_meta.is_some().then(|| ctx.props().node.gross());
+        let gross = supply.filter(|_| self.show_gross);
+        let mut entries: Vec<(ItemId, f32)> = balance
+            .balances
+            .iter()
+            .map(|(&itemid, &rate)| (itemid, shown_rate(rate)))
+            .filter(|&(itemid, _)| {
+                editing_filters
+                    || group_meta
+                        .as_ref()
+                        .is_none_or(|(_, meta)| meta.shows_item(itemid))
+            })
+            .collect();
+        // Balanced items are left out when hidden, but counted so it's clear they exist.
+        // Assumed supplies are always shown, since their deficit is hidden.
+        let hide_balanced = global_meta.hide_empty_balances && !editing_filters;
+        let mut hidden_balanced = 0;
+        entries.retain(|&(itemid, rate)| {
+            let balanced =
+                global_meta.item_rate_style(itemid, rate) == "neutral" && !is_assumed(itemid);
+            if hide_balanced && balanced {
+                hidden_balanced += 1;
+                false
+            } else {
+                true
+            }
+        });
+        // Pinned items go first, otherwise the order of the balance is kept.
+        entries.sort_by_key(|(itemid, _)| !global_meta.pinned_items.contains(itemid));
+        let view_entry = |(itemid, rate): (ItemId, f32)| {
+            let pinned = global_meta.pinned_items.contains(&itemid);
+            let toggle_pin = {
+                let set_global_metadata = ctx.props().set_global_metadata.clone();
+                let updated = global_meta.with_pin_toggled(itemid);
+                Callback::from(move |_: MouseEvent| set_global_metadata.emit(updated.clone()))
+            };
+            let (title, icon) = match db.get(itemid) {
+                Some(item) => (item.name.clone(), Some(item.image.clone())),
+                None => ("Unknown Item".into(), None),
+            };
+            let sunk = global_meta.sunk_items.contains(&itemid);
+            let assumed = is_assumed(itemid);
+            let style = global_meta.item_rate_style(itemid, rate);
+            match (editing_filters, &group_meta) {
+                (true, Some((id, meta))) => {
+                    let shown = meta.shows_item(itemid);
+                    let exclusive = meta.shown_items.contains(&itemid);
+                    let hidden = meta.hidden_items.contains(&itemid);
+                    let set_filter = |update: NodeMeta| {
+                        let set_metadata = ctx.props().set_metadata.clone();
+                        let update = (*id, update);
+                        Callback::from(move |_: MouseEvent| set_metadata.emit(update.clone()))
+                    };
+                    let toggle_assumed = ctx
+                        .link()
+                        .callback(move |_| Msg::ToggleAssumedSupply { item: itemid });
+                    let toggle_hidden = set_filter(meta.with_hidden_toggled(itemid));
+                    let toggle_shown = set_filter(meta.with_shown_toggled(itemid));
+                    let toggle_sunk = {
+                        let set_global_metadata = ctx.props().set_global_metadata.clone();
+                        let updated = global_meta.with_sink_toggled(itemid);
+                        Callback::from(move |_: MouseEvent| {
+                            set_global_metadata.emit(updated.clone())
+                        })
+                    };
+                    html! {
+                        <div class={classes!("entry-row", "editing-filter",
+                                style, (!shown).then(|| "filtered"))}
+                            {title}>
+                            <Icon {icon}/>
+                            <div class="balance-value">{rate_unit.round(rate)}</div>
+                            <button class={classes!("filter-toggle", hidden.then(|| "active"))}
+                                title="Hide this item" onclick={toggle_hidden}>
+                                <span class="material-icons">{"visibility_off"}</span>
+                            </button>
+                            <button class={classes!("filter-toggle", exclusive.then(|| "active"))}
+                                title="Show only selected items" onclick={toggle_shown}>
+                                <span class="material-icons">{"filter_center_focus"}</span>
+                            </button>
+                            <button class={classes!("filter-toggle", sunk.then(|| "active"))}
+                                title="Treat surplus as intentionally sunk (everywhere)"
+                                onclick={toggle_sunk}>
+                                <span class="material-icons">{"delete_sweep"}</span>
+                            </button>
+                            <button class={classes!("filter-toggle", assumed.then(|| "active"))}
+                                title="Assume supplied externally (hide deficits in this group)"
+                                onclick={toggle_assumed}>
+                                <span class="material-icons">{"local_shipping"}</span>
+                            </button>
                         </div>
-                    },
-                    None => html! {
-                        <div class={classes!("entry-row", balance_style(rate))}
-                            title="Unknown Item">
-                            <Icon />
-                            <div class="balance-value">{rounded(rate)}</div>
+                    }
+                }
+                _ => {
+                    let title = match reference_belt.map(|belt| belt.capacity_for(itemid)) {
+                        Some((reference, _)) => {
+                            let rate = rate_unit.format(rate);
+                            format!("{}: {} of {}", title, rate, reference).into()
+                        }
+                        None => title,
+                    };
+                    let title = if assumed {
+                        format!("{} (assumed supplied externally)", title).into()
+                    } else {
+                        title
+                    };
+                    html! {
+                        <div class={classes!("entry-row", style,
+                                pinned.then(|| "pinned"), assumed.then(|| "assumed-supplied"))}
+                            {title} onclick={toggle_pin}>
+                            if pinned {
+                                <span class="material-icons pin">{"push_pin"}</span>
+                            }
+                            if sunk {
+                                <span class="material-icons sunk">{"delete_sweep"}</span>
+                            }
+                            if assumed {
+                                <span class="material-icons assumed">{"local_shipping"}</span>
+                            }
+                            <Icon {icon}/>
+                            <div class="balance-value">
+                                {BeltTier::format_rate(reference_belt, rate_unit, itemid, rate)}
+                            </div>
+                            if let Some(supply) = supply {
+                                {supply_bar(supply.produced(itemid), supply.consumed(itemid), style, rate_unit)}
+                            }
+                            if let Some(gross) = gross {
+                                <div class="gross">
+                                    <span class="produced" title="Produced">
+                                        {format!("+{}", rate_unit.round(gross.produced(itemid)))}
+                                    </span>
+                                    <span class="consumed" title="Consumed">
+                                        {format!("-{}", rate_unit.round(gross.consumed(itemid)))}
+                                    </span>
+                                </div>
+                            }
                         </div>
                     }
+                }
+            }
+        };
+        let rows = if global_meta.group_balance_by_category && !editing_filters {
+            self.view_categorized_entries(ctx, &db, entries, view_entry)
+        } else {
+            html! { for entries.into_iter().map(view_entry) }
+        };
+        let power_entry = self.view_power_entry(
+            ctx,
+            shown_rate(balance.power),
+            group_meta.as_ref().map(|(id, meta)| (*id, meta)),
+            editing_filters,
+        );
+        // Power stays at the top while editing filters, so its toggles are easy to find.
+        let power_first = !global_meta.unpin_power || editing_filters;
+        html! {
+            <div class={classes!("balance", balance_block_style(vertical),
+                    per_building.is_some().then(|| "per-building-rates"))} title="Power">
+                if let Some(copies) = per_building {
+                    <div class="per-building-label"
+                        title={format!("Rates of one building; the totals are {} times this", copies)}>
+                        {"Per building"}
+                    </div>
+                }
+                if power_first {
+                    {power_entry.clone()}
+                }
+                {rows}
+                if !power_first {
+                    {power_entry}
+                }
+                if hidden_balanced > 0 {
+                    {self.hidden_balanced_entry(ctx, hidden_balanced)}
+                }
+                if editing_filters {
+                    {self.clear_filters_button(ctx)}
+                }
+                if compact {
+                    <button class="collapse-balance" title="Collapse Balance"
+                        onclick={ctx.link().callback(|_| Msg::ToggleCompactBalance)}>
+                        <span class="material-icons">{"expand_less"}</span>
+                    </button>
+                }
+            </div>
+        }
+    }
+
+    /// Build balance entries grouped under collapsible headers by item category. Pinned
+    /// items stay at the top, outside of any category.
+    fn view_categorized_entries(
+        &self,
+        ctx: &Context<Self>,
+        db: &Database,
+        entries: Vec<(ItemId, f32)>,
+        view_entry: impl Fn((ItemId, f32)) -> Html,
+    ) -> Html {
+        let global_meta = ctx.global_meta();
+        let rate_unit = global_meta.rate_unit;
+        let (pinned, unpinned): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|(itemid, _)| global_meta.pinned_items.contains(itemid));
+        let mut categories: BTreeMap<ItemCategory, Vec<(ItemId, f32)>> = BTreeMap::new();
+        for (itemid, rate) in unpinned {
+            categories
+                .entry(db.item_category(itemid))
+                .or_default()
+                .push((itemid, rate));
+        }
+        html! {
+            <>
+                { for pinned.into_iter().map(&view_entry) }
+                { for categories.into_iter().map(|(category, entries)| {
+                    let expanded = global_meta.expanded_item_categories.contains(&category);
+                    let subtotal: f32 = entries.iter().map(|(_, rate)| rate).sum();
+                    let name = category.name(db);
+                    let toggle = {
+                        let set_global_metadata = ctx.props().set_global_metadata.clone();
+                        let updated = global_meta.with_category_toggled(category);
+                        Callback::from(move |_: MouseEvent| {
+                            set_global_metadata.emit(updated.clone())
+                        })
+                    };
+                    let title = format!("{} ({} items)", name, entries.len());
+                    html! {
+                        <>
+                            <div class={classes!("entry-row", "category-header",
+                                    global_meta.rate_style(subtotal))}
+                                {title} onclick={toggle}>
+                                <span class="material-icons">
+                                    {if expanded { "expand_more" } else { "chevron_right" }}
+                                </span>
+                                <span class="category-name">{name}</span>
+                                <div class="balance-value">{rate_unit.round(subtotal)}</div>
+                            </div>
+                            if expanded {
+                                { for entries.into_iter().map(&view_entry) }
+                            }
+                        </>
+                    }
                 }) }
+            </>
+        }
+    }
+
+    /// Build the power entry of a balance, or nothing if the group's filters hide it.
+    /// Clicking it moves power between the top of every balance and after the items.
+    fn view_power_entry(
+        &self,
+        ctx: &Context<Self>,
+        power: f32,
+        group_meta: Option<(Uuid, &NodeMeta)>,
+        editing_filters: bool,
+    ) -> Html {
+        thread_local! {
+            static POWER_LINE: Rc<str> = "power-line".into();
+        }
+
+        let global_meta = ctx.global_meta();
+        let style = global_meta.rate_style(power);
+        let formatted = global_meta.power_unit.format(power);
+        let icon = POWER_LINE.with(Clone::clone);
+        match (editing_filters, group_meta) {
+            (true, Some((id, meta))) => {
+                let set_filter = |update: NodeMeta| {
+                    let set_metadata = ctx.props().set_metadata.clone();
+                    let update = (id, update);
+                    Callback::from(move |_: MouseEvent| set_metadata.emit(update.clone()))
+                };
+                let toggle_hidden = set_filter(meta.with_power_hidden_toggled());
+                let toggle_shown = set_filter(meta.with_power_shown_toggled());
+                html! {
+                    <div class={classes!("entry-row", "power-entry", "editing-filter", style,
+                            (!meta.shows_power()).then(|| "filtered"))}
+                        title="Power">
+                        <Icon {icon}/>
+                        <div class="balance-value">{formatted}</div>
+                        <button class={classes!("filter-toggle", meta.power_hidden.then(|| "active"))}
+                            title="Hide power" onclick={toggle_hidden}>
+                            <span class="material-icons">{"visibility_off"}</span>
+                        </button>
+                        <button class={classes!("filter-toggle", meta.power_shown.then(|| "active"))}
+                            title="Show only selected items" onclick={toggle_shown}>
+                            <span class="material-icons">{"filter_center_focus"}</span>
+                        </button>
+                    </div>
+                }
+            }
+            (_, meta) => {
+                if meta.is_some_and(|(_, meta)| !meta.shows_power()) {
+                    return html! {};
+                }
+                let unpinned = global_meta.unpin_power;
+                let title = if unpinned {
+                    "Power (click to list it first)"
+                } else {
+                    "Power (click to list it after the items)"
+                };
+                let set_global_metadata = ctx.props().set_global_metadata.clone();
+                let updated = GlobalMetadata {
+                    unpin_power: !unpinned,
+                    ..global_meta
+                };
+                let onclick =
+                    Callback::from(move |_: MouseEvent| set_global_metadata.emit(updated.clone()));
+                html! {
+                    <div class={classes!("entry-row", "power-entry", style)} {title} {onclick}>
+                        <Icon {icon}/>
+                        <div class="balance-value">{formatted}</div>
+                    </div>
+                }
+            }
+        }
+    }
+
+    /// Build the entry noting how many balanced items are hidden, which shows them again
+    /// when clicked.
+    fn hidden_balanced_entry(&self, ctx: &Context<Self>, count: usize) -> Html {
+        let set_global_metadata = ctx.props().set_global_metadata.clone();
+        let updated = GlobalMetadata {
+            hide_empty_balances: false,
+            ..ctx.global_meta()
+        };
+        let onclick =
+            Callback::from(move |_: MouseEvent| set_global_metadata.emit(updated.clone()));
+        let title = if count == 1 {
+            "1 balanced item hidden".to_string()
+        } else {
+            format!("{} balanced items hidden", count)
+        };
+        html! {
+            <div class="entry-row hidden-balanced" {title} {onclick}>
+                <span class="material-icons">{"visibility_off"}</span>
+                <div class="balance-value">{format!("{} balanced", count)}</div>
             </div>
         }
     }
+
+    /// Build a tappable one-line summary of the balance, used in place of the full
+    /// balance in the compact layout.
+    fn view_balance_summary(&self, ctx: &Context<Self>, vertical: bool) -> Html {
+        let balance = ctx.props().node.balance();
+        let power = match self.per_building_copies(ctx) {
+            Some(copies) => balance.power / copies as f32,
+            None => balance.power,
+        };
+        let global_meta = ctx.global_meta();
+        let deficits = balance
+            .balances
+            .iter()
+            .filter(|(_, &rate)| global_meta.is_deficit(rate))
+            .count();
+        let onclick = ctx.link().callback(|_| Msg::ToggleCompactBalance);
+        html! {
+            <button class={classes!("balance", "balance-summary", balance_block_style(vertical))}
+                title="Show Balance" {onclick}>
+                <span class={classes!("power", global_meta.rate_style(power))}>
+                    {global_meta.power_unit.format(power)}
+                </span>
+                <span class="items">{format!("{} items", balance.balances.len())}</span>
+                if deficits > 0 {
+                    <span class="deficits negative">{format!("{} short", deficits)}</span>
+                }
+                <span class="material-icons">{"expand_more"}</span>
+            </button>
+        }
+    }
+
+    /// Get the number of copies a building's balance is divided by to show the rates of
+    /// one building, if it is shown per building. The node's balance itself always holds
+    /// the totals.
+    pub(super) fn per_building_copies(&self, ctx: &Context<Self>) -> Option<u32> {
+        if !self.per_building {
+            return None;
+        }
+        ctx.props()
+            .node
+            .building()
+            .map(|building| building.copies)
+            .filter(|&copies| copies > 1)
+    }
+
+    /// Build a compact chip showing the node's net power.
+    pub(super) fn power_chip(&self, ctx: &Context<Self>) -> Html {
+        let per_building = self.per_building_copies(ctx);
+        let power = match per_building {
+            Some(copies) => ctx.props().node.balance().power / copies as f32,
+            None => ctx.props().node.balance().power,
+        };
+        let global_meta = ctx.global_meta();
+        let manual = ctx
+            .props()
+            .node
+            .building()
+            .is_some_and(|building| building.power_override.is_some());
+        let title = match (manual, per_building.is_some()) {
+            (true, true) => "Net Power per Building (set manually)",
+            (true, false) => "Net Power (set manually)",
+            (false, true) => "Net Power per Building",
+            (false, false) => "Net Power",
+        };
+        html! {
+            <span class={classes!("power-chip", global_meta.rate_style(power),
+                    manual.then(|| "manual"))} {title}>
+                <span class="material-icons">{"bolt"}</span>
+                <span>{global_meta.power_unit.format(power)}</span>
+            </span>
+        }
+    }
+
+    /// Button to clear all balance filters of this group.
+    fn clear_filters_button(&self, ctx: &Context<Self>) -> Html {
+        match ctx.props().node.group() {
+            Some(group) => {
+                let meta = ctx.meta(group.id);
+                let set_metadata = ctx.props().set_metadata.clone();
+                let update = (group.id, meta.without_filters());
+                let onclick = Callback::from(move |_| set_metadata.emit(update.clone()));
+                html! {
+                    <button class="clear-filters" title="Clear Filters" {onclick}
+                        disabled={!meta.has_filters()}>
+                        <span class="material-icons">{"filter_alt_off"}</span>
+                    </button>
+                }
+            }
+            None => html! {},
+        }
+    }
 }
 
-fn rounded(val: f32) -> f32 {
-    (val * 100.0).round() / 100.0
+/// Build a two-tone bar comparing the amount of an item produced to the amount consumed.
+/// The solid part is the demand which is met, and the rest is the surplus or the unmet
+/// demand, colored by `style`. Balanced items show a full neutral bar.
+fn supply_bar(produced: f32, consumed: f32, style: &'static str, rate_unit: RateUnit) -> Html {
+    let larger = produced.max(consumed);
+    let met = if larger > 0.0 {
+        produced.min(consumed) / larger
+    } else {
+        1.0
+    };
+    let title = format!(
+        "Produced {} / Consumed {}",
+        rate_unit.format(produced),
+        rate_unit.format(consumed)
+    );
+    html! {
+        <div class={classes!("supply-bar", style)} {title}>
+            <div class="met" style={format!("width: {:.1}%", met * 100.0)} />
+        </div>
+    }
 }
 
 fn balance_block_style(vertical: bool) -> &'static str {
@@ -60,13 +513,3 @@ fn balance_block_style(vertical: bool) -> &'static str {
         "horizontal"
     }
 }
-
-fn balance_style(balance: f32) -> &'static str {
-    if balance < 0.0 {
-        "negative"
-    } else if balance > 0.0 {
-        "positive"
-    } else {
-        "neutral"
-    }
-}
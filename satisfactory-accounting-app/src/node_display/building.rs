@@ -9,14 +9,17 @@ use satisfactory_accounting::accounting::{
     BuildError, Building, BuildingSettings, GeneratorSettings, GeothermalSettings,
     ManufacturerSettings, MinerSettings, PumpSettings, ResourcePurity, StationSettings,
 };
-use satisfactory_accounting::database::BuildingId;
+use satisfactory_accounting::database::{BuildingId, BuildingKind, ItemAmount};
 use yew::prelude::*;
 
 use crate::node_display::copies::VirtualCopies;
-use crate::node_display::{Msg, NodeDisplay};
+use crate::node_display::tag::tag_class;
+use crate::node_display::{dom_path, Msg, NodeDisplay};
+use crate::CtxHelper;
 
 use building_type::BuildingTypeDisplay;
 use clock::ClockSpeed;
+use extraction_presets::ExtractionPresets;
 use item::ItemDisplay;
 use multi_purity::MultiPurity;
 use purity::Purity;
@@ -24,8 +27,9 @@ use recipe::RecipeDisplay;
 use station_consumption::StationConsumption;
 
 mod building_type;
-mod choose_from_list;
+pub(crate) mod choose_from_list;
 mod clock;
+mod extraction_presets;
 mod item;
 mod multi_purity;
 mod purity;
@@ -38,28 +42,194 @@ impl NodeDisplay {
         let update_copies = ctx.link().callback(|copies| Msg::SetCopyCount { copies });
         let change_type = ctx.link().callback(|id| Msg::ChangeType { id });
         html! {
-            <div class="NodeDisplay building">
+            <div class={classes!("NodeDisplay", "building", building.locked.then(|| "locked"),
+                    building.tag.map(tag_class))}
+                data-path={dom_path(&ctx.props().path)}>
                 <div class="section">
                     {self.drag_handle(ctx)}
                     <div class="section spaced">
                         <BuildingTypeDisplay id={building.building} {change_type} />
                         {self.view_building_settings(ctx, building)}
                     </div>
+                    {self.power_chip(ctx)}
+                    {self.power_override_button(ctx, building)}
                 </div>
                 <div class="section">
                     if let Some(warning) = ctx.props().node.warning() {
                         {self.view_warning(warning)}
                     } else {
+                        if let Some(reason) = unconfigured_reason(building) {
+                            <span class="BuildError material-icons warning unconfigured"
+                                title={reason}>
+                                {"warning"}
+                            </span>
+                        }
                         {self.view_balance(ctx, false)}
                     }
+                    if ctx.global_meta().show_buildable_counts {
+                        {self.view_buildable_count(ctx, building)}
+                    }
+                    {self.view_power_shards(ctx, building)}
                     <VirtualCopies copies={building.copies} {update_copies} />
+                    {self.per_building_button(ctx, building)}
+                    {self.lock_button(ctx, building)}
+                    {self.tag_button(ctx)}
+                    {self.expand_ingredients_button(ctx)}
+                    {self.match_demand_button(ctx)}
+                    {self.split_button(ctx)}
+                    {self.wrap_button(ctx)}
                     {self.copy_button(ctx)}
+                    {self.copy_scaled_button(ctx)}
                     {self.delete_button(ctx)}
                 </div>
             </div>
         }
     }
 
+    /// Show the number of buildings at 100% clock speed this building is equivalent to,
+    /// along with the rounded-up number of buildings and the resulting overproduction.
+    fn view_buildable_count(&self, ctx: &Context<Self>, building: &Building) -> Html {
+        let ideal = building.copies as f32 * building.settings.clock_speed();
+        let whole = ideal.ceil();
+        let overproduction = whole - ideal;
+        if overproduction < 0.001 {
+            return html! {};
+        }
+        // The balance is linear in copies and clock speed, so the per-building rate is
+        // the balance divided by the ideal count.
+        let db = ctx.db();
+        let rate_unit = ctx.global_meta().rate_unit;
+        let title = ctx
+            .props()
+            .node
+            .balance()
+            .balances
+            .iter()
+            .filter(|(_, &rate)| rate > 0.0)
+            .map(|(&item, &rate)| {
+                let name = db
+                    .get(item)
+                    .map(|item| item.name.as_ref())
+                    .unwrap_or("Unknown");
+                format!(
+                    "+{} {}",
+                    rate_unit.format(rate / ideal * overproduction),
+                    name
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        html! {
+            <span class="buildable-count" {title}>
+                {format!("{:.1} → {} (+{:.1} overproduction)", ideal, whole, overproduction)}
+            </span>
+        }
+    }
+
+    /// Get a button to set or change the manual power of this building.
+    fn power_override_button(&self, ctx: &Context<Self>, building: &Building) -> Html {
+        let onclick = ctx.link().callback(|_| Msg::SetPowerOverride);
+        let title = match building.power_override {
+            Some(power) => format!(
+                "Power set manually to {} MW per building (click to change or clear)",
+                power
+            ),
+            None => "Set Power Manually".to_string(),
+        };
+        html! {
+            <button class={classes!("power-override",
+                    building.power_override.is_some().then(|| "active"))}
+                {onclick} {title}>
+                <span class="material-icons">{"edit"}</span>
+            </button>
+        }
+    }
+
+    /// Get a button to switch the balance between the totals of all copies and the rates of
+    /// one building. Only shown when there is more than one copy.
+    fn per_building_button(&self, ctx: &Context<Self>, building: &Building) -> Html {
+        if building.copies <= 1 {
+            return html! {};
+        }
+        let onclick = ctx.link().callback(|_| Msg::TogglePerBuilding);
+        let title = if self.per_building {
+            format!("Show Totals for All {} Buildings", building.copies)
+        } else {
+            "Show Rates per Building".to_string()
+        };
+        html! {
+            <button class={classes!("per-building", self.per_building.then(|| "active"))}
+                {onclick} {title}>
+                <span class="material-icons">{"looks_one"}</span>
+            </button>
+        }
+    }
+
+    /// Get a button to lock or unlock this building against bulk operations.
+    fn lock_button(&self, ctx: &Context<Self>, building: &Building) -> Html {
+        let onclick = ctx.link().callback(|_| Msg::ToggleLocked);
+        let (title, icon) = if building.locked {
+            ("Unlock (allow bulk changes)", "lock")
+        } else {
+            ("Lock (exclude from bulk changes)", "lock_open")
+        };
+        html! {
+            <button class={classes!("lock", building.locked.then(|| "active"))}
+                {onclick} {title}>
+                <span class="material-icons">{icon}</span>
+            </button>
+        }
+    }
+
+    /// Button to add producers for this building's inputs, if it consumes any items.
+    fn expand_ingredients_button(&self, ctx: &Context<Self>) -> Html {
+        let consumes_items = ctx
+            .props()
+            .node
+            .balance()
+            .balances
+            .values()
+            .any(|&rate| rate < 0.0);
+        if !consumes_items {
+            return html! {};
+        }
+        let onclick = ctx.link().callback(|_| Msg::ExpandIngredients);
+        html! {
+            <button class="expand-ingredients" {onclick}
+                title="Add Producers for Ingredients">
+                <span class="material-icons">{"input"}</span>
+            </button>
+        }
+    }
+
+    /// Show the number of power shards each copy of this building needs for its clock
+    /// speed, flagged if that is more than the unlocked shard slots.
+    fn view_power_shards(&self, ctx: &Context<Self>, building: &Building) -> Html {
+        let shards = building.settings.power_shards();
+        if shards == 0 {
+            return html! {};
+        }
+        let global_meta = ctx.global_meta();
+        let over = global_meta.exceeds_shard_slots(shards);
+        let title = match global_meta.power_shard_slots {
+            Some(slots) if over => format!(
+                "Needs {} power shards per building, but only {} slots are unlocked",
+                shards, slots
+            ),
+            _ => format!(
+                "Needs {} power shards per building ({} in total)",
+                shards,
+                building.power_shards()
+            ),
+        };
+        html! {
+            <span class={classes!("power-shards", over.then(|| "warning"))} {title}>
+                <span class="material-icons">{"diamond"}</span>
+                <span>{format!("×{}", shards)}</span>
+            </span>
+        }
+    }
+
     fn view_warning(&self, err: BuildError) -> Html {
         // TODO: give better error messages.
         html! {
@@ -103,16 +273,62 @@ impl NodeDisplay {
     ) -> Html {
         let link = ctx.link();
         let change_recipe = link.callback(|id| Msg::ChangeRecipe { id });
+        let clear_recent_recipes = link.callback(|()| Msg::ClearRecentRecipes);
         let update_speed = link.callback(|clock_speed| Msg::ChangeClockSpeed { clock_speed });
         html! {
             <>
                 <RecipeDisplay building_id={building} recipe_id={settings.recipe}
-                    {change_recipe} />
+                    {change_recipe} {clear_recent_recipes} />
                 <ClockSpeed clock_speed={settings.clock_speed} {update_speed} />
+                {self.view_cycle(ctx, building, settings)}
             </>
         }
     }
 
+    /// Display the effective cycle time of a manufacturer's recipe, with the items used
+    /// and made each cycle in the title.
+    fn view_cycle(
+        &self,
+        ctx: &Context<Self>,
+        building: BuildingId,
+        settings: &ManufacturerSettings,
+    ) -> Html {
+        let db = ctx.db();
+        let recipe = match settings.recipe.and_then(|id| db.get(id)) {
+            Some(recipe) => recipe,
+            None => return html! {},
+        };
+        let speed = match db.get(building).map(|building| &building.kind) {
+            Some(BuildingKind::Manufacturer(m)) => m.manufacturing_speed,
+            _ => return html! {},
+        };
+        let amounts = |amounts: &[ItemAmount]| {
+            amounts
+                .iter()
+                .map(|amount| {
+                    let name = db
+                        .get(amount.item)
+                        .map(|item| item.name.as_ref())
+                        .unwrap_or("Unknown");
+                    format!("{} {}", amount.amount, name)
+                })
+                .collect::<Vec<_>>()
+                .join(" + ")
+        };
+        let title = format!(
+            "Each cycle: {} → {}",
+            amounts(&recipe.ingredients),
+            amounts(&recipe.products)
+        );
+        let cycle_time = recipe.time / (speed * settings.clock_speed);
+        html! {
+            <span class="cycle-time" {title}>
+                <span class="material-icons">{"timer"}</span>
+                <span>{format!("{:.2} s", cycle_time)}</span>
+            </span>
+        }
+    }
+
     /// Display the settings for a miner.
     fn view_miner_settings(
         &self,
@@ -124,12 +340,16 @@ impl NodeDisplay {
         let change_item = link.callback(|id| Msg::ChangeItem { id });
         let update_speed = link.callback(|clock_speed| Msg::ChangeClockSpeed { clock_speed });
         let set_purity = link.callback(|purity| Msg::ChangePurity { purity });
+        let select_preset =
+            link.callback(|(building, purity)| Msg::ApplyExtractionPreset { building, purity });
         html! {
             <>
                 <ItemDisplay building_id={building} item_id={settings.resource}
                     {change_item} />
                 <ClockSpeed clock_speed={settings.clock_speed} {update_speed} />
                 <Purity purity={settings.purity} {set_purity} />
+                <ExtractionPresets resource={settings.resource} {building}
+                    purity={settings.purity} select={select_preset} />
             </>
         }
     }
@@ -149,10 +369,42 @@ impl NodeDisplay {
                 <ItemDisplay building_id={building} item_id={settings.fuel}
                     {change_item} />
                 <ClockSpeed clock_speed={settings.clock_speed} {update_speed} />
+                {self.view_fuel_burn(ctx, settings)}
             </>
         }
     }
 
+    /// Display the rate a generator burns fuel at, and how long the entered stockpile
+    /// would last.
+    fn view_fuel_burn(&self, ctx: &Context<Self>, settings: &GeneratorSettings) -> Html {
+        let burn_rate = match settings.fuel {
+            Some(fuel) => -ctx
+                .props()
+                .node
+                .balance()
+                .balances
+                .get(&fuel)
+                .copied()
+                .unwrap_or_default(),
+            None => return html! {},
+        };
+        if burn_rate <= 0.0 {
+            return html! {};
+        }
+        let onclick = ctx.link().callback(|_| Msg::SetFuelStockpile);
+        html! {
+            <span class="fuel-burn" title="Fuel Burned (click to enter a stockpile)" {onclick}>
+                <span class="material-icons">{"local_fire_department"}</span>
+                <span>{ctx.global_meta().rate_unit.format(burn_rate)}</span>
+                if let Some(stockpile) = self.fuel_stockpile {
+                    <span class="runtime">
+                        {format!("{} lasts {}", stockpile, format_duration(stockpile / burn_rate))}
+                    </span>
+                }
+            </span>
+        }
+    }
+
     /// Display the settings for a pump.
     fn view_pump_settings(
         &self,
@@ -209,3 +461,36 @@ impl NodeDisplay {
         }
     }
 }
+
+/// If the building is missing the setting it needs to produce or consume anything, get a
+/// description of what is missing.
+fn unconfigured_reason(building: &Building) -> Option<&'static str> {
+    if building.building.is_none() {
+        return Some("No building selected");
+    }
+    match &building.settings {
+        BuildingSettings::Manufacturer(settings) if settings.recipe.is_none() => {
+            Some("No recipe selected")
+        }
+        BuildingSettings::Miner(MinerSettings { resource: None, .. })
+        | BuildingSettings::Pump(PumpSettings { resource: None, .. }) => {
+            Some("No resource selected")
+        }
+        BuildingSettings::Generator(GeneratorSettings { fuel: None, .. })
+        | BuildingSettings::Station(StationSettings { fuel: None, .. }) => Some("No fuel selected"),
+        _ => None,
+    }
+}
+
+/// Format a duration given in minutes as days, hours and minutes.
+fn format_duration(minutes: f32) -> String {
+    let minutes = minutes.floor() as u64;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
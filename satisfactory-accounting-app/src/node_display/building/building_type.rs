@@ -76,6 +76,7 @@ impl Component for BuildingTypeDisplay {
                     image: html! {
                         <Icon icon={building.image.clone()}/>
                     },
+                    detail: None,
                 })
                 .collect();
 
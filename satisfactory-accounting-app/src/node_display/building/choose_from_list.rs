@@ -8,13 +8,12 @@
 use std::marker::PhantomData;
 use std::rc::Rc;
 
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
 use log::warn;
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlElement, HtmlInputElement};
 use yew::prelude::*;
 
+use crate::fuzzy;
 use crate::node_display::get_value_from_input_event;
 
 /// An option to choose from.
@@ -26,6 +25,8 @@ pub struct Choice<Id> {
     pub name: Rc<str>,
     /// Name of the image to show. This should be the the slug for the icon.
     pub image: Html,
+    /// Extra information shown after the name. Not used for matching.
+    pub detail: Option<Rc<str>>,
 }
 
 #[derive(PartialEq, Properties)]
@@ -36,6 +37,15 @@ pub struct Props<I: PartialEq> {
     pub selected: Callback<I>,
     /// Callback for when selection is cancelled.
     pub cancelled: Callback<()>,
+    /// Choice to highlight initially, so it is selected if nothing is typed.
+    #[prop_or_default]
+    pub initial: Option<I>,
+    /// Choices listed first, in order, while nothing is typed.
+    #[prop_or_default]
+    pub quick_picks: Vec<I>,
+    /// Callback to clear the quick picks. If set, a button to clear them is shown.
+    #[prop_or_default]
+    pub clear_quick_picks: Option<Callback<()>>,
 }
 
 /// Messages for [`ChooseFromList`].
@@ -62,7 +72,8 @@ pub struct ChooseFromList<I> {
     highlighted: usize,
     /// Filtered set of choices with their assigned scores.
     filtered: Vec<(i64, Choice<I>)>,
-    matcher: SkimMatcherV2,
+    /// Number of quick picks at the start of the filtered choices.
+    quick_pick_count: usize,
     /// Input element, for focusing.
     input_ref: NodeRef,
     _phantom: PhantomData<I>,
@@ -73,22 +84,27 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
     type Properties = Props<I>;
 
     fn create(ctx: &Context<Self>) -> Self {
-        let mut filtered: Vec<_> = ctx
-            .props()
-            .choices
-            .iter()
-            .cloned()
-            .map(|choice| (0, choice))
-            .collect();
-        filtered.sort_by(|(_, c1), (_, c2)| c1.name.cmp(&c2.name));
-        Self {
+        let mut chooser = Self {
             input: String::new(),
             highlighted: 0,
-            filtered,
-            matcher: Default::default(),
+            filtered: Vec::new(),
+            quick_pick_count: 0,
             input_ref: Default::default(),
             _phantom: PhantomData,
-        }
+        };
+        chooser.refilter(ctx);
+        chooser.highlighted = ctx
+            .props()
+            .initial
+            .and_then(|initial| chooser.filtered.iter().position(|(_, c)| c.id == initial))
+            .unwrap_or_default();
+        chooser
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        self.refilter(ctx);
+        self.highlighted = self.highlighted.min(self.filtered.len().saturating_sub(1));
+        true
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
@@ -125,19 +141,7 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
             Msg::UpdateInput { input } => {
                 if input != self.input {
                     self.input = input;
-                    self.filtered = ctx
-                        .props()
-                        .choices
-                        .iter()
-                        .filter_map(|choice| {
-                            self.matcher
-                                .fuzzy_match(&choice.name, &self.input)
-                                .map(|score| (score, choice.clone()))
-                        })
-                        .collect();
-                    self.filtered.sort_by(|(s1, c1), (s2, c2)| {
-                        s1.cmp(s2).then_with(|| c1.name.cmp(&c2.name))
-                    });
+                    self.refilter(ctx);
                     self.highlighted = 0;
                     true
                 } else {
@@ -157,6 +161,11 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let link = ctx.link();
+        let clear_quick_picks = ctx
+            .props()
+            .clear_quick_picks
+            .clone()
+            .filter(|_| self.quick_pick_count > 0);
         let highlighted = self.highlighted;
         let onkeydown = link.batch_callback(|e: KeyboardEvent| match &*e.key() {
             "Up" | "ArrowUp" => {
@@ -176,7 +185,8 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
         let onblur = link.batch_callback(|e: FocusEvent| {
             if let Some(target) = e.related_target() {
                 if let Ok(element) = target.dyn_into::<HtmlElement>() {
-                    if element.class_list().contains("available-item") {
+                    let classes = element.class_list();
+                    if classes.contains("available-item") || classes.contains("clear-quick-picks") {
                         return None;
                     } else {
                     }
@@ -201,8 +211,16 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
                     {onkeydown} {onkeyup} {oninput}
                     ref={self.input_ref.clone()} />
                 <div class="available">
+                    if let Some(clear) = clear_quick_picks {
+                        <button type="button" class="clear-quick-picks"
+                            title="Clear Recently Used" onclick={clear.reform(|_| ())}>
+                            <span class="material-icons">{"history_toggle_off"}</span>
+                            <span>{"Clear recent"}</span>
+                        </button>
+                    }
                     { for self.filtered.iter().enumerate().map(|(i, (_, item))| {
                         let selected = (i == self.highlighted).then(|| "selected");
+                        let quick_pick = (i < self.quick_pick_count).then(|| "quick-pick");
                         let onclick = link.callback(move |_|
                             Msg::Select {
                             filtered_idx: i,
@@ -211,10 +229,17 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
                             filtered_idx: i,
                         });
                         html! {
-                            <div tabindex="-1" class={classes!("available-item", selected)}
+                            <div tabindex="-1"
+                                class={classes!("available-item", selected, quick_pick)}
                                 {onclick} {onmouseenter}>
+                                if quick_pick.is_some() {
+                                    <span class="material-icons recent">{"history"}</span>
+                                }
                                 {item.image.clone()}
                                 <span>{&item.name}</span>
+                                if let Some(detail) = &item.detail {
+                                    <span class="detail">{detail}</span>
+                                }
                             </div>
                         }
                     }) }
@@ -233,3 +258,43 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
         }
     }
 }
+
+impl<I: PartialEq + Copy + Clone + 'static> ChooseFromList<I> {
+    /// Recompute the filtered choices from the current input. With no input, quick picks
+    /// come first and the remaining choices are sorted by name. Otherwise the best fuzzy
+    /// matches come first, with ties in alphabetical order.
+    fn refilter(&mut self, ctx: &Context<Self>) {
+        let props = ctx.props();
+        if self.input.trim().is_empty() {
+            let mut quick_picks: Vec<_> = props
+                .quick_picks
+                .iter()
+                .filter_map(|&id| props.choices.iter().find(|choice| choice.id == id))
+                .cloned()
+                .map(|choice| (0, choice))
+                .collect();
+            let mut rest: Vec<_> = props
+                .choices
+                .iter()
+                .filter(|choice| !props.quick_picks.contains(&choice.id))
+                .cloned()
+                .map(|choice| (0, choice))
+                .collect();
+            rest.sort_by(|(_, c1), (_, c2)| c1.name.cmp(&c2.name));
+            self.quick_pick_count = quick_picks.len();
+            quick_picks.extend(rest);
+            self.filtered = quick_picks;
+        } else {
+            self.filtered = props
+                .choices
+                .iter()
+                .filter_map(|choice| {
+                    fuzzy::score(&self.input, &choice.name).map(|score| (score, choice.clone()))
+                })
+                .collect();
+            self.filtered
+                .sort_by(|(s1, c1), (s2, c2)| s2.cmp(s1).then_with(|| c1.name.cmp(&c2.name)));
+            self.quick_pick_count = 0;
+        }
+    }
+}
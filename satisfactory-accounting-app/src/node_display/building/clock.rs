@@ -6,10 +6,11 @@
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
 use log::warn;
-use web_sys::HtmlInputElement;
+use web_sys::{Element, HtmlInputElement};
 use yew::prelude::*;
 
-use crate::node_display::get_value_from_input_event;
+use crate::node_display::wheel::WheelNudge;
+use crate::node_display::{get_value_from_input_event, parse_number};
 
 #[derive(Debug, PartialEq, Properties)]
 pub struct Props {
@@ -28,6 +29,8 @@ pub enum Msg {
     FinishEdit,
     /// Cancel editing without changing the value.
     Cancel,
+    /// Move the value by a number of steps without editing it.
+    Nudge { steps: i32 },
 }
 
 /// Display and editing for clock speed.
@@ -39,6 +42,10 @@ pub struct ClockSpeed {
     did_focus: bool,
     /// Input to focus on editing.
     input: NodeRef,
+    /// Value shown while not editing.
+    display: NodeRef,
+    /// Listener for scroll-wheel nudges. Only set while not editing.
+    wheel: Option<WheelNudge>,
 }
 
 impl Component for ClockSpeed {
@@ -62,7 +69,7 @@ impl Component for ClockSpeed {
             }
             Msg::FinishEdit => {
                 if let Some(edit_text) = self.edit_text.take() {
-                    if let Ok(value) = edit_text.parse::<f32>() {
+                    if let Some(value) = parse_number(&edit_text) {
                         ctx.props().update_speed.emit(value.clamp(0.01, 2.5));
                     }
                     true
@@ -75,6 +82,15 @@ impl Component for ClockSpeed {
                 self.edit_text = None;
                 true
             }
+            Msg::Nudge { steps } => {
+                // Nudges move between whole percentages.
+                let current = ctx.props().clock_speed;
+                let speed = (((current * 100.0).round() + steps as f32) / 100.0).clamp(0.01, 2.5);
+                if speed != current {
+                    ctx.props().update_speed.emit(speed);
+                }
+                false
+            }
         }
     }
 
@@ -104,7 +120,8 @@ impl Component for ClockSpeed {
             let value = ctx.props().clock_speed;
             let onclick = link.callback(move |_| Msg::StartEdit { input: value });
             html! {
-                <div class="ClockSpeed" title="Clock Speed" {onclick}>
+                <div class="ClockSpeed" title="Clock Speed" {onclick}
+                    ref={self.display.clone()}>
                     <span class="material-icons-outlined">{"timer"}</span>
                     <span class="current-speed">{value.to_string()}</span>
                 </div>
@@ -112,7 +129,14 @@ impl Component for ClockSpeed {
         }
     }
 
-    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+    fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+        if self.edit_text.is_some() {
+            self.wheel = None;
+        } else if self.wheel.is_none() {
+            self.wheel = self.display.cast::<Element>().map(|display| {
+                WheelNudge::new(&display, ctx.link().callback(|steps| Msg::Nudge { steps }))
+            });
+        }
         if !self.did_focus {
             if let Some(input) = self.input.cast::<HtmlInputElement>() {
                 if let Err(e) = input.focus() {
@@ -0,0 +1,140 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Table of standard extraction rates for choosing a miner and node purity together.
+
+use satisfactory_accounting::accounting::ResourcePurity;
+use satisfactory_accounting::database::{BuildingId, ItemId};
+use yew::prelude::*;
+
+use crate::node_display::building::purity::purity_icon;
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Resource being extracted. Nothing is shown if not set.
+    pub resource: Option<ItemId>,
+    /// Currently selected miner.
+    pub building: BuildingId,
+    /// Currently selected node purity.
+    pub purity: ResourcePurity,
+    /// Callback to switch to the miner and purity of a preset.
+    pub select: Callback<(BuildingId, ResourcePurity)>,
+}
+
+pub enum Msg {
+    /// Show or hide the table.
+    ToggleOpen { open: bool },
+    /// Choose a preset.
+    Select {
+        building: BuildingId,
+        purity: ResourcePurity,
+    },
+}
+
+/// Button which opens a miner tier by node purity table of extraction rates.
+#[derive(Default)]
+pub struct ExtractionPresets {
+    /// Whether the table is shown.
+    open: bool,
+}
+
+impl Component for ExtractionPresets {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Default::default()
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::ToggleOpen { open } => {
+                self.open = open;
+                true
+            }
+            Msg::Select { building, purity } => {
+                ctx.props().select.emit((building, purity));
+                self.open = false;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let resource = match ctx.props().resource {
+            Some(resource) => resource,
+            None => return html! {},
+        };
+        let link = ctx.link();
+        if !self.open {
+            let onclick = link.callback(|_| Msg::ToggleOpen { open: true });
+            return html! {
+                <button class="ExtractionPresets open-presets" title="Extraction Presets"
+                    {onclick}>
+                    <span class="material-icons">{"table_chart"}</span>
+                </button>
+            };
+        }
+        let db = ctx.db();
+        let presets = db.extraction_presets(resource);
+        let rate_unit = ctx.global_meta().rate_unit;
+        // Presets are sorted by rate, so miners appear from slowest to fastest.
+        let mut miners: Vec<BuildingId> = Vec::new();
+        for preset in &presets {
+            if !miners.contains(&preset.building) {
+                miners.push(preset.building);
+            }
+        }
+        let current = (ctx.props().building, ctx.props().purity);
+        let close = link.callback(|_| Msg::ToggleOpen { open: false });
+        html! {
+            <div class="ExtractionPresets">
+                <table>
+                    <tr>
+                        <th>
+                            <button class="close" title="Close" onclick={close}>
+                                <span class="material-icons">{"close"}</span>
+                            </button>
+                        </th>
+                        { for ResourcePurity::values().map(|purity| html! {
+                            <th title={purity.name()}>{purity_icon(purity)}</th>
+                        }) }
+                    </tr>
+                    { for miners.into_iter().map(|building| {
+                        let name = db
+                            .get(building)
+                            .map(|b| b.name.as_ref())
+                            .unwrap_or("Unknown Building");
+                        html! {
+                            <tr>
+                                <th>{name}</th>
+                                { for presets.iter().filter(|p| p.building == building).map(|p| {
+                                    let (building, purity) = (p.building, p.purity);
+                                    let onclick = link.callback(move |_| Msg::Select {
+                                        building,
+                                        purity,
+                                    });
+                                    let title = format!("{} on {} node", name, purity.name());
+                                    html! {
+                                        <td>
+                                            <button class={classes!("preset",
+                                                    (current == (building, purity)).then(|| "active"))}
+                                                {title} {onclick}>
+                                                {format!("{}{}", rate_unit.round(p.rate), rate_unit.name())}
+                                            </button>
+                                        </td>
+                                    }
+                                }) }
+                            </tr>
+                        }
+                    }) }
+                </table>
+            </div>
+        }
+    }
+}
@@ -107,11 +107,13 @@ impl Component for ItemDisplay {
                         image: html! {
                             <Icon icon={item.image.clone()}/>
                         },
+                        detail: None,
                     },
                     None => Choice {
                         id: item_id,
                         name: format!("Unknown Item {}", item_id).into(),
                         image: html! { <Icon /> },
+                        detail: None,
                     },
                 })
                 .collect();
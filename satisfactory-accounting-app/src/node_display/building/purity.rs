@@ -61,6 +61,7 @@ impl Component for Purity {
                     id: purity,
                     name: purity.name().into(),
                     image: purity_icon(purity),
+                    detail: None,
                 })
                 .collect();
             let selected = link.callback(|purity| Msg::Select { purity });
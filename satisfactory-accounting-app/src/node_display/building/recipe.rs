@@ -5,10 +5,13 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
+use std::rc::Rc;
+
 use log::warn;
-use satisfactory_accounting::database::{BuildingId, BuildingKind, RecipeId};
+use satisfactory_accounting::database::{BuildingId, BuildingKind, Database, Recipe, RecipeId};
 use yew::prelude::*;
 
+use crate::app::{PowerUnit, RateUnit};
 use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
 use crate::node_display::icon::Icon;
 use crate::CtxHelper;
@@ -17,13 +20,15 @@ use crate::CtxHelper;
 pub struct Props {
     /// Building used to choose which recipes are available.
     pub building_id: BuildingId,
-    /// ID of the selected building, if any.
+    /// ID of the selected recipe, if any.
     pub recipe_id: Option<RecipeId>,
-    /// Callback to change the type of this building.
+    /// Callback to change the recipe of this building.
     pub change_recipe: Callback<RecipeId>,
+    /// Callback to forget the recently used recipes.
+    pub clear_recent_recipes: Callback<()>,
 }
 
-/// Messages for [`BuildingTypeDisplay`]
+/// Messages for [`RecipeDisplay`]
 pub enum Msg {
     /// Switches in or out of editing.
     ToggleEdit {
@@ -83,8 +88,8 @@ impl Component for RecipeDisplay {
             }
             Some(building) => building,
         };
-        let recipes = if let BuildingKind::Manufacturer(m) = &building.kind {
-            &m.available_recipes
+        let (recipes, power) = if let BuildingKind::Manufacturer(m) = &building.kind {
+            (&m.available_recipes, m.power_consumption.power)
         } else {
             warn!(
                 "Cannot show recipes for building with kind {:?}",
@@ -92,8 +97,21 @@ impl Component for RecipeDisplay {
             );
             return html! {};
         };
+        // Alternate recipes hidden for this factory are still offered if already chosen.
+        let hide_alternates = ctx.document_settings().hide_alternate_recipes;
+        let recipes: Vec<RecipeId> = recipes
+            .iter()
+            .copied()
+            .filter(|&id| {
+                !hide_alternates
+                    || Some(id) == recipe_id
+                    || db.get(id).is_none_or(|recipe| !recipe.is_alternate)
+            })
+            .collect();
         let link = ctx.link();
         if self.editing {
+            let global_meta = ctx.global_meta();
+            let (rate_unit, power_unit) = (global_meta.rate_unit, global_meta.power_unit);
             let choices: Vec<_> = recipes
                 .iter()
                 .map(|&recipe_id| match db.get(recipe_id) {
@@ -103,20 +121,29 @@ impl Component for RecipeDisplay {
                         image: html! {
                             <Icon icon={recipe.image.clone()} />
                         },
+                        detail: Some(recipe_detail(&db, recipe, rate_unit, power_unit, power)),
                     },
                     None => Choice {
                         id: recipe_id,
                         name: format!("Unknown Recipe {}", recipe_id).into(),
                         image: html! { <Icon /> },
+                        detail: None,
                     },
                 })
                 .collect();
 
+            let quick_picks: Vec<_> = global_meta
+                .recent_recipes
+                .into_iter()
+                .filter(|id| recipes.contains(id))
+                .collect();
+            let clear_quick_picks = Some(ctx.props().clear_recent_recipes.clone());
             let selected = link.callback(|id| Msg::Select { id });
             let cancelled = link.callback(|()| Msg::ToggleEdit { editing: false });
             html! {
                 <span class="name" title="Recipe">
-                    <ChooseFromList<RecipeId> {choices} {selected} {cancelled} />
+                    <ChooseFromList<RecipeId> {choices} {selected} {cancelled}
+                        {quick_picks} {clear_quick_picks} />
                 </span>
             }
         } else {
@@ -136,10 +163,10 @@ impl Component for RecipeDisplay {
                             <span>{"Unknown Recipe "}{id}</span>
                         </span>
                     },
-                    Some(building) => html! {
+                    Some(recipe) => html! {
                         <span class="name" title="Recipe" onclick={edit}>
-                            <Icon icon={building.image.clone()} />
-                            <span>{&building.name}</span>
+                            <Icon icon={recipe.image.clone()} />
+                            <span>{&recipe.name}</span>
                         </span>
                     },
                 },
@@ -147,3 +174,35 @@ impl Component for RecipeDisplay {
         }
     }
 }
+
+/// Describe what a recipe makes at 100% clock speed along with the building's power, so
+/// alternates can be compared while choosing. Recipes whose power varies over the
+/// production cycle show the range instead of the building's nominal power.
+fn recipe_detail(
+    db: &Database,
+    recipe: &Recipe,
+    rate_unit: RateUnit,
+    power_unit: PowerUnit,
+    power: f32,
+) -> Rc<str> {
+    let mut parts: Vec<String> = recipe
+        .products
+        .iter()
+        .map(|product| {
+            let name = db
+                .get(product.item)
+                .map_or("Unknown Item", |item| &*item.name);
+            let rate = product.amount * 60.0 / recipe.time;
+            format!("{} {}", rate_unit.format(rate), name)
+        })
+        .collect();
+    match recipe.variable_power {
+        Some(range) => parts.push(format!(
+            "{}–{}",
+            power_unit.format(range.min),
+            power_unit.format(range.max)
+        )),
+        None => parts.push(power_unit.format(power)),
+    }
+    parts.join(", ").into()
+}
@@ -9,7 +9,7 @@ use log::warn;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
-use crate::node_display::get_value_from_input_event;
+use crate::node_display::{get_value_from_input_event, parse_number};
 
 #[derive(Debug, PartialEq, Properties)]
 pub struct Props {
@@ -62,7 +62,7 @@ impl Component for StationConsumption {
             }
             Msg::FinishEdit => {
                 if let Some(edit_text) = self.edit_text.take() {
-                    if let Ok(value) = edit_text.parse::<f32>() {
+                    if let Some(value) = parse_number(&edit_text) {
                         ctx.props().update_consumption.emit(value.max(0.0));
                     }
                     true
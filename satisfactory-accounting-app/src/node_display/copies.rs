@@ -6,10 +6,15 @@
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
 use log::warn;
-use web_sys::HtmlInputElement;
+use web_sys::{Element, HtmlInputElement};
 use yew::prelude::*;
 
 use crate::node_display::get_value_from_input_event;
+use crate::node_display::wheel::WheelNudge;
+
+/// Fewest copies a node can have. A multiplier of zero would silently drop the node from
+/// every balance, so it is never set from here.
+const MIN_COPIES: u32 = 1;
 
 #[derive(Debug, PartialEq, Properties)]
 pub struct Props {
@@ -28,6 +33,8 @@ pub enum Msg {
     FinishEdit,
     /// Cancel editing without changing the value.
     Cancel,
+    /// Move the value by a number of steps without editing it.
+    Nudge { steps: i32 },
 }
 
 /// Display and editing for number of coipes.
@@ -39,6 +46,10 @@ pub struct VirtualCopies {
     did_focus: bool,
     /// Input to focus on editing.
     input: NodeRef,
+    /// Value shown while not editing.
+    display: NodeRef,
+    /// Listener for scroll-wheel nudges. Only set while not editing.
+    wheel: Option<WheelNudge>,
 }
 
 impl Component for VirtualCopies {
@@ -62,8 +73,10 @@ impl Component for VirtualCopies {
             }
             Msg::FinishEdit => {
                 if let Some(edit_text) = self.edit_text.take() {
-                    if let Ok(value) = edit_text.parse::<u32>() {
-                        ctx.props().update_copies.emit(value);
+                    if let Ok(value) = edit_text.trim().parse::<u32>() {
+                        ctx.props().update_copies.emit(value.max(MIN_COPIES));
+                    } else {
+                        warn!("Invalid number of copies {:?}", edit_text);
                     }
                     true
                 } else {
@@ -75,6 +88,19 @@ impl Component for VirtualCopies {
                 self.edit_text = None;
                 true
             }
+            Msg::Nudge { steps } => {
+                let current = ctx.props().copies;
+                let copies = if steps < 0 {
+                    current.saturating_sub(steps.unsigned_abs())
+                } else {
+                    current.saturating_add(steps.unsigned_abs())
+                }
+                .max(MIN_COPIES);
+                if copies != current {
+                    ctx.props().update_copies.emit(copies);
+                }
+                false
+            }
         }
     }
 
@@ -104,7 +130,8 @@ impl Component for VirtualCopies {
             let value = ctx.props().copies;
             let onclick = link.callback(move |_| Msg::StartEdit { input: value });
             html! {
-                <div class="VirtualCopies" title="Multiplier" {onclick}>
+                <div class="VirtualCopies" title="Multiplier" {onclick}
+                    ref={self.display.clone()}>
                     <span class="current-virt-copies">{value.to_string()}</span>
                     <span>{"×"}</span>
                 </div>
@@ -112,7 +139,14 @@ impl Component for VirtualCopies {
         }
     }
 
-    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+    fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+        if self.edit_text.is_some() {
+            self.wheel = None;
+        } else if self.wheel.is_none() {
+            self.wheel = self.display.cast::<Element>().map(|display| {
+                WheelNudge::new(&display, ctx.link().callback(|steps| Msg::Nudge { steps }))
+            });
+        }
         if !self.did_focus {
             if let Some(input) = self.input.cast::<HtmlInputElement>() {
                 if let Err(e) = input.focus() {
@@ -7,8 +7,17 @@
 //       http://www.apache.org/licenses/LICENSE-2.0
 //! Utilities for manipulating the node graph.
 
+use std::collections::{BTreeSet, HashSet};
+
 use log::warn;
-use satisfactory_accounting::accounting::{Group, Node, NodeKind};
+use satisfactory_accounting::accounting::{
+    Balance, BuildNode, Building, BuildingSettings, Group, ManufacturerSettings, Node, NodeKind,
+};
+use satisfactory_accounting::database::{
+    BuildingId, BuildingKind, BuildingType, Database, ItemId, RecipeId,
+};
+use satisfactory_accounting::solver::{self, RecipeStrategy};
+use uuid::Uuid;
 
 /// Move a node from one position in a group to another. Both src and dest paths should be
 /// rooted at this group. Assumes that this node is the lowest common ancestor of src and
@@ -52,6 +61,7 @@ pub fn move_child(group: &Group, src: &[usize], dest: &[usize]) -> Option<Group>
     }
 
     if dest_prefix.is_empty() {
+        let moved = with_unique_name(moved, &new_group.children);
         new_group.children.insert(dest_first, moved);
     } else {
         new_group.children[dest_first] =
@@ -113,9 +123,1344 @@ pub fn insert_child(node: &Node, child: &[usize], moved: Node) -> Option<Node> {
 
     let mut new_group = group.clone();
     if rest.is_empty() {
+        let moved = with_unique_name(moved, &new_group.children);
         new_group.children.insert(next_idx, moved);
     } else {
         new_group.children[next_idx] = insert_child(&new_group.children[next_idx], rest, moved)?;
     }
     Some(new_group.into())
 }
+
+/// Rename a group being added alongside `siblings` if one of them is a group with the
+/// same name, by numbering it like "Steel Line (2)". Buildings, unnamed groups, and groups
+/// whose name is free are returned unchanged.
+pub fn with_unique_name(node: Node, siblings: &[Node]) -> Node {
+    let group = match node.group() {
+        Some(group) if !group.name.is_empty() => group,
+        _ => return node,
+    };
+    let taken: HashSet<&str> = siblings
+        .iter()
+        .filter_map(|sibling| Some(sibling.group()?.name.as_str()))
+        .collect();
+    if !taken.contains(group.name.as_str()) {
+        return node;
+    }
+    // Number from the original name, so copying "Line (2)" gives "Line (3)".
+    let base = match group
+        .name
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once(" ("))
+    {
+        Some((base, number)) if number.parse::<u32>().is_ok() => base,
+        _ => group.name.as_str(),
+    };
+    let name = (2..)
+        .map(|n| format!("{} ({})", base, n))
+        .find(|name| !taken.contains(name.as_str()))
+        .expect("some number is always free");
+    Group {
+        name,
+        ..group.clone()
+    }
+    .into()
+}
+
+/// Recursively replaces a child node. Returns the new group to replace the one modified.
+/// Returns none if not a group or out of bounds.
+pub fn replace_child(node: &Node, child: &[usize], replacement: Node) -> Option<Node> {
+    let group = match node.kind() {
+        NodeKind::Group(group) => group,
+        _ => {
+            warn!("Target for replace child did not point to a group");
+            return None;
+        }
+    };
+
+    let (&next_idx, rest) = child
+        .split_first()
+        .expect("Don't call replace_child with an empty path");
+
+    if next_idx >= group.children.len() {
+        warn!("Attempting to replace an out of bounds index");
+        return None;
+    }
+
+    let mut new_group = group.clone();
+    new_group.children[next_idx] = if rest.is_empty() {
+        replacement
+    } else {
+        replace_child(&new_group.children[next_idx], rest, replacement)?
+    };
+    Some(new_group.into())
+}
+
+/// Result of balancing the clocks of the generators in a group.
+pub struct GeneratorBalance {
+    /// Group with the generator clock speeds updated.
+    pub group: Group,
+    /// If the generators cannot meet demand even at maximum clock speed, the total
+    /// number of generators that would be needed to do so.
+    pub generators_needed: Option<u32>,
+}
+
+/// Set the clock speed of the generators which are direct children of this group so
+/// that the group's net power is as close to zero as possible. Only generators identical
+/// to the first unlocked generator in the group (same building and fuel) are adjusted,
+/// and they are all given the same clock speed. Locked generators are left unchanged.
+/// Returns None if there are no generators to adjust.
+pub fn balance_generator_clocks(group: &Group, db: &Database) -> Option<GeneratorBalance> {
+    let (building_id, fuel) = group
+        .children
+        .iter()
+        .find_map(|child| match child.building() {
+            Some(Building {
+                building: Some(id),
+                settings: BuildingSettings::Generator(gs),
+                locked: false,
+                ..
+            }) => Some((*id, gs.fuel)),
+            _ => None,
+        })?;
+    if fuel.is_none() {
+        warn!("Cannot balance generators which have no fuel set");
+        return None;
+    }
+    let generator = match db.get(building_id) {
+        Some(BuildingType {
+            kind: BuildingKind::Generator(g),
+            ..
+        }) => g,
+        _ => {
+            warn!("Generator {} is not a known generator", building_id);
+            return None;
+        }
+    };
+    let is_match = |child: &Node| {
+        matches!(child.building(), Some(Building {
+            building: Some(id),
+            settings: BuildingSettings::Generator(gs),
+            locked: false,
+            ..
+        }) if *id == building_id && gs.fuel == fuel)
+    };
+
+    let net_power: f32 = group
+        .children
+        .iter()
+        .map(|child| child.balance().power)
+        .sum();
+    let (generated, count) = group.children.iter().filter(|child| is_match(child)).fold(
+        (0.0, 0),
+        |(power, count), child| {
+            let copies = child.building().map(|b| b.copies).unwrap_or_default();
+            (power + child.balance().power, count + copies)
+        },
+    );
+    if count == 0 || generator.power_production.power <= 0.0 {
+        return None;
+    }
+
+    // Power the matching generators need to produce for the group to net zero.
+    let required = (generated - net_power).max(0.0);
+    let per_generator = required / count as f32;
+    let max_per_generator = generator.power_production.get_production_rate(2.5);
+    let clock_speed = (per_generator / generator.power_production.power)
+        .powf(generator.power_production.power_exponent)
+        .clamp(0.01, 2.5);
+    let generators_needed =
+        (per_generator > max_per_generator).then(|| (required / max_per_generator).ceil() as u32);
+
+    let mut new_group = group.clone();
+    for child in &mut new_group.children {
+        if is_match(child) {
+            let mut building = child.building().unwrap().clone();
+            building.settings.set_clock_speed(clock_speed);
+            *child = match building.clone().build_node(db) {
+                Ok(node) => node,
+                Err(e) => e.into_warning_node(building),
+            };
+        }
+    }
+    Some(GeneratorBalance {
+        group: new_group,
+        generators_needed,
+    })
+}
+
+/// Wrap a node in a new group which contains only that node.
+pub fn wrap_in_group(node: &Node) -> Node {
+    let mut group = Group::empty();
+    group.children.push(node.clone());
+    group.into()
+}
+
+/// Wrap a building in a new group along with buildings producing each of its inputs at
+/// the rate it consumes them, using default recipes. Inputs which are raw resources or
+/// have no recipe are left unsupplied. Returns None if no input can be produced.
+pub fn expand_ingredients(node: &Node, db: &Database) -> Option<Node> {
+    let inputs: Vec<_> = node
+        .balance()
+        .balances
+        .iter()
+        .filter(|(_, &rate)| rate < 0.0)
+        .map(|(&item, &rate)| (item, -rate))
+        .collect();
+    let feeders = solver::produce_directly(db, &inputs, RecipeStrategy::Standard);
+    if feeders.is_empty() {
+        return None;
+    }
+    let mut group = Group::empty();
+    group.name = node_label(node, db);
+    group.children.push(node.clone());
+    group.children.extend(feeders);
+    Some(group.into())
+}
+
+/// Replace a group which has exactly one child with that child, multiplying the child's
+/// copies by the group's copies so the balance is unchanged. Returns None if the group
+/// doesn't have exactly one child.
+pub fn flatten_group(group: &Group, db: &Database) -> Option<Node> {
+    let child = match &group.children[..] {
+        [child] => child,
+        _ => return None,
+    };
+    match child.kind() {
+        NodeKind::Group(child_group) => {
+            let mut new_group = child_group.clone();
+            new_group.copies *= group.copies;
+            Some(new_group.into())
+        }
+        NodeKind::Building(building) => {
+            let mut new_bldg = building.clone();
+            new_bldg.copies *= group.copies;
+            Some(match new_bldg.clone().build_node(db) {
+                Ok(node) => node,
+                Err(err) => err.into_warning_node(new_bldg),
+            })
+        }
+    }
+}
+
+/// Remove every unnamed group in the subtree below this group which has no children,
+/// including groups which only contain other removable groups. Named groups are kept,
+/// since they may be intentional placeholders. Returns the new group and the number of
+/// groups removed, or None if there was nothing to remove.
+pub fn remove_empty_groups(group: &Group) -> Option<(Group, usize)> {
+    let removed = count_empty_groups(group);
+    if removed == 0 {
+        return None;
+    }
+    let mut new_group = group.clone();
+    new_group.children = group
+        .children
+        .iter()
+        .filter_map(|child| match child.group() {
+            Some(child_group) if is_removable(child_group) => None,
+            Some(child_group) if count_empty_groups(child_group) > 0 => {
+                remove_empty_groups(child_group).map(|(cleaned, _)| cleaned.into())
+            }
+            _ => Some(child.clone()),
+        })
+        .collect();
+    Some((new_group, removed))
+}
+
+/// Count the groups which [`remove_empty_groups`] would remove from this group.
+pub fn count_empty_groups(group: &Group) -> usize {
+    group
+        .children
+        .iter()
+        .filter_map(|child| child.group())
+        .map(|child| {
+            if is_removable(child) {
+                // Every descendant of a removable group is also a removable group.
+                1 + child
+                    .children
+                    .iter()
+                    .map(|c| c.iter().count())
+                    .sum::<usize>()
+            } else {
+                count_empty_groups(child)
+            }
+        })
+        .sum()
+}
+
+/// Whether a group is unnamed and contains nothing but other removable groups.
+fn is_removable(group: &Group) -> bool {
+    group.name.is_empty()
+        && group
+            .children
+            .iter()
+            .all(|child| matches!(child.group(), Some(g) if is_removable(g)))
+}
+
+/// Key used to sort the children of a group.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SortKey {
+    /// Alphabetically by group name, or the recipe or item name for buildings.
+    Name,
+    /// By net power, largest consumers first.
+    Power,
+}
+
+/// Reorder the children of a group by the given key. The sort is stable, so children
+/// with equal keys keep their current order.
+pub fn sort_children(group: &Group, key: SortKey, db: &Database) -> Group {
+    let mut new_group = group.clone();
+    match key {
+        SortKey::Name => new_group
+            .children
+            .sort_by_cached_key(|child| node_label(child, db).to_lowercase()),
+        SortKey::Power => new_group
+            .children
+            .sort_by(|a, b| a.balance().power.total_cmp(&b.balance().power)),
+    }
+    new_group
+}
+
+/// Get a short label for a node, which it is also sorted by. Groups use their name,
+/// buildings use the name of their recipe or item, falling back to the building name if
+/// neither is set.
+pub fn node_label(node: &Node, db: &Database) -> String {
+    match node.kind() {
+        NodeKind::Group(group) => group.name.clone(),
+        NodeKind::Building(building) => {
+            let label = match &building.settings {
+                BuildingSettings::Manufacturer(settings) => settings
+                    .recipe
+                    .and_then(|id| db.get(id))
+                    .map(|recipe| recipe.name.clone()),
+                BuildingSettings::Miner(settings) => settings
+                    .resource
+                    .and_then(|id| db.get(id))
+                    .map(|item| item.name.clone()),
+                BuildingSettings::Generator(settings) => settings
+                    .fuel
+                    .and_then(|id| db.get(id))
+                    .map(|item| item.name.clone()),
+                BuildingSettings::Pump(settings) => settings
+                    .resource
+                    .and_then(|id| db.get(id))
+                    .map(|item| item.name.clone()),
+                BuildingSettings::Station(settings) => settings
+                    .fuel
+                    .and_then(|id| db.get(id))
+                    .map(|item| item.name.clone()),
+                BuildingSettings::Geothermal(_) | BuildingSettings::PowerConsumer => None,
+            };
+            label
+                .or_else(|| {
+                    building
+                        .building
+                        .and_then(|id| db.get(id))
+                        .map(|building| building.name.clone())
+                })
+                .map_or_else(String::new, |name| name.to_string())
+        }
+    }
+}
+
+/// Whether a node matches a search for the given lowercase text. Groups match by name,
+/// buildings by their label, the name of the building, or the name of any item they
+/// produce or consume.
+pub fn matches_search(node: &Node, query: &str, db: &Database) -> bool {
+    let matches = |name: &str| name.to_lowercase().contains(query);
+    match node.kind() {
+        NodeKind::Group(group) => matches(&group.name),
+        NodeKind::Building(building) => {
+            matches(&node_label(node, db))
+                || building
+                    .building
+                    .and_then(|id| db.get(id))
+                    .is_some_and(|building| matches(&building.name))
+                || node
+                    .balance()
+                    .balances
+                    .iter()
+                    .filter(|(_, &rate)| rate != 0.0)
+                    .filter_map(|(&item, _)| db.get(item))
+                    .any(|item| matches(&item.name))
+        }
+    }
+}
+
+/// Find the items other than `primary` which the children of a group make, for groups
+/// meant to make a single product. Only items the group has a surplus of above `tolerance`
+/// count, so intermediates used up within the group are fine, as are byproducts of children
+/// which also make the primary product. Returns the index of each offending child along
+/// with the item.
+pub fn stray_products(
+    group: &Group,
+    balance: &Balance,
+    primary: ItemId,
+    tolerance: f32,
+) -> Vec<(usize, ItemId)> {
+    let surplus: Vec<ItemId> = balance
+        .balances
+        .iter()
+        .filter(|(&item, &rate)| item != primary && rate > tolerance)
+        .map(|(&item, _)| item)
+        .collect();
+    let mut stray = Vec::new();
+    for (idx, child) in group.children.iter().enumerate() {
+        let rates = &child.balance().balances;
+        let makes = |item: ItemId| rates.get(&item).is_some_and(|&rate| rate > 0.0);
+        if makes(primary) {
+            continue;
+        }
+        stray.extend(
+            surplus
+                .iter()
+                .filter(|&&item| makes(item))
+                .map(|&item| (idx, item)),
+        );
+    }
+    stray
+}
+
+/// Result of replacing a recipe throughout a tree.
+pub struct RecipeReplacement {
+    /// Tree with the recipe replaced.
+    pub root: Node,
+    /// Paths of the buildings which were switched to the new recipe.
+    pub changed: Vec<Vec<usize>>,
+}
+
+/// Switch every unlocked building using recipe `from` to recipe `to`, keeping clock
+/// speeds and copies. Buildings which can't run the new recipe are switched to a
+/// manufacturer which can. Returns None if no building was changed.
+pub fn replace_recipe(
+    root: &Node,
+    from: RecipeId,
+    to: RecipeId,
+    db: &Database,
+) -> Option<RecipeReplacement> {
+    let manufacturer = db.get(to)?.produced_in.iter().copied().find(|&id| {
+        matches!(
+            db.get(id).map(|building| &building.kind),
+            Some(BuildingKind::Manufacturer(m)) if m.available_recipes.contains(&to)
+        )
+    })?;
+    let mut changed = Vec::new();
+    let root = replace_recipe_in(
+        root,
+        from,
+        to,
+        manufacturer,
+        db,
+        &mut Vec::new(),
+        &mut changed,
+    );
+    (!changed.is_empty()).then(|| RecipeReplacement { root, changed })
+}
+
+/// Recursively replace a recipe below the given node, recording the paths of changed
+/// buildings. Unchanged nodes are returned as-is.
+fn replace_recipe_in(
+    node: &Node,
+    from: RecipeId,
+    to: RecipeId,
+    manufacturer: BuildingId,
+    db: &Database,
+    path: &mut Vec<usize>,
+    changed: &mut Vec<Vec<usize>>,
+) -> Node {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            let num_changed = changed.len();
+            let children: Vec<Node> = group
+                .children
+                .iter()
+                .enumerate()
+                .map(|(idx, child)| {
+                    path.push(idx);
+                    let child = replace_recipe_in(child, from, to, manufacturer, db, path, changed);
+                    path.pop();
+                    child
+                })
+                .collect();
+            if changed.len() == num_changed {
+                return node.clone();
+            }
+            Group {
+                children,
+                ..group.clone()
+            }
+            .into()
+        }
+        NodeKind::Building(building) => {
+            let settings = match &building.settings {
+                BuildingSettings::Manufacturer(ms)
+                    if ms.recipe == Some(from) && !building.locked =>
+                {
+                    ms
+                }
+                _ => return node.clone(),
+            };
+            let runs_recipe = |id: BuildingId| {
+                matches!(
+                    db.get(id).map(|building| &building.kind),
+                    Some(BuildingKind::Manufacturer(m)) if m.available_recipes.contains(&to)
+                )
+            };
+            let new_bldg = Building {
+                building: building
+                    .building
+                    .filter(|&id| runs_recipe(id))
+                    .or(Some(manufacturer)),
+                settings: ManufacturerSettings {
+                    recipe: Some(to),
+                    ..settings.clone()
+                }
+                .into(),
+                ..building.clone()
+            };
+            changed.push(path.clone());
+            match new_bldg.clone().build_node(db) {
+                Ok(node) => node,
+                Err(e) => e.into_warning_node(new_bldg),
+            }
+        }
+    }
+}
+
+/// Whether the node is or contains a locked building.
+pub fn contains_locked(node: &Node) -> bool {
+    node.iter()
+        .any(|node| matches!(node.building(), Some(building) if building.locked))
+}
+
+/// Result of normalizing a group to a single copy.
+pub struct Normalized {
+    /// Group with one copy and all children divided by the original number of copies.
+    pub group: Group,
+    /// Number of buildings whose copies didn't divide evenly, so their clock speed was
+    /// reduced instead.
+    pub adjusted_clocks: usize,
+    /// Number of buildings which couldn't be divided exactly, because they don't have a
+    /// clock speed or would need a clock speed below the minimum.
+    pub inexact: usize,
+}
+
+/// Set the copies of a group to 1, dividing the copies of its children by the group's
+/// original copies, so the group represents a single unit of production. Returns None if
+/// the group already has one copy.
+pub fn normalize_group(group: &Group, db: &Database) -> Option<Normalized> {
+    if group.copies <= 1 {
+        return None;
+    }
+    let mut result = Normalized {
+        group: group.clone(),
+        adjusted_clocks: 0,
+        inexact: 0,
+    };
+    result.group.copies = 1;
+    result.group.children = group
+        .children
+        .iter()
+        .map(|child| divide_node(child, group.copies, db, &mut result))
+        .collect();
+    Some(result)
+}
+
+/// Divide the number of copies of a node by the given divisor, recursing into groups
+/// whose copies don't divide evenly and falling back to clock speed for buildings.
+fn divide_node(node: &Node, divisor: u32, db: &Database, result: &mut Normalized) -> Node {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            let common = gcd(group.copies, divisor);
+            let mut new_group = group.clone();
+            new_group.copies = group.copies / common;
+            let remaining = divisor / common;
+            if remaining > 1 {
+                new_group.children = group
+                    .children
+                    .iter()
+                    .map(|child| divide_node(child, remaining, db, result))
+                    .collect();
+            }
+            new_group.into()
+        }
+        NodeKind::Building(building) => {
+            let mut new_bldg = building.clone();
+            match set_fractional_copies(&mut new_bldg, building.copies as f32 / divisor as f32) {
+                FractionalCopies::Exact => {}
+                FractionalCopies::AdjustedClock => result.adjusted_clocks += 1,
+                FractionalCopies::Inexact => result.inexact += 1,
+            }
+            match new_bldg.clone().build_node(db) {
+                Ok(node) => node,
+                Err(e) => e.into_warning_node(new_bldg),
+            }
+        }
+    }
+}
+
+/// How closely a building could be set to a fractional number of copies.
+enum FractionalCopies {
+    /// The number of copies was whole.
+    Exact,
+    /// The copies were rounded up and the clock speed reduced to make up the difference.
+    AdjustedClock,
+    /// The building has no clock speed, or would need one below the minimum, so its rate
+    /// is off.
+    Inexact,
+}
+
+/// Set a building to the given, possibly fractional, number of copies. Fractions use as
+/// few buildings as possible, then slow them down to make up the difference.
+fn set_fractional_copies(building: &mut Building, target: f32) -> FractionalCopies {
+    let whole = target.round();
+    if whole >= 1.0 && (target - whole).abs() < 1e-4 {
+        building.copies = whole as u32;
+        return FractionalCopies::Exact;
+    }
+    let copies = (target.ceil() as u32).max(1);
+    let has_clock = matches!(
+        building.settings,
+        BuildingSettings::Manufacturer(_)
+            | BuildingSettings::Miner(_)
+            | BuildingSettings::Generator(_)
+            | BuildingSettings::Pump(_)
+    );
+    let clock_speed = building.settings.clock_speed() * target / copies as f32;
+    building.copies = copies;
+    if has_clock && clock_speed >= 0.01 {
+        building.settings.set_clock_speed(clock_speed);
+        FractionalCopies::AdjustedClock
+    } else {
+        building.settings.set_clock_speed(clock_speed.max(0.01));
+        FractionalCopies::Inexact
+    }
+}
+
+/// Result of scaling a node by a multiplier.
+pub struct Scaled {
+    /// The scaled node.
+    pub node: Node,
+    /// Number of buildings whose copies came out fractional, so their clock speed was
+    /// reduced instead.
+    pub adjusted_clocks: usize,
+    /// Number of buildings which couldn't be scaled exactly, because they don't have a
+    /// clock speed or would need a clock speed below the minimum.
+    pub inexact: usize,
+}
+
+/// Multiply the production of a node by `factor`. Groups whose copies multiply to a whole
+/// number have their copies changed, otherwise their children are scaled instead. Buildings
+/// whose copies would be fractional are rounded up and slowed down, like when normalizing.
+pub fn scale_node(node: &Node, factor: f32, db: &Database) -> Scaled {
+    fn scale(node: &Node, factor: f32, db: &Database, result: &mut Scaled) -> Node {
+        match node.kind() {
+            NodeKind::Group(group) => {
+                let mut new_group = group.clone();
+                let copies = group.copies as f32 * factor;
+                if copies.round() >= 1.0 && (copies - copies.round()).abs() < 1e-4 {
+                    new_group.copies = copies.round() as u32;
+                } else {
+                    new_group.children = group
+                        .children
+                        .iter()
+                        .map(|child| scale(child, factor, db, result))
+                        .collect();
+                }
+                new_group.into()
+            }
+            NodeKind::Building(building) => {
+                let mut new_bldg = building.clone();
+                match set_fractional_copies(&mut new_bldg, building.copies as f32 * factor) {
+                    FractionalCopies::Exact => {}
+                    FractionalCopies::AdjustedClock => result.adjusted_clocks += 1,
+                    FractionalCopies::Inexact => result.inexact += 1,
+                }
+                match new_bldg.clone().build_node(db) {
+                    Ok(node) => node,
+                    Err(e) => e.into_warning_node(new_bldg),
+                }
+            }
+        }
+    }
+    let mut result = Scaled {
+        node: node.clone(),
+        adjusted_clocks: 0,
+        inexact: 0,
+    };
+    result.node = scale(node, factor, db, &mut result);
+    result
+}
+
+/// Find the paths of nodes whose balance or warning differs between two copies of the same
+/// tree, such as the tree as it was built incrementally and a full recomputation of it.
+/// Rates are compared with a small relative tolerance for rounding error. The trees are
+/// only compared as far as their structure matches.
+pub fn balance_mismatches(cached: &Node, fresh: &Node) -> Vec<Vec<usize>> {
+    fn differs(a: f32, b: f32) -> bool {
+        (a - b).abs() > 1e-4 * a.abs().max(b.abs()).max(1.0)
+    }
+    fn visit(cached: &Node, fresh: &Node, path: &mut Vec<usize>, found: &mut Vec<Vec<usize>>) {
+        let (cached_balance, fresh_balance) = (cached.balance(), fresh.balance());
+        let items_differ = cached_balance
+            .balances
+            .keys()
+            .chain(fresh_balance.balances.keys())
+            .any(|item| {
+                let rate = |balance: &Balance| balance.balances.get(item).copied().unwrap_or(0.0);
+                differs(rate(cached_balance), rate(fresh_balance))
+            });
+        if items_differ
+            || differs(cached_balance.power, fresh_balance.power)
+            || cached.warning() != fresh.warning()
+        {
+            found.push(path.clone());
+        }
+        for (idx, (cached, fresh)) in cached.children().zip(fresh.children()).enumerate() {
+            path.push(idx);
+            visit(&cached, &fresh, path, found);
+            path.pop();
+        }
+    }
+    let mut found = Vec::new();
+    visit(cached, fresh, &mut Vec::new(), &mut found);
+    found
+}
+
+/// Approximate floor space of the buildings in a node.
+pub struct FoundationEstimate {
+    /// Number of foundations covered by buildings with a known footprint.
+    pub foundations: u32,
+    /// Buildings with no known footprint, which aren't counted.
+    pub missing: BTreeSet<BuildingId>,
+}
+
+/// Estimate the number of foundations needed to place every building in a node, counting
+/// virtual copies of both buildings and groups. Each building is rounded up to whole
+/// foundations on each side, and the space for belts and pipes between them isn't
+/// counted, so this is only a rough lower bound.
+pub fn estimate_foundations(node: &Node, db: &Database) -> FoundationEstimate {
+    fn count(node: &Node, db: &Database, estimate: &mut FoundationEstimate) -> u32 {
+        match node.kind() {
+            NodeKind::Group(group) => {
+                let per_copy: u32 = group
+                    .children
+                    .iter()
+                    .map(|child| count(child, db, estimate))
+                    .sum();
+                per_copy * group.copies
+            }
+            NodeKind::Building(building) => {
+                let id = match building.building {
+                    Some(id) => id,
+                    None => return 0,
+                };
+                match db.get(id).and_then(|info| info.footprint) {
+                    Some(footprint) => footprint.foundations() * building.copies,
+                    None => {
+                        estimate.missing.insert(id);
+                        0
+                    }
+                }
+            }
+        }
+    }
+    let mut estimate = FoundationEstimate {
+        foundations: 0,
+        missing: BTreeSet::new(),
+    };
+    estimate.foundations = count(node, db, &mut estimate);
+    estimate
+}
+
+/// Result of splitting a building into several.
+pub struct Split {
+    /// One building node per proportion, together producing what the original did.
+    pub nodes: Vec<Node>,
+    /// Number of parts which couldn't be set exactly, because the building has no clock
+    /// speed or the part would need a clock speed below the minimum.
+    pub inexact: usize,
+}
+
+/// Split a building into one building per proportion, each getting that share of the
+/// original's copies. Shares which come out fractional are rounded up and slowed down like
+/// when scaling, so the parts together produce exactly what the original did. Returns None
+/// if there are fewer than two proportions or any of them isn't positive.
+pub fn split_building(building: &Building, proportions: &[f32], db: &Database) -> Option<Split> {
+    if proportions.len() < 2 || !proportions.iter().all(|&p| p.is_finite() && p > 0.0) {
+        return None;
+    }
+    let total: f32 = proportions.iter().sum();
+    let mut inexact = 0;
+    let nodes = proportions
+        .iter()
+        .map(|&proportion| {
+            let mut part = building.clone();
+            let target = building.copies as f32 * proportion / total;
+            if let FractionalCopies::Inexact = set_fractional_copies(&mut part, target) {
+                inexact += 1;
+            }
+            match part.clone().build_node(db) {
+                Ok(node) => node,
+                Err(e) => e.into_warning_node(part),
+            }
+        })
+        .collect();
+    Some(Split { nodes, inexact })
+}
+
+/// Merge two groups into one holding the children of both, the first group's children
+/// first. The merged group gets the largest number of copies which divides the copies of
+/// both, and the children of each group are multiplied by the rest of that group's copies,
+/// so the merged group produces exactly what the two did. It keeps the id, tag, and
+/// primary product of the first group, falling back to the second's tag and primary
+/// product, and assumes supplied the items either group assumed.
+pub fn merge_groups(first: &Group, second: &Group, name: String, db: &Database) -> Group {
+    let copies = gcd(first.copies, second.copies).max(1);
+    let mut merged = first.clone();
+    merged.name = name;
+    merged.copies = copies;
+    merged.tag = first.tag.or(second.tag);
+    merged.primary_product = first.primary_product.or(second.primary_product);
+    merged
+        .assumed_supplied
+        .extend(second.assumed_supplied.iter().copied());
+    merged.children = [first, second]
+        .into_iter()
+        .flat_map(|group| {
+            let factor = group.copies / copies;
+            group
+                .children
+                .iter()
+                .map(move |child| multiply_copies(child, factor, db))
+        })
+        .collect();
+    merged
+}
+
+/// Multiply the number of copies of a node by the given factor.
+fn multiply_copies(node: &Node, factor: u32, db: &Database) -> Node {
+    if factor == 1 {
+        return node.clone();
+    }
+    match node.kind() {
+        NodeKind::Group(group) => {
+            let mut new_group = group.clone();
+            new_group.copies *= factor;
+            new_group.into()
+        }
+        NodeKind::Building(building) => {
+            let mut new_bldg = building.clone();
+            new_bldg.copies *= factor;
+            match new_bldg.clone().build_node(db) {
+                Ok(node) => node,
+                Err(e) => e.into_warning_node(new_bldg),
+            }
+        }
+    }
+}
+
+/// Move every generator in the tree into a group named `name` at the end of the root,
+/// returning the new root and the number of generators moved, or None if there are none
+/// to move. An existing top-level group with that name and a single copy is reused, and
+/// the generators already in it are left alone. Generators are multiplied by the copies
+/// of the groups they are moved out of, so the total power is unchanged. Groups emptied by
+/// the move are kept.
+pub fn collect_generators(root: &Group, name: &str, db: &Database) -> Option<(Group, usize)> {
+    fn is_generator(node: &Node) -> bool {
+        matches!(
+            node.building().map(|building| &building.settings),
+            Some(BuildingSettings::Generator(_)) | Some(BuildingSettings::Geothermal(_))
+        )
+    }
+
+    /// Remove the generators from a group and the groups within it except `skip`,
+    /// multiplying them by `factor`. Returns None if there are none to remove.
+    fn take_generators(
+        group: &Group,
+        factor: u32,
+        skip: Option<Uuid>,
+        db: &Database,
+        taken: &mut Vec<Node>,
+    ) -> Option<Group> {
+        let found = taken.len();
+        let children = group
+            .children
+            .iter()
+            .filter_map(|child| match child.group() {
+                Some(child_group) if Some(child_group.id) != skip => {
+                    let factor = factor * child_group.copies;
+                    Some(
+                        match take_generators(child_group, factor, skip, db, taken) {
+                            Some(updated) => updated.into(),
+                            None => child.clone(),
+                        },
+                    )
+                }
+                None if is_generator(child) => {
+                    taken.push(multiply_copies(child, factor, db));
+                    None
+                }
+                _ => Some(child.clone()),
+            })
+            .collect();
+        (taken.len() > found).then(|| Group {
+            children,
+            ..group.clone()
+        })
+    }
+
+    let existing = root
+        .children
+        .iter()
+        .filter_map(|child| child.group())
+        .find(|group| group.name == name && group.copies == 1);
+    let mut taken = Vec::new();
+    let mut new_root = take_generators(root, 1, existing.map(|group| group.id), db, &mut taken)?;
+    let moved = taken.len();
+    match existing {
+        Some(existing) => {
+            let mut power = existing.clone();
+            power.children.extend(taken);
+            for child in &mut new_root.children {
+                if child.group().is_some_and(|group| group.id == power.id) {
+                    *child = power.clone().into();
+                }
+            }
+        }
+        None => {
+            let mut power = Group::empty();
+            power.name = name.to_string();
+            power.children = taken;
+            new_root.children.push(power.into());
+        }
+    }
+    Some((new_root, moved))
+}
+
+/// Get the largest number of copies of a group whose power consumption fits within the
+/// budget, given the group's net power at its current number of copies. Returns None if the
+/// group doesn't consume power.
+pub fn copies_within_power_budget(power: f32, copies: u32, budget: f32) -> Option<u32> {
+    let per_copy = -power / copies.max(1) as f32;
+    if per_copy <= 0.0 {
+        return None;
+    }
+    // Allow for rounding error so a budget of exactly N copies' consumption gives N.
+    Some((budget / per_copy + 1e-4).floor().max(0.0) as u32)
+}
+
+/// Number of buildings and clock speed which produce a target rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DemandScale {
+    /// Number of copies of the building.
+    pub copies: u32,
+    /// Clock speed of each copy.
+    pub clock_speed: f32,
+    /// Number of buildings needed at 100% clock speed, possibly fractional.
+    pub needed: f32,
+}
+
+/// Work out how to scale a building producing `rate` at `copies` and `clock_speed` so it
+/// produces `target` instead. The current number of copies is kept if the clock speed
+/// stays within its current range: up to 100%, or up to 250% if already overclocked.
+/// Otherwise the fewest copies within that range are used. Returns None if the target is
+/// below what one building makes at the minimum clock speed.
+pub fn scale_to_rate(copies: u32, clock_speed: f32, rate: f32, target: f32) -> Option<DemandScale> {
+    const EPSILON: f32 = 1e-4;
+    let per_building = rate / (copies.max(1) as f32 * clock_speed);
+    let needed = target / per_building;
+    let max_clock = if clock_speed > 1.0 + EPSILON {
+        2.5
+    } else {
+        1.0
+    };
+    let copies = if copies > 0 && needed / copies as f32 <= max_clock + EPSILON {
+        copies
+    } else {
+        ((needed / max_clock) - EPSILON).ceil().max(1.0) as u32
+    };
+    let clock_speed = needed / copies as f32;
+    (clock_speed >= 0.01 - EPSILON).then(|| DemandScale {
+        copies,
+        clock_speed: clock_speed.clamp(0.01, 2.5),
+        needed,
+    })
+}
+
+/// Greatest common divisor of two numbers.
+pub(super) fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::GeneratorSettings;
+
+    use super::*;
+
+    #[test]
+    fn demand_scaling() {
+        // 2 buildings at 100% making 60/min, so 30/min each.
+        let scaled = scale_to_rate(2, 1.0, 60.0, 45.0).unwrap();
+        assert_eq!(scaled.copies, 2);
+        assert!((scaled.clock_speed - 0.75).abs() < 1e-4);
+        assert!((scaled.needed - 1.5).abs() < 1e-4);
+        // More demand than the current copies can meet without overclocking.
+        let scaled = scale_to_rate(2, 1.0, 60.0, 100.0).unwrap();
+        assert_eq!(scaled.copies, 4);
+        assert!((scaled.clock_speed - 100.0 / 120.0).abs() < 1e-4);
+        // Already overclocked buildings may stay overclocked.
+        let scaled = scale_to_rate(1, 2.0, 60.0, 75.0).unwrap();
+        assert_eq!(scaled.copies, 1);
+        assert!((scaled.clock_speed - 2.5).abs() < 1e-4);
+        assert_eq!(scale_to_rate(1, 1.0, 30.0, 0.1), None);
+    }
+
+    #[test]
+    fn power_budget_copies() {
+        // 3 copies using 30 MW each.
+        assert_eq!(copies_within_power_budget(-90.0, 3, 300.0), Some(10));
+        assert_eq!(copies_within_power_budget(-90.0, 3, 299.0), Some(9));
+        assert_eq!(copies_within_power_budget(-90.0, 3, 20.0), Some(0));
+        assert_eq!(copies_within_power_budget(50.0, 1, 100.0), None);
+        assert_eq!(copies_within_power_budget(0.0, 1, 100.0), None);
+    }
+
+    #[test]
+    fn pasted_groups_get_unique_names() {
+        let named = |name: &str| -> Node {
+            let mut group = Group::empty();
+            group.name = name.to_string();
+            group.into()
+        };
+        let name_of = |node: &Node| node.group().unwrap().name.clone();
+        let siblings = vec![named("Steel Line"), named("Steel Line (2)"), named("")];
+        assert_eq!(
+            name_of(&with_unique_name(named("Steel Line"), &siblings)),
+            "Steel Line (3)"
+        );
+        assert_eq!(
+            name_of(&with_unique_name(named("Steel Line (2)"), &siblings)),
+            "Steel Line (3)"
+        );
+        assert_eq!(
+            name_of(&with_unique_name(named("Iron Line"), &siblings)),
+            "Iron Line"
+        );
+        // Unnamed groups are never numbered.
+        assert_eq!(name_of(&with_unique_name(named(""), &siblings)), "");
+    }
+
+    #[test]
+    fn search_matches_recipes_and_items() {
+        let db = Database::load_default();
+        let plastic = Building {
+            building: Some("Desc_OilRefinery_C".into()),
+            settings: BuildingSettings::Manufacturer(ManufacturerSettings {
+                recipe: Some("Recipe_Plastic_C".into()),
+                clock_speed: 1.0,
+            }),
+            ..Building::empty()
+        }
+        .build_node(&db)
+        .unwrap();
+        // By recipe, by building, and by produced and consumed items.
+        assert!(matches_search(&plastic, "plastic", &db));
+        assert!(matches_search(&plastic, "refinery", &db));
+        assert!(matches_search(&plastic, "heavy oil", &db));
+        assert!(matches_search(&plastic, "crude", &db));
+        assert!(!matches_search(&plastic, "iron", &db));
+        let mut group = Group::empty();
+        group.name = "Plastic Line".to_string();
+        assert!(matches_search(&group.into(), "plastic", &db));
+    }
+
+    #[test]
+    fn generators_are_collected_into_a_power_group() {
+        let db = Database::load_default();
+        let generator = Building {
+            building: Some("Desc_GeneratorCoal_C".into()),
+            settings: BuildingSettings::Generator(GeneratorSettings {
+                fuel: Some("Desc_Coal_C".into()),
+                clock_speed: 1.0,
+            }),
+            ..Building::empty()
+        }
+        .build_node(&db)
+        .unwrap();
+        let mut base = Group::empty();
+        base.copies = 2;
+        base.children = vec![generator.clone(), Building::empty_node()];
+        let mut root = Group::empty();
+        root.children = vec![base.into(), generator];
+        let before: Node = root.clone().into();
+
+        let (collected, moved) = collect_generators(&root, "Power", &db).unwrap();
+        assert_eq!(moved, 2);
+        assert_eq!(collected.children.len(), 2);
+        assert_eq!(collected.children[0].group().unwrap().children.len(), 1);
+        let power = collected.children[1].group().unwrap();
+        assert_eq!(power.name, "Power");
+        // The generator from the doubled group is doubled so the power is unchanged.
+        let copies: Vec<u32> = power
+            .children
+            .iter()
+            .map(|child| child.building().unwrap().copies)
+            .collect();
+        assert_eq!(copies, [2, 1]);
+        let after: Node = collected.clone().into();
+        assert!((after.balance().power - before.balance().power).abs() < 1e-3);
+        // Running again finds nothing more to move.
+        assert!(collect_generators(&collected, "Power", &db).is_none());
+    }
+
+    #[test]
+    fn scaling_rounds_up_fractional_copies() {
+        let db = Database::load_default();
+        let plates = |copies: u32| -> Node {
+            Building {
+                building: Some("Desc_ConstructorMk1_C".into()),
+                settings: BuildingSettings::Manufacturer(ManufacturerSettings {
+                    recipe: Some("Recipe_IronPlate_C".into()),
+                    clock_speed: 1.0,
+                }),
+                copies,
+                ..Building::empty()
+            }
+            .build_node(&db)
+            .unwrap()
+        };
+        let plate_rate = |node: &Node| node.balance().balances[&ItemId::from("Desc_IronPlate_C")];
+
+        // Whole results just change the copies.
+        let scaled = scale_node(&plates(2), 1.5, &db);
+        assert_eq!(scaled.node.building().unwrap().copies, 3);
+        assert_eq!(scaled.adjusted_clocks, 0);
+        // Fractional results use more buildings at a lower clock speed.
+        let scaled = scale_node(&plates(3), 0.5, &db);
+        let building = scaled.node.building().unwrap();
+        assert_eq!(building.copies, 2);
+        assert!((building.settings.clock_speed() - 0.75).abs() < 1e-4);
+        assert_eq!(scaled.adjusted_clocks, 1);
+        assert!((plate_rate(&scaled.node) - plate_rate(&plates(3)) / 2.0).abs() < 1e-3);
+        // Groups change their own copies when they can.
+        let mut group = Group::empty();
+        group.copies = 2;
+        group.children = vec![plates(1)];
+        let scaled = scale_node(&group.clone().into(), 0.5, &db);
+        assert_eq!(scaled.node.group().unwrap().copies, 1);
+        let scaled = scale_node(&group.into(), 0.25, &db);
+        let scaled_group = scaled.node.group().unwrap();
+        assert_eq!(scaled_group.copies, 2);
+        assert_eq!(scaled_group.children[0].building().unwrap().copies, 1);
+        assert_eq!(scaled.adjusted_clocks, 1);
+    }
+
+    #[test]
+    fn splitting_keeps_the_total_output() {
+        let db = Database::load_default();
+        let plates = Building {
+            building: Some("Desc_ConstructorMk1_C".into()),
+            settings: BuildingSettings::Manufacturer(ManufacturerSettings {
+                recipe: Some("Recipe_IronPlate_C".into()),
+                clock_speed: 1.0,
+            }),
+            copies: 3,
+            ..Building::empty()
+        };
+        let original = plates.clone().build_node(&db).unwrap();
+        for proportions in [
+            vec![1.0, 2.0],
+            vec![1.0, 1.0],
+            vec![3.0, 1.0, 1.0],
+            vec![0.5, 0.25],
+        ] {
+            let split = split_building(&plates, &proportions, &db).unwrap();
+            assert_eq!(split.nodes.len(), proportions.len());
+            assert_eq!(split.inexact, 0);
+            let mut total = Balance::empty();
+            for node in &split.nodes {
+                total += node.balance();
+            }
+            for (item, &rate) in &original.balance().balances {
+                let split_rate = total.balances.get(item).copied().unwrap_or_default();
+                assert!(
+                    (split_rate - rate).abs() < 1e-3,
+                    "{:?}: {} != {}",
+                    proportions,
+                    split_rate,
+                    rate
+                );
+            }
+        }
+        // Even shares keep whole buildings, so the power is unchanged too. Slowed down parts
+        // use less power, since power doesn't scale linearly with clock speed.
+        let split = split_building(&plates, &[1.0, 2.0], &db).unwrap();
+        let copies: Vec<_> = split
+            .nodes
+            .iter()
+            .map(|n| n.building().unwrap().copies)
+            .collect();
+        assert_eq!(copies, [1, 2]);
+        let power: f32 = split.nodes.iter().map(|n| n.balance().power).sum();
+        assert!((power - original.balance().power).abs() < 1e-2);
+        assert!(split_building(&plates, &[1.0], &db).is_none());
+        assert!(split_building(&plates, &[1.0, 0.0], &db).is_none());
+    }
+
+    #[test]
+    fn foundations_count_copies_and_skip_unknown_footprints() {
+        let db = Database::load_default();
+        let building = |id: &str, copies: u32| -> Node {
+            let id: BuildingId = id.into();
+            let building = Building {
+                building: Some(id),
+                settings: db.get(id).unwrap().get_default_settings(),
+                copies,
+                ..Building::empty()
+            };
+            building
+                .clone()
+                .build_node(&db)
+                .unwrap_or_else(|e| e.into_warning_node(building))
+        };
+        // A constructor is 8x10 meters, so it needs a 1x2 block of foundations.
+        let mut group = Group::empty();
+        group.copies = 3;
+        group.children = vec![
+            building("Desc_ConstructorMk1_C", 2),
+            building("Desc_StreetLight_C", 1),
+            Building::empty_node(),
+        ];
+        let estimate = estimate_foundations(&group.into(), &db);
+        assert_eq!(estimate.foundations, 12);
+        assert_eq!(
+            estimate.missing.into_iter().collect::<Vec<_>>(),
+            [BuildingId::from("Desc_StreetLight_C")]
+        );
+    }
+
+    #[test]
+    fn balance_mismatches_find_stale_nodes() {
+        let db = Database::load_default();
+        let plates = Building {
+            building: Some("Desc_ConstructorMk1_C".into()),
+            settings: BuildingSettings::Manufacturer(ManufacturerSettings {
+                recipe: Some("Recipe_IronPlate_C".into()),
+                clock_speed: 1.0,
+            }),
+            ..Building::empty()
+        };
+        let mut group = Group::empty();
+        let built = plates.build_node(&db).unwrap();
+        group.children = vec![built.clone()];
+        let root: Node = group.clone().into();
+        assert!(balance_mismatches(&root, &root.rebuild(&db)).is_empty());
+
+        // A node whose stored balance is wrong is found along with its parent.
+        let mut stale = serde_json::to_value(&built).unwrap();
+        stale["balance"]["power"] = serde_json::json!(1.0);
+        group.children.push(serde_json::from_value(stale).unwrap());
+        let root: Node = group.into();
+        assert_eq!(
+            balance_mismatches(&root, &root.rebuild(&db)),
+            [vec![], vec![1]]
+        );
+    }
+
+    #[test]
+    fn pure_lines_flag_other_products() {
+        let db = Database::load_default();
+        let manufacturer = |building: &str, recipe: &str| -> Node {
+            Building {
+                building: Some(building.into()),
+                settings: BuildingSettings::Manufacturer(ManufacturerSettings {
+                    recipe: Some(recipe.into()),
+                    clock_speed: 1.0,
+                }),
+                ..Building::empty()
+            }
+            .build_node(&db)
+            .unwrap()
+        };
+        let plates = ItemId::from("Desc_IronPlate_C");
+        let stray = |children: Vec<Node>| {
+            let mut group = Group::empty();
+            group.children = children;
+            let node: Node = group.clone().into();
+            stray_products(&group, node.balance(), plates, 0.01)
+        };
+        let smelter = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C");
+        let plate = manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C");
+        let rod = manufacturer("Desc_ConstructorMk1_C", "Recipe_IronRod_C");
+        // Ingots are used up making plates.
+        assert!(stray(vec![smelter.clone(), plate.clone()]).is_empty());
+        let rods = ItemId::from("Desc_IronRod_C");
+        assert_eq!(
+            stray(vec![smelter.clone(), plate.clone(), rod]),
+            [(2, rods)]
+        );
+        // Spare ingots are flagged on each smelter making them.
+        let ingots = ItemId::from("Desc_IronIngot_C");
+        assert_eq!(
+            stray(vec![smelter.clone(), plate, smelter]),
+            [(0, ingots), (2, ingots)]
+        );
+    }
+
+    #[test]
+    fn merged_groups_keep_their_production() {
+        let db = Database::load_default();
+        let iron_plates = |copies: u32| -> Node {
+            Building {
+                building: Some("Desc_ConstructorMk1_C".into()),
+                settings: BuildingSettings::Manufacturer(ManufacturerSettings {
+                    recipe: Some("Recipe_IronPlate_C".into()),
+                    clock_speed: 1.0,
+                }),
+                copies,
+                ..Building::empty()
+            }
+            .build_node(&db)
+            .unwrap()
+        };
+        let group = |copies: u32, children: Vec<Node>| {
+            let mut group = Group::empty();
+            group.copies = copies;
+            group.children = children;
+            group
+        };
+        let plates = |node: &Node| node.balance().balances[&ItemId::from("Desc_IronPlate_C")];
+
+        // 2 × 1 and 3 × 2 constructors, so 8 in total.
+        let first = group(2, vec![iron_plates(1)]);
+        let second = group(3, vec![iron_plates(2)]);
+        let expected = plates(&first.clone().into()) + plates(&second.clone().into());
+        let merged = merge_groups(&first, &second, "Plates".into(), &db);
+        assert_eq!(merged.copies, 1);
+        assert_eq!(merged.id, first.id);
+        assert_eq!(merged.name, "Plates");
+        let copies: Vec<u32> = merged
+            .children
+            .iter()
+            .map(|child| child.building().unwrap().copies)
+            .collect();
+        assert_eq!(copies, vec![2, 6]);
+        assert!((plates(&merged.into()) - expected).abs() < 1e-3);
+
+        // Common copies are kept on the merged group.
+        let merged = merge_groups(
+            &group(2, vec![iron_plates(1)]),
+            &group(4, vec![]),
+            "".into(),
+            &db,
+        );
+        assert_eq!(merged.copies, 2);
+        assert_eq!(merged.children[0].building().unwrap().copies, 1);
+    }
+}
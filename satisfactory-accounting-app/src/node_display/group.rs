@@ -5,17 +5,33 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
-use satisfactory_accounting::accounting::{Building, Group};
+use satisfactory_accounting::accounting::{Building, BuildingSettings, Group};
+use satisfactory_accounting::database::ItemId;
 use yew::prelude::*;
 
+use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
 use crate::node_display::copies::VirtualCopies;
-use crate::node_display::{Msg, NodeDisplay, NodeMeta, DRAG_INSERT_POINT};
+use crate::node_display::graph_manipulation::{self, SortKey};
+use crate::node_display::icon::Icon;
+use crate::node_display::tag::tag_class;
+use crate::node_display::{dom_path, Msg, NodeDisplay, NodeMeta, DRAG_INSERT_POINT};
 use crate::CtxHelper;
 
 use group_name::GroupName;
 
+pub(super) use file_io::{describe_lost, download_json, export_group, load_import};
+
+mod file_io;
 mod group_name;
 
+/// Number of colors indentation guides cycle through as groups nest deeper.
+const GUIDE_COLORS: usize = 4;
+
+/// Get the class which colors the indentation guide of a group at the given depth.
+fn guide_depth_class(depth: usize) -> String {
+    format!("guide-depth-{}", depth % GUIDE_COLORS)
+}
+
 impl NodeDisplay {
     /// Build the display for a Group.
     pub(super) fn view_group(&self, ctx: &Context<Self>, group: &Group) -> Html {
@@ -34,6 +50,10 @@ impl NodeDisplay {
         let replace = link.callback(|(idx, replacement)| Msg::ReplaceChild { idx, replacement });
         let delete = link.callback(|idx| Msg::DeleteChild { idx });
         let copy = link.callback(|idx| Msg::CopyChild { idx });
+        let copy_scaled = link.callback(|idx| Msg::CopyChildScaled { idx });
+        let split = link.callback(|idx| Msg::SplitChild { idx });
+        let match_demand = link.callback(|idx| Msg::MatchSiblingDemand { idx });
+        let merge_next = link.callback(|idx| Msg::MergeWithNext { idx });
         let move_node = link.callback(|(src_path, dest_path)| Msg::MoveNode {
             src_path,
             dest_path,
@@ -45,6 +65,27 @@ impl NodeDisplay {
             child: Building::empty_node(),
         });
         let rename = link.callback(|name| Msg::Rename { name });
+        let hide_buildings = ctx.global_meta().groups_only && !ctx.meta(group.id).show_buildings;
+        let hidden_buildings: u32 = if hide_buildings {
+            group
+                .children
+                .iter()
+                .filter_map(|child| child.building())
+                .map(|building| building.copies)
+                .sum()
+        } else {
+            0
+        };
+        let has_generators = group.children.iter().any(|child| {
+            matches!(
+                child.building(),
+                Some(Building {
+                    settings: BuildingSettings::Generator(_),
+                    ..
+                })
+            )
+        });
+        let balance_generators = link.callback(|_| Msg::BalanceGeneratorClocks);
 
         let ondragover = self.drag_over_handler(ctx, |insert_pos| Msg::DragOver { insert_pos });
         let ondragenter = self.drag_over_handler(ctx, |insert_pos| Msg::DragEnter { insert_pos });
@@ -53,26 +94,58 @@ impl NodeDisplay {
 
         let set_metadata = &ctx.props().set_metadata;
         let batch_set_metadata = &ctx.props().batch_set_metadata;
+        let set_global_metadata = &ctx.props().set_global_metadata;
+        let delete_node = &ctx.props().delete_node;
+        let guide_title = if group.name.is_empty() {
+            "Contents of this group".to_string()
+        } else {
+            format!("Contents of {}", group.name)
+        };
         html! {
-            <div class="NodeDisplay group expanded" key={group.id.as_u128()}>
+            <div class={classes!("NodeDisplay", "group", "expanded", group.tag.map(tag_class))}
+                key={group.id.as_u128()}
+                data-path={dom_path(&ctx.props().path)}>
                 <div class="header">
                     {self.drag_handle(ctx)}
                     <GroupName name={group.name.clone()} {rename} />
+                    {self.primary_product(ctx, group)}
+                    {self.power_chip(ctx)}
+                    if ctx.global_meta().show_foundation_estimates {
+                        {self.foundation_estimate(ctx)}
+                    }
                     {self.child_warnings(ctx)}
+                    {self.pure_line_warning(ctx, group)}
+                    {self.filter_button(ctx, group)}
+                    {self.gross_button(ctx)}
+                    {self.tag_button(ctx)}
                     {self.collapse_button(ctx, group)}
+                    {self.show_buildings_button(ctx, group)}
                     if !ctx.props().path.is_empty() {
                         <VirtualCopies copies={group.copies} {update_copies} />
                     }
+                    {self.normalize_button(ctx, group)}
+                    {self.flatten_button(ctx, group)}
+                    {self.merge_button(ctx)}
+                    {self.wrap_button(ctx)}
                     {self.copy_button(ctx)}
+                    {self.copy_scaled_button(ctx)}
                     {self.delete_button(ctx)}
                 </div>
                 <div class="body">
+                    <div class={classes!("indent-guide", guide_depth_class(ctx.props().path.len()))}
+                        title={guide_title} />
                     <div class="children-display"
                         {ondragover} {ondragenter} {ondragleave} {ondrop}
                         ref={self.children.clone()}>
-                        { for group.children.iter().cloned().enumerate().map(|(i, node)| {
+                        { for group.children.iter().cloned().enumerate()
+                            .filter(|(_, node)| !hide_buildings || node.group().is_some())
+                            .map(|(i, node)| {
                             let mut path = ctx.props().path.clone();
                             path.push(i);
+                            // Groups can be merged with a group directly below them.
+                            let next_is_group = node.group().is_some()
+                                && group.get_child(i + 1).is_some_and(|next| next.group().is_some());
+                            let merge_next = next_is_group.then(|| merge_next.clone());
                             html! {
                                 <>
                                     if self.insert_pos == Some(i) {
@@ -82,27 +155,81 @@ impl NodeDisplay {
                                         replace={replace.clone()}
                                         delete={delete.clone()}
                                         copy={copy.clone()}
+                                        copy_scaled={copy_scaled.clone()}
+                                        split={split.clone()}
+                                        match_demand={match_demand.clone()}
+                                        {merge_next}
                                         move_node={move_node.clone()}
+                                        delete_node={delete_node.clone()}
                                         set_metadata={set_metadata.clone()}
-                                        batch_set_metadata={batch_set_metadata.clone()} />
+                                        batch_set_metadata={batch_set_metadata.clone()}
+                                        set_global_metadata={set_global_metadata.clone()} />
                                 </>
                             }
                         }) }
                         if self.insert_pos == Some(group.children.len()) {
                             <div class={DRAG_INSERT_POINT} />
                         }
+                        if hidden_buildings > 0 {
+                            <div class="hidden-buildings">
+                                {format!("{} {} hidden", hidden_buildings,
+                                    if hidden_buildings == 1 { "building" } else { "buildings" })}
+                            </div>
+                        }
                     </div>
                     {self.view_balance(ctx, true)}
                 </div>
+                if self.show_combined_producers {
+                    {self.view_combined_producers(ctx, group)}
+                }
                 <div class="footer">
-                    <button class="create create-group" title="Add Group"
-                        onclick={add_group}>
-                        <span class="material-icons">{"create_new_folder"}</span>
-                    </button>
-                    <button class="create create-building" title="Add Building"
-                        onclick={add_building}>
-                        <span class="material-icons">{"add"}</span>
+                    if has_generators {
+                        <button class="balance-generators"
+                            title="Set generator clocks to balance this group's power"
+                            onclick={balance_generators}>
+                            <span class="material-icons">{"bolt"}</span>
+                        </button>
+                    }
+                    if group.children.len() > 1 {
+                        <button class="sort-children" title="Sort by Name"
+                            onclick={link.callback(|_| Msg::SortChildren { key: SortKey::Name })}>
+                            <span class="material-icons">{"sort_by_alpha"}</span>
+                        </button>
+                        <button class="sort-children" title="Sort by Power Use"
+                            onclick={link.callback(|_| Msg::SortChildren { key: SortKey::Power })}>
+                            <span class="material-icons">{"sort"}</span>
+                        </button>
+                    }
+                    {self.cleanup_button(ctx, group)}
+                    {self.pure_line_button(ctx, group)}
+                    if !ctx.props().path.is_empty() && ctx.props().node.balance().power < 0.0 {
+                        <button class="scale-to-power" title="Scale to Power Budget"
+                            onclick={link.callback(|_| Msg::ScaleToPowerBudget)}>
+                            <span class="material-icons">{"electrical_services"}</span>
+                        </button>
+                    }
+                    {self.export_button(ctx)}
+                    {self.planner_export_button(ctx)}
+                    {self.import_button(ctx)}
+                    <button class="combined-producers-toggle"
+                        title="Show/Hide Items With Several Producers"
+                        onclick={link.callback(|_| Msg::ToggleCombinedProducers)}>
+                        <span class="material-icons">{"call_merge"}</span>
                     </button>
+                    {self.solver_button(ctx)}
+                    if ctx.compact_layout() {
+                        {self.add_menu(ctx, add_group, add_building)}
+                    } else {
+                        {self.quick_add_buttons(ctx)}
+                        <button class="create create-group" title="Add Group"
+                            onclick={add_group}>
+                            <span class="material-icons">{"create_new_folder"}</span>
+                        </button>
+                        <button class="create create-building" title="Add Building"
+                            onclick={add_building}>
+                            <span class="material-icons">{"add"}</span>
+                        </button>
+                    }
                 </div>
             </div>
         }
@@ -112,17 +239,26 @@ impl NodeDisplay {
         let rename = ctx.link().callback(|name| Msg::Rename { name });
         let update_copies = ctx.link().callback(|copies| Msg::SetCopyCount { copies });
         html! {
-            <div class="NodeDisplay group collapsed" key={group.id.as_u128()}>
+            <div class={classes!("NodeDisplay", "group", "collapsed", group.tag.map(tag_class))}
+                key={group.id.as_u128()}
+                data-path={dom_path(&ctx.props().path)}>
                 <div class="summary">
                     {self.drag_handle(ctx)}
                     <GroupName name={group.name.clone()} {rename} />
+                    {self.primary_product(ctx, group)}
+                    {self.power_chip(ctx)}
                     {self.view_balance(ctx, false)}
                     {self.child_warnings(ctx)}
                     {self.collapse_button(ctx, group)}
                     if !ctx.props().path.is_empty() {
                         <VirtualCopies copies={group.copies} {update_copies} />
                     }
+                    {self.normalize_button(ctx, group)}
+                    {self.flatten_button(ctx, group)}
+                    {self.merge_button(ctx)}
+                    {self.wrap_button(ctx)}
                     {self.copy_button(ctx)}
+                    {self.copy_scaled_button(ctx)}
                     {self.delete_button(ctx)}
                 </div>
             </div>
@@ -130,6 +266,35 @@ impl NodeDisplay {
     }
 
     /// Get a collapse/expand button for this node.
+    /// Get a button which shows or hides the buildings of this group while only groups
+    /// are being shown. Empty otherwise.
+    fn show_buildings_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        if !ctx.global_meta().groups_only {
+            return html! {};
+        }
+        let meta = ctx.meta(group.id);
+        let set_metadata = ctx.props().set_metadata.clone();
+        let update = (
+            group.id,
+            NodeMeta {
+                show_buildings: !meta.show_buildings,
+                ..meta.clone()
+            },
+        );
+        let onclick = Callback::from(move |_| set_metadata.emit(update.clone()));
+        let title = if meta.show_buildings {
+            "Hide Buildings in This Group"
+        } else {
+            "Show Buildings in This Group"
+        };
+        html! {
+            <button class={classes!("show-buildings", meta.show_buildings.then(|| "active"))}
+                {onclick} {title}>
+                <span class="material-icons">{"precision_manufacturing"}</span>
+            </button>
+        }
+    }
+
     fn collapse_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
         if ctx.props().path.is_empty() {
             // No collapse for root.
@@ -160,6 +325,351 @@ impl NodeDisplay {
         }
     }
 
+    /// Get the button to solve for a target item, or the item chooser if choosing.
+    fn solver_button(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        if self.choosing_target {
+            let db = ctx.db();
+            let choices: Vec<_> = db
+                .items
+                .values()
+                .filter(|item| !item.produced_by.is_empty() && item.mined_by.is_empty())
+                .map(|item| Choice {
+                    id: item.id,
+                    name: item.name.clone(),
+                    image: html! {
+                        <Icon icon={item.image.clone()}/>
+                    },
+                    detail: None,
+                })
+                .collect();
+            let selected = link.callback(|id| Msg::SolveFor { id });
+            let cancelled = link.callback(|()| Msg::ToggleChoosingTarget { choosing: false });
+            let initial = ctx.props().node.primary_product();
+            html! {
+                <ChooseFromList<ItemId> {choices} {selected} {cancelled} {initial} />
+            }
+        } else {
+            let onclick = link.callback(|_| Msg::ToggleChoosingTarget { choosing: true });
+            html! {
+                <button class="create solve" title="Add Production Chain for an Item"
+                    {onclick}>
+                    <span class="material-icons">{"calculate"}</span>
+                </button>
+            }
+        }
+    }
+
+    /// Get buttons which add a building of a frequently used type.
+    fn quick_add_buttons(&self, ctx: &Context<Self>) -> Html {
+        let db = ctx.db();
+        let buttons = ctx
+            .global_meta()
+            .quick_add_buildings()
+            .into_iter()
+            .filter_map(|id| db.get(id))
+            .map(|building| {
+                let id = building.id;
+                let onclick = ctx.link().callback(move |_| Msg::QuickAddBuilding { id });
+                html! {
+                    <button class="create quick-add" title={format!("Add {}", building.name)}
+                        {onclick}>
+                        <Icon icon={building.image.clone()} />
+                    </button>
+                }
+            });
+        html! { for buttons }
+    }
+
+    /// In the compact layout, a single button which opens a menu of the ways to add a
+    /// child, in place of a button for each.
+    fn add_menu(
+        &self,
+        ctx: &Context<Self>,
+        add_group: Callback<MouseEvent>,
+        add_building: Callback<MouseEvent>,
+    ) -> Html {
+        let toggle = ctx.link().callback(|_| Msg::ToggleAddMenu);
+        let db = ctx.db();
+        let quick_adds = ctx
+            .global_meta()
+            .quick_add_buildings()
+            .into_iter()
+            .filter_map(|id| db.get(id))
+            .map(|building| {
+                let id = building.id;
+                let onclick = ctx.link().callback(move |_| Msg::QuickAddBuilding { id });
+                html! {
+                    <button class="add-option" {onclick}>
+                        <Icon icon={building.image.clone()} />
+                        <span>{&building.name}</span>
+                    </button>
+                }
+            });
+        html! {
+            <div class="add-menu">
+                <button class={classes!("create", "add-menu-toggle",
+                        self.add_menu_open.then(|| "active"))}
+                    title="Add…" onclick={toggle}>
+                    <span class="material-icons">{"add"}</span>
+                </button>
+                if self.add_menu_open {
+                    <div class="add-options">
+                        <button class="add-option" onclick={add_group}>
+                            <span class="material-icons">{"create_new_folder"}</span>
+                            <span>{"Group"}</span>
+                        </button>
+                        <button class="add-option" onclick={add_building}>
+                            <span class="material-icons">{"add"}</span>
+                            <span>{"Building"}</span>
+                        </button>
+                        { for quick_adds }
+                    </div>
+                }
+            </div>
+        }
+    }
+
+    /// Get a button to remove the empty groups below this group, if there are any.
+    fn cleanup_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let empty_groups = graph_manipulation::count_empty_groups(group);
+        if empty_groups == 0 {
+            html! {}
+        } else {
+            let onclick = ctx.link().callback(|_| Msg::RemoveEmptyGroups);
+            let title = format!("Remove {} Empty Group(s)", empty_groups);
+            html! {
+                <button class="remove-empty-groups" {onclick} {title}>
+                    <span class="material-icons">{"cleaning_services"}</span>
+                </button>
+            }
+        }
+    }
+
+    /// Get a button to switch pure line mode on or off for this group.
+    fn pure_line_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let meta = ctx.meta(group.id);
+        let set_metadata = ctx.props().set_metadata.clone();
+        let update = (
+            group.id,
+            NodeMeta {
+                pure_line: !meta.pure_line,
+                ..meta.clone()
+            },
+        );
+        let onclick = Callback::from(move |_| set_metadata.emit(update.clone()));
+        let title = if meta.pure_line {
+            "Stop Warning About Products Other Than the Primary Product"
+        } else {
+            "Warn About Products Other Than the Primary Product (Pure Line)"
+        };
+        html! {
+            <button class={classes!("pure-line-toggle", meta.pure_line.then(|| "active"))}
+                {onclick} {title}>
+                <span class="material-icons">{"looks_one"}</span>
+            </button>
+        }
+    }
+
+    /// In pure line mode, show a warning listing the children which make items other than
+    /// the group's primary product, if there are any.
+    fn pure_line_warning(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        if !ctx.meta(group.id).pure_line {
+            return html! {};
+        }
+        let node = &ctx.props().node;
+        let primary = match node.primary_product() {
+            Some(primary) => primary,
+            None => return html! {},
+        };
+        let tolerance = ctx.global_meta().balance_tolerance();
+        let stray = graph_manipulation::stray_products(group, node.balance(), primary, tolerance);
+        if stray.is_empty() {
+            return html! {};
+        }
+        let db = ctx.db();
+        let mut title = String::from("Not a pure line:");
+        for (idx, item) in stray {
+            let item = db
+                .get(item)
+                .map_or("Unknown Item", |item| item.name.as_ref());
+            let child = graph_manipulation::node_label(&group.children[idx], &db);
+            let child = if child.is_empty() {
+                "unnamed group"
+            } else {
+                &child
+            };
+            title.push_str(&format!("\n{} also makes {}", child, item));
+        }
+        html! {
+            <span class="material-icons warning pure-line-warning" {title}>
+                {"call_split"}
+            </span>
+        }
+    }
+
+    /// Show the estimated number of foundations this group's buildings cover, noting any
+    /// buildings which aren't counted because their footprint isn't known.
+    fn foundation_estimate(&self, ctx: &Context<Self>) -> Html {
+        let db = ctx.db();
+        let estimate = graph_manipulation::estimate_foundations(&ctx.props().node, &db);
+        let mut title = String::from(
+            "Approximate number of foundations covered by buildings, not counting space for belts and pipes",
+        );
+        if !estimate.missing.is_empty() {
+            let names: Vec<_> = estimate
+                .missing
+                .iter()
+                .map(|&id| db.get(id).map_or("Unknown Building", |b| b.name.as_ref()))
+                .collect();
+            title.push_str(&format!(
+                "\nNot counted, no footprint known: {}",
+                names.join(", ")
+            ));
+        }
+        html! {
+            <span class={classes!("foundation-estimate",
+                    (!estimate.missing.is_empty()).then(|| "incomplete"))} {title}>
+                <span class="material-icons">{"grid_on"}</span>
+                <span>{estimate.foundations}</span>
+                if !estimate.missing.is_empty() {
+                    <span>{"+"}</span>
+                }
+            </span>
+        }
+    }
+
+    /// Show the primary product of this group with its net rate, or the chooser for the
+    /// primary product if choosing.
+    fn primary_product(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let db = ctx.db();
+        let link = ctx.link();
+        let node = &ctx.props().node;
+        if self.choosing_primary {
+            let automatic = Choice {
+                id: None,
+                name: "Automatic (largest output)".into(),
+                image: html! {
+                    <span class="material-icons">{"auto_awesome"}</span>
+                },
+                detail: None,
+            };
+            let choices: Vec<_> = std::iter::once(automatic)
+                .chain(node.balance().balances.keys().filter_map(|&id| {
+                    let item = db.get(id)?;
+                    Some(Choice {
+                        id: Some(id),
+                        name: item.name.clone(),
+                        image: html! {
+                            <Icon icon={item.image.clone()}/>
+                        },
+                        detail: None,
+                    })
+                }))
+                .collect();
+            let selected = link.callback(|item| Msg::SetPrimaryProduct { item });
+            let cancelled = link.callback(|()| Msg::ToggleChoosingPrimary { choosing: false });
+            let initial = Some(group.primary_product);
+            return html! {
+                <ChooseFromList<Option<ItemId>> {choices} {selected} {cancelled} {initial} />
+            };
+        }
+        let onclick = link.callback(|_| Msg::ToggleChoosingPrimary { choosing: true });
+        match node.primary_product() {
+            Some(id) => {
+                let rate = node
+                    .balance()
+                    .balances
+                    .get(&id)
+                    .copied()
+                    .unwrap_or_default();
+                let (name, icon) = match db.get(id) {
+                    Some(item) => (item.name.to_string(), Some(item.image.clone())),
+                    None => ("Unknown Item".to_string(), None),
+                };
+                let inferred = group.primary_product.is_none();
+                let title = if inferred {
+                    format!("Primary Product (inferred): {}", name)
+                } else {
+                    format!("Primary Product: {}", name)
+                };
+                html! {
+                    <span class={classes!("primary-product", inferred.then(|| "inferred"))}
+                        {title} {onclick}>
+                        <Icon {icon} />
+                        <span>{ctx.global_meta().rate_unit.format(rate)}</span>
+                    </span>
+                }
+            }
+            None => html! {
+                <button class="primary-product" title="Set Primary Product" {onclick}>
+                    <span class="material-icons">{"star_outline"}</span>
+                </button>
+            },
+        }
+    }
+
+    /// Get a button to normalize this group to a single copy, if it has more than one.
+    fn normalize_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        if group.copies <= 1 {
+            html! {}
+        } else {
+            let onclick = ctx.link().callback(|_| Msg::Normalize);
+            html! {
+                <button class="normalize" {onclick} title="Normalize to 1x">
+                    <span class="material-icons">{"exposure_neg_1"}</span>
+                </button>
+            }
+        }
+    }
+
+    /// Get a button to replace this group with its only child, if it has exactly one.
+    fn flatten_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        if ctx.props().path.is_empty() || group.children.len() != 1 {
+            html! {}
+        } else {
+            let onclick = ctx.link().callback(|_| Msg::Flatten);
+            html! {
+                <button class="flatten" {onclick} title="Ungroup">
+                    <span class="material-icons">{"folder_off"}</span>
+                </button>
+            }
+        }
+    }
+
+    /// Get a button to start or stop editing the balance filters of this group.
+    fn filter_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let onclick = ctx.link().callback(|_| Msg::ToggleFilterEditing);
+        let active = self.editing_filters || ctx.meta(group.id).has_filters();
+        let title = if self.editing_filters {
+            "Done Editing Filters"
+        } else {
+            "Filter Balance"
+        };
+        html! {
+            <button class={classes!("filter-balance", active.then(|| "active"))}
+                {onclick} {title}>
+                <span class="material-icons">{"filter_alt"}</span>
+            </button>
+        }
+    }
+
+    /// Get a button to show or hide gross production and consumption in the balance.
+    fn gross_button(&self, ctx: &Context<Self>) -> Html {
+        let onclick = ctx.link().callback(|_| Msg::ToggleGross);
+        let title = if self.show_gross {
+            "Show Net Balance Only"
+        } else {
+            "Show Gross Production and Consumption"
+        };
+        html! {
+            <button class={classes!("gross-balance", self.show_gross.then(|| "active"))}
+                {onclick} {title}>
+                <span class="material-icons">{"compare_arrows"}</span>
+            </button>
+        }
+    }
+
     /// Show an icon to notify if any children have warnings.
     fn child_warnings(&self, ctx: &Context<Self>) -> Html {
         if ctx.props().node.children_had_warnings() {
@@ -0,0 +1,144 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Saving a group to a file and loading groups from files.
+
+use gloo::dialogs::alert;
+use gloo::file::{Blob, ObjectUrl};
+use gloo::timers::callback::Timeout;
+use log::warn;
+use satisfactory_accounting::accounting::{Group, Node};
+use satisfactory_accounting::database::Database;
+use satisfactory_accounting::{io, schema};
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, HtmlInputElement};
+use yew::prelude::*;
+
+use crate::node_display::{Msg, NodeDisplay};
+
+impl NodeDisplay {
+    /// Button to download this group as a standalone factory file.
+    pub(super) fn export_button(&self, ctx: &Context<Self>) -> Html {
+        let onclick = ctx.link().callback(|_| Msg::ExportGroup);
+        html! {
+            <button class="export-group" title="Export Group to File" {onclick}>
+                <span class="material-icons">{"file_download"}</span>
+            </button>
+        }
+    }
+
+    /// Button to download this group in the Satisfactory Tools planner format.
+    pub(super) fn planner_export_button(&self, ctx: &Context<Self>) -> Html {
+        let onclick = ctx.link().callback(|_| Msg::ExportPlanner);
+        html! {
+            <button class="export-planner" title="Export Group for Satisfactory Tools" {onclick}>
+                <span class="material-icons">{"ios_share"}</span>
+            </button>
+        }
+    }
+
+    /// Button to load a factory file and add it to this group.
+    pub(super) fn import_button(&self, ctx: &Context<Self>) -> Html {
+        let onchange = ctx.link().batch_callback(|e: Event| {
+            let input: HtmlInputElement = e.target()?.dyn_into().ok()?;
+            let file = input.files()?.get(0);
+            // Clear the input so the same file can be imported again.
+            input.set_value("");
+            file.map(|file| Msg::ImportFile { file: file.into() })
+        });
+        html! {
+            <label class="import-group" title="Import Group or Satisfactory Tools Factory">
+                <input type="file" accept=".json,application/json" {onchange} />
+                <span class="material-icons">{"file_upload"}</span>
+            </label>
+        }
+    }
+}
+
+/// Serialize a group to the stored document format, with fresh ids so the file doesn't
+/// share groups with this tree.
+pub(in crate::node_display) fn export_group(group: &Group) -> String {
+    let node: Node = group.create_copy().into();
+    schema::store(&node).to_string()
+}
+
+/// Load the contents of an imported file, which may be either a stored factory or a
+/// Satisfactory Tools factory. Anything the planner format couldn't carry over is
+/// reported to the user.
+pub(in crate::node_display) fn load_import(text: &str, db: &Database) -> Result<Node, String> {
+    let stored: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if !io::is_planner_factory(&stored) {
+        return schema::load(stored).map_err(|e| e.to_string());
+    }
+    let import = io::import(stored, db).map_err(|e| e.to_string())?;
+    if !import.lost.is_empty() {
+        alert(&format!(
+            "Some parts of the factory couldn't be imported:\n{}",
+            describe_lost(&import.lost)
+        ));
+    }
+    Ok(import.group.into())
+}
+
+/// List lost parts of a conversion, one per line.
+pub(in crate::node_display) fn describe_lost<'a>(
+    lost: impl IntoIterator<Item = &'a io::Lost>,
+) -> String {
+    lost.into_iter()
+        .map(|lost| format!("- {}", lost))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Start a download of the given JSON as a file.
+pub(in crate::node_display) fn download_json(name: &str, json: String) {
+    let blob = Blob::new_with_options(json.as_str(), Some("application/json"));
+    let url = ObjectUrl::from(blob);
+    let link = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.create_element("a").ok())
+        .and_then(|link| link.dyn_into::<HtmlElement>().ok());
+    let link = match link {
+        Some(link) => link,
+        None => {
+            warn!("Unable to create a download link");
+            return;
+        }
+    };
+    let filename = format!("{}.json", file_stem(name));
+    if let Err(e) = link
+        .set_attribute("href", &url)
+        .and_then(|()| link.set_attribute("download", &filename))
+    {
+        warn!("Unable to set up the download link: {:?}", e);
+        return;
+    }
+    link.click();
+    // Keep the URL alive briefly, since some browsers start the download asynchronously.
+    Timeout::new(1000, move || drop(url)).forget();
+}
+
+/// Build a file name from a group name, keeping only characters which are safe in file
+/// names on every platform.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.is_empty() {
+        "group".to_string()
+    } else {
+        stem
+    }
+}
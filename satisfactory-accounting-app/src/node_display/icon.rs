@@ -28,10 +28,49 @@ pub fn icon(props: &Props) -> Html {
     }
 }
 
-/// Get the icon path for a given slug name.
+/// Path or URL icons are loaded from. Can be overridden by setting `ICON_BASE_PATH` at
+/// build time, for deployments under a subpath or serving icons from a CDN.
+const ICON_BASE_PATH: &str = match option_env!("ICON_BASE_PATH") {
+    Some(path) => path,
+    None => "/images/items/",
+};
+
+/// Get the icon path for a given slug name. This is the only place icon paths are built,
+/// so anything showing an item or building image should go through [`Icon`].
 fn slug_to_icon(slug: impl AsRef<str>) -> String {
-    let mut icon = slug.as_ref().to_owned();
-    icon.insert_str(0, "/images/items/");
+    icon_path(ICON_BASE_PATH, slug.as_ref())
+}
+
+/// Build the path of the icon for the given slug under the given base path.
+fn icon_path(base: &str, slug: &str) -> String {
+    let mut icon = base.to_owned();
+    if !icon.ends_with('/') {
+        icon.push('/');
+    }
+    icon.push_str(slug);
     icon.push_str("_64.png");
     icon
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icon_paths() {
+        assert_eq!(
+            icon_path("/images/items/", "desc-ironplate-c"),
+            "/images/items/desc-ironplate-c_64.png"
+        );
+        assert_eq!(
+            icon_path("https://cdn.example.com/icons", "desc-ironplate-c"),
+            "https://cdn.example.com/icons/desc-ironplate-c_64.png"
+        );
+        if option_env!("ICON_BASE_PATH").is_none() {
+            assert_eq!(
+                slug_to_icon("desc-ironplate-c"),
+                "/images/items/desc-ironplate-c_64.png"
+            );
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Summary of items produced by more than one child of a group.
+
+use std::collections::BTreeMap;
+
+use satisfactory_accounting::accounting::Group;
+use satisfactory_accounting::database::ItemId;
+use yew::prelude::*;
+
+use crate::app::BeltTier;
+use crate::node_display::icon::Icon;
+use crate::node_display::NodeDisplay;
+use crate::CtxHelper;
+
+/// Combined output of an item from several children of a group.
+struct CombinedProducers {
+    /// Item being produced.
+    item: ItemId,
+    /// Number of children producing the item.
+    producers: usize,
+    /// Total rate the children produce the item at.
+    total: f32,
+}
+
+impl NodeDisplay {
+    /// Build the display for the items produced by more than one child of this group.
+    pub(super) fn view_combined_producers(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let db = ctx.db();
+        let rate_unit = ctx.global_meta().rate_unit;
+        let combined = combined_producers(group);
+        html! {
+            <div class="combined-producers">
+                if combined.is_empty() {
+                    <span class="empty">{"No item has more than one producer"}</span>
+                } else {
+                    <table>
+                        <tr>
+                            <th>{"Item"}</th>
+                            <th>{"Producers"}</th>
+                            <th>{"Combined"}</th>
+                            <th>{"Belt"}</th>
+                        </tr>
+                        { for combined.iter().map(|combined| {
+                            let (title, icon) = match db.get(combined.item) {
+                                Some(item) => (item.name.clone(), Some(item.image.clone())),
+                                None => ("Unknown Item".into(), None),
+                            };
+                            html! {
+                                <tr>
+                                    <td {title}><Icon {icon} /></td>
+                                    <td>{combined.producers}</td>
+                                    <td>{rate_unit.format(combined.total)}</td>
+                                    <td>{belt_requirement(combined.total)}</td>
+                                </tr>
+                            }
+                        }) }
+                    </table>
+                }
+            </div>
+        }
+    }
+}
+
+/// Find every item which is produced by at least two direct children of the group.
+fn combined_producers(group: &Group) -> Vec<CombinedProducers> {
+    let mut combined: BTreeMap<ItemId, (usize, f32)> = BTreeMap::new();
+    for child in &group.children {
+        for (&item, &rate) in &child.balance().balances {
+            if rate > 0.0 {
+                let (producers, total) = combined.entry(item).or_default();
+                *producers += 1;
+                *total += rate;
+            }
+        }
+    }
+    combined
+        .into_iter()
+        .filter(|&(_, (producers, _))| producers >= 2)
+        .map(|(item, (producers, total))| CombinedProducers {
+            item,
+            producers,
+            total,
+        })
+        .collect()
+}
+
+/// Describe the belt needed to carry the given rate out of a single merger, or how many
+/// of the fastest belts are needed if one isn't enough.
+fn belt_requirement(rate: f32) -> String {
+    match BeltTier::ALL.iter().find(|tier| rate <= tier.capacity()) {
+        Some(tier) => tier.name().to_string(),
+        None => {
+            let fastest = BeltTier::ALL[BeltTier::ALL.len() - 1];
+            format!(
+                "{} × {} (too much for one belt)",
+                (rate / fastest.capacity()).ceil(),
+                fastest.name()
+            )
+        }
+    }
+}
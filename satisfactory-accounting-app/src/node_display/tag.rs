@@ -0,0 +1,63 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Color tags for visually grouping nodes.
+
+use satisfactory_accounting::accounting::ColorTag;
+use yew::prelude::*;
+
+use crate::node_display::{Msg, NodeDisplay};
+
+impl NodeDisplay {
+    /// Build the button showing this node's color tag, or the picker to change it.
+    pub(super) fn tag_button(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let current = ctx.props().node.tag();
+        if self.choosing_tag {
+            let clear = link.callback(|_| Msg::SetTag { tag: None });
+            html! {
+                <span class="tag-picker">
+                    { for ColorTag::ALL.iter().map(|&tag| {
+                        let onclick = link.callback(move |_| Msg::SetTag { tag: Some(tag) });
+                        html! {
+                            <button class={classes!("tag-swatch", tag_class(tag),
+                                    (current == Some(tag)).then(|| "active"))}
+                                title={tag.name()} {onclick} />
+                        }
+                    }) }
+                    <button class="tag-clear" title="No Tag" onclick={clear}>
+                        <span class="material-icons">{"label_off"}</span>
+                    </button>
+                </span>
+            }
+        } else {
+            let onclick = link.callback(|_| Msg::ToggleChoosingTag { choosing: true });
+            let (title, icon) = match current {
+                Some(tag) => (format!("Color Tag: {}", tag.name()), "label"),
+                None => ("Color Tag".to_string(), "label_outline"),
+            };
+            html! {
+                <button class={classes!("tag-button", current.map(tag_class))}
+                    {onclick} {title}>
+                    <span class="material-icons">{icon}</span>
+                </button>
+            }
+        }
+    }
+}
+
+/// CSS class used to color nodes and swatches with the given tag.
+pub(super) fn tag_class(tag: ColorTag) -> &'static str {
+    match tag {
+        ColorTag::Red => "tag-red",
+        ColorTag::Orange => "tag-orange",
+        ColorTag::Yellow => "tag-yellow",
+        ColorTag::Green => "tag-green",
+        ColorTag::Blue => "tag-blue",
+        ColorTag::Purple => "tag-purple",
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Nudging numeric values with the scroll wheel.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gloo::events::{EventListener, EventListenerOptions};
+use wasm_bindgen::JsCast;
+use web_sys::{Element, WheelEvent};
+use yew::prelude::*;
+
+/// Number of steps a single wheel notch moves the value while a modifier is held.
+const LARGE_STEP: i32 = 10;
+
+/// Listens for the scroll wheel over a value display and reports the number of steps to
+/// move the value by: positive for scrolling up, and `LARGE_STEP` times as many while
+/// Shift, Ctrl, or Alt is held.
+///
+/// Wheel events are only captured once the pointer has moved over the element, so
+/// scrolling the page doesn't start changing values as they pass under a stationary
+/// pointer. Listeners are removed when this is dropped.
+pub(crate) struct WheelNudge {
+    _listeners: [EventListener; 3],
+}
+
+impl WheelNudge {
+    /// Start listening for wheel events on `element`.
+    pub(crate) fn new(element: &Element, nudge: Callback<i32>) -> Self {
+        let hovered = Rc::new(Cell::new(false));
+        let on_move = {
+            let hovered = Rc::clone(&hovered);
+            EventListener::new(element, "mousemove", move |_| hovered.set(true))
+        };
+        let on_leave = {
+            let hovered = Rc::clone(&hovered);
+            EventListener::new(element, "mouseleave", move |_| hovered.set(false))
+        };
+        // Non-passive so that scrolling over the value doesn't also scroll the page.
+        let on_wheel = EventListener::new_with_options(
+            element,
+            "wheel",
+            EventListenerOptions::enable_prevent_default(),
+            move |e| {
+                if !hovered.get() {
+                    return;
+                }
+                let e = match e.dyn_ref::<WheelEvent>() {
+                    Some(e) => e,
+                    None => return,
+                };
+                // Browsers often turn Shift+wheel into horizontal scrolling.
+                let delta = if e.delta_y() != 0.0 {
+                    e.delta_y()
+                } else {
+                    e.delta_x()
+                };
+                if delta == 0.0 {
+                    return;
+                }
+                e.prevent_default();
+                let step = if e.shift_key() || e.ctrl_key() || e.alt_key() {
+                    LARGE_STEP
+                } else {
+                    1
+                };
+                nudge.emit(if delta < 0.0 { step } else { -step });
+            },
+        );
+        Self {
+            _listeners: [on_move, on_leave, on_wheel],
+        }
+    }
+}
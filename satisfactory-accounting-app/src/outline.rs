@@ -0,0 +1,92 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Compact tree of group names for navigating large factories.
+
+use satisfactory_accounting::accounting::{Group, Node};
+use uuid::Uuid;
+use yew::prelude::*;
+
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Root node of the factory.
+    pub root: Node,
+    /// Callback to focus the group with the given id in the main view.
+    pub focus: Callback<Uuid>,
+}
+
+/// Shows the names of all groups as a tree. Groups which are collapsed in the main view
+/// are shown collapsed here too.
+pub struct Outline;
+
+impl Component for Outline {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="Outline">
+                if let Some(root) = ctx.props().root.group() {
+                    {self.view_children(ctx, root)}
+                }
+            </div>
+        }
+    }
+}
+
+impl Outline {
+    /// Display the child groups of a group, if it has any.
+    fn view_children(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let children: Vec<_> = group
+            .children
+            .iter()
+            .filter_map(|child| child.group())
+            .collect();
+        if children.is_empty() {
+            return html! {};
+        }
+        html! {
+            <ul>
+                { for children.into_iter().map(|child| self.view_group(ctx, child)) }
+            </ul>
+        }
+    }
+
+    /// Display a single group entry and, if it is expanded, its child groups.
+    fn view_group(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let id = group.id;
+        let onclick = ctx.props().focus.reform(move |_| id);
+        let collapsed = ctx.meta(id).is_collapsed();
+        let name = if group.name.is_empty() {
+            "unnamed"
+        } else {
+            group.name.as_str()
+        };
+        html! {
+            <li key={id.as_u128()}>
+                <span class={classes!("entry", group.name.is_empty().then(|| "unnamed"))}
+                    {onclick}>
+                    if collapsed {
+                        <span class="material-icons">{"chevron_right"}</span>
+                    } else {
+                        <span class="material-icons">{"expand_more"}</span>
+                    }
+                    <span>{name}</span>
+                </span>
+                if !collapsed {
+                    {self.view_children(ctx, group)}
+                }
+            </li>
+        }
+    }
+}
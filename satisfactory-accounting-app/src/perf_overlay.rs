@@ -0,0 +1,156 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Developer overlay showing how long the tree takes to render.
+
+use std::cell::RefCell;
+
+use gloo::timers::callback::Interval;
+use yew::prelude::*;
+
+/// How often the overlay refreshes its figures, in milliseconds.
+const REFRESH_INTERVAL: u32 = 500;
+
+thread_local! {
+    static STATS: RefCell<RenderStats> = RefCell::new(Default::default());
+}
+
+/// Render timings collected from the root component.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct RenderStats {
+    /// Time the current root render started, if one is in progress.
+    started: Option<f64>,
+    /// Duration of the most recent root render in milliseconds.
+    last: Option<f64>,
+    /// Longest root render seen in milliseconds.
+    max: Option<f64>,
+    /// Number of root renders measured.
+    renders: u64,
+    /// Number of `NodeDisplay` components currently mounted.
+    mounted_nodes: usize,
+}
+
+/// Get the current high resolution time in milliseconds.
+fn now() -> Option<f64> {
+    Some(web_sys::window()?.performance()?.now())
+}
+
+/// Record the start of a root render. Called at the start of the root view.
+pub fn start_render() {
+    STATS.with(|stats| stats.borrow_mut().started = now());
+}
+
+/// Record the end of a root render. Called once the root has been rendered to the DOM.
+pub fn finish_render() {
+    STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        if let (Some(start), Some(end)) = (stats.started.take(), now()) {
+            let elapsed = end - start;
+            stats.last = Some(elapsed);
+            stats.max = Some(stats.max.map_or(elapsed, |max| max.max(elapsed)));
+            stats.renders += 1;
+        }
+    });
+}
+
+/// Record that a `NodeDisplay` was mounted.
+pub fn node_mounted() {
+    STATS.with(|stats| stats.borrow_mut().mounted_nodes += 1);
+}
+
+/// Record that a `NodeDisplay` was unmounted.
+pub fn node_unmounted() {
+    STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        stats.mounted_nodes = stats.mounted_nodes.saturating_sub(1);
+    });
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Callback to hide the overlay.
+    pub close: Callback<()>,
+}
+
+pub enum Msg {
+    /// Re-read the collected stats.
+    Refresh,
+}
+
+/// Small fixed overlay which periodically shows the collected render stats. It polls
+/// rather than being told about renders, so showing it doesn't itself cause the tree to
+/// re-render.
+pub struct PerfOverlay {
+    /// Stats as of the last refresh.
+    stats: RenderStats,
+    /// Timer driving refreshes. Cancelled when dropped.
+    _refresh: Interval,
+}
+
+impl Component for PerfOverlay {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let link = ctx.link().clone();
+        Self {
+            stats: STATS.with(|stats| stats.borrow().clone()),
+            _refresh: Interval::new(REFRESH_INTERVAL, move || link.send_message(Msg::Refresh)),
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Refresh => {
+                let stats = STATS.with(|stats| stats.borrow().clone());
+                if stats == self.stats {
+                    false
+                } else {
+                    self.stats = stats;
+                    true
+                }
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let close = ctx.props().close.reform(|_| ());
+        let format_ms = |ms: Option<f64>| match ms {
+            Some(ms) => format!("{:.1} ms", ms),
+            None => "-".to_string(),
+        };
+        html! {
+            <div class="PerfOverlay">
+                <div class="header">
+                    <span class="material-icons">{"speed"}</span>
+                    <span class="title">{"Render Performance"}</span>
+                    <button class="close" title="Hide Performance Overlay" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <table>
+                    <tr>
+                        <th>{"Last render"}</th>
+                        <td>{format_ms(self.stats.last)}</td>
+                    </tr>
+                    <tr>
+                        <th>{"Slowest render"}</th>
+                        <td>{format_ms(self.stats.max)}</td>
+                    </tr>
+                    <tr>
+                        <th>{"Renders"}</th>
+                        <td>{self.stats.renders}</td>
+                    </tr>
+                    <tr>
+                        <th>{"Mounted nodes"}</th>
+                        <td>{self.stats.mounted_nodes}</td>
+                    </tr>
+                </table>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Side-by-side comparison of the recipes which produce an item.
+
+use satisfactory_accounting::database::{
+    BuildingKind, BuildingType, Database, ItemAmount, ItemId, Recipe,
+};
+use satisfactory_accounting::solver::{self, RecipeStrategy};
+use yew::prelude::*;
+
+use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
+use crate::node_display::icon::Icon;
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Callback to close the comparison.
+    pub close: Callback<()>,
+}
+
+/// Metric used to rank recipes. Lower is better for all metrics.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Metric {
+    /// Power used per item produced each minute.
+    #[default]
+    PowerPerItem,
+    /// Number of raw resources mined per item produced, with the recipe's ingredients
+    /// made by their standard recipes.
+    RawResourcesPerItem,
+}
+
+impl Metric {
+    /// Get a string suitable for human display of this metric.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PowerPerItem => "MW per item/min",
+            Self::RawResourcesPerItem => "Raw resources per item",
+        }
+    }
+
+    /// Get the next metric, wrapping around.
+    pub fn next(self) -> Self {
+        match self {
+            Self::PowerPerItem => Self::RawResourcesPerItem,
+            Self::RawResourcesPerItem => Self::PowerPerItem,
+        }
+    }
+}
+
+/// Messages for [`RecipeComparison`].
+pub enum Msg {
+    /// Switches in or out of choosing the item.
+    ToggleChoosing {
+        /// The new choosing state.
+        choosing: bool,
+    },
+    /// Select the item to compare recipes for.
+    SelectItem {
+        /// ID of the item.
+        id: ItemId,
+    },
+    /// Change the metric used to rank recipes.
+    SetMetric { metric: Metric },
+}
+
+/// Compares all recipes which produce a chosen item.
+#[derive(Default)]
+pub struct RecipeComparison {
+    /// Item being compared.
+    item: Option<ItemId>,
+    /// Whether the item is currently being chosen.
+    choosing: bool,
+    /// Metric used to rank the recipes.
+    metric: Metric,
+}
+
+impl Component for RecipeComparison {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Default::default()
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::ToggleChoosing { choosing } => {
+                self.choosing = choosing;
+                true
+            }
+            Msg::SelectItem { id } => {
+                self.item = Some(id);
+                self.choosing = false;
+                true
+            }
+            Msg::SetMetric { metric } => {
+                self.metric = metric;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let db = ctx.db();
+        let link = ctx.link();
+        let close = ctx.props().close.reform(|_| ());
+        let metric = self.metric;
+        let next_metric = link.callback(move |_| Msg::SetMetric {
+            metric: metric.next(),
+        });
+        let mut stats = match self.item {
+            Some(item) => recipe_stats(&db, item),
+            None => Vec::new(),
+        };
+        stats.sort_by(|s1, s2| s1.metric(metric).total_cmp(&s2.metric(metric)));
+        html! {
+            <div class="RecipeComparison">
+                <div class="header">
+                    <span class="title">{"Compare Recipes"}</span>
+                    {self.view_item(ctx, &db)}
+                    <button class="metric" onclick={next_metric} title="Ranking Metric">
+                        <span class="material-icons">{"sort"}</span>
+                        <span>{metric.name()}</span>
+                    </button>
+                    <button class="close" onclick={close} title="Close">
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                if !stats.is_empty() {
+                    <table class="recipes">
+                        <tr>
+                            <th>{"Recipe"}</th>
+                            <th>{"Building"}</th>
+                            <th>{"Cycle"}</th>
+                            <th>{"Inputs"}</th>
+                            <th>{"Outputs"}</th>
+                            <th>{"Power"}</th>
+                            <th>{metric.name()}</th>
+                        </tr>
+                        { for stats.iter().map(|stats| html! {
+                            <tr>
+                                <td>
+                                    <Icon icon={stats.recipe.image.clone()} />
+                                    <span>{&stats.recipe.name}</span>
+                                </td>
+                                <td>
+                                    <Icon icon={stats.building.image.clone()} />
+                                    <span>{&stats.building.name}</span>
+                                </td>
+                                <td>{format!("{:.2} s", 60.0 / stats.runs_per_minute)}</td>
+                                <td>{view_rates(&db, &stats.recipe.ingredients, stats.runs_per_minute)}</td>
+                                <td>{view_rates(&db, &stats.recipe.products, stats.runs_per_minute)}</td>
+                                <td>{format!("{} MW", stats.power)}</td>
+                                <td>{format!("{:.2}", stats.metric(metric))}</td>
+                            </tr>
+                        }) }
+                    </table>
+                }
+            </div>
+        }
+    }
+}
+
+impl RecipeComparison {
+    /// Display the chosen item, or the chooser if choosing.
+    fn view_item(&self, ctx: &Context<Self>, db: &Database) -> Html {
+        let link = ctx.link();
+        if self.choosing {
+            let choices: Vec<_> = db
+                .items
+                .values()
+                .filter(|item| !item.produced_by.is_empty())
+                .map(|item| Choice {
+                    id: item.id,
+                    name: item.name.clone(),
+                    image: html! {
+                        <Icon icon={item.image.clone()}/>
+                    },
+                    detail: None,
+                })
+                .collect();
+            let selected = link.callback(|id| Msg::SelectItem { id });
+            let cancelled = link.callback(|()| Msg::ToggleChoosing { choosing: false });
+            html! {
+                <span class="name" title="Item">
+                    <ChooseFromList<ItemId> {choices} {selected} {cancelled} />
+                </span>
+            }
+        } else {
+            let onclick = link.callback(|_| Msg::ToggleChoosing { choosing: true });
+            match self.item.and_then(|id| db.get(id)) {
+                Some(item) => html! {
+                    <span class="name" title="Item" {onclick}>
+                        <Icon icon={item.image.clone()} />
+                        <span>{&item.name}</span>
+                    </span>
+                },
+                None => html! {
+                    <span class="name" title="Item" {onclick}>{"select item"}</span>
+                },
+            }
+        }
+    }
+}
+
+/// Statistics for one recipe, run in one building at 100% clock speed.
+struct RecipeStats<'a> {
+    /// The recipe being run.
+    recipe: &'a Recipe,
+    /// The building the recipe is run in.
+    building: &'a BuildingType,
+    /// Number of times the recipe completes per minute.
+    runs_per_minute: f32,
+    /// Rate at which the compared item is produced, per minute.
+    rate: f32,
+    /// Power used by the building in MW.
+    power: f32,
+    /// Raw resources mined per item produced.
+    raw_per_item: f32,
+}
+
+impl RecipeStats<'_> {
+    /// Compute the value of the given metric for this recipe.
+    fn metric(&self, metric: Metric) -> f32 {
+        match metric {
+            Metric::PowerPerItem => self.power / self.rate,
+            Metric::RawResourcesPerItem => self.raw_per_item,
+        }
+    }
+}
+
+/// Compute the statistics for every recipe which produces the given item.
+fn recipe_stats(db: &Database, item: ItemId) -> Vec<RecipeStats<'_>> {
+    let item_info = match db.get(item) {
+        Some(item_info) => item_info,
+        None => return Vec::new(),
+    };
+    item_info
+        .produced_by
+        .iter()
+        .filter_map(|&recipe_id| {
+            let recipe = db.get(recipe_id)?;
+            let (building, m) = recipe.produced_in.iter().find_map(|&building_id| {
+                let building = db.get(building_id)?;
+                match &building.kind {
+                    BuildingKind::Manufacturer(m) => Some((building, m)),
+                    _ => None,
+                }
+            })?;
+            let runs_per_minute = 60.0 / recipe.time * m.manufacturing_speed;
+            let amount: f32 = recipe
+                .products
+                .iter()
+                .filter(|product| product.item == item)
+                .map(|product| product.amount)
+                .sum();
+            if amount <= 0.0 {
+                return None;
+            }
+            Some(RecipeStats {
+                recipe,
+                building,
+                runs_per_minute,
+                rate: amount * runs_per_minute,
+                power: m.power_consumption.power,
+                raw_per_item: raw_resources_per_item(db, recipe, amount),
+            })
+        })
+        .collect()
+}
+
+/// Count the raw resources mined to make one of an item with the given recipe, which
+/// makes `amount` of it per cycle. The recipe's ingredients are expanded with their
+/// standard recipes, so this compares just this recipe's choice. Byproducts aren't
+/// credited.
+fn raw_resources_per_item(db: &Database, recipe: &Recipe, amount: f32) -> f32 {
+    let targets: Vec<_> = recipe
+        .ingredients
+        .iter()
+        .map(|ingredient| (ingredient.item, ingredient.amount / amount))
+        .collect();
+    solver::solve(db, &targets, RecipeStrategy::Standard)
+        .imports
+        .iter()
+        .filter(|&(&item, _)| db.get(item).is_some_and(|info| !info.mined_by.is_empty()))
+        .map(|(_, &rate)| rate)
+        .sum()
+}
+
+/// Show the per-minute rates of a set of recipe inputs or outputs, with the amount per
+/// cycle in the title.
+fn view_rates(db: &Database, amounts: &[ItemAmount], runs_per_minute: f32) -> Html {
+    html! {
+        <div class="rates">
+            { for amounts.iter().map(|amount| {
+                let icon = db.get(amount.item).map(|item| item.image.clone());
+                let name = db
+                    .get(amount.item)
+                    .map(|item| item.name.as_ref())
+                    .unwrap_or("Unknown");
+                let title = format!("{}: {} per cycle", name, amount.amount);
+                html! {
+                    <span class="rate" {title}>
+                        <Icon {icon} />
+                        <span>{amount.amount * runs_per_minute}</span>
+                    </span>
+                }
+            }) }
+        </div>
+    }
+}
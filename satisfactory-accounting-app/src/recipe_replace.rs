@@ -0,0 +1,306 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Tree-wide replacement of one recipe with another.
+
+use std::collections::BTreeSet;
+
+use satisfactory_accounting::accounting::{BuildingSettings, Node};
+use satisfactory_accounting::database::{Database, RecipeId};
+use yew::prelude::*;
+
+use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
+use crate::node_display::icon::Icon;
+use crate::node_display::{node_label, replace_recipe, RecipeReplacement};
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Root node of the factory.
+    pub root: Node,
+    /// Callback to replace the root once the replacement is applied.
+    pub replace_root: Callback<Node>,
+    /// Callback to close the tool.
+    pub close: Callback<()>,
+}
+
+/// Which of the two recipes is being chosen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Side {
+    /// The recipe to find.
+    From,
+    /// The recipe to replace it with.
+    To,
+}
+
+/// Messages for [`RecipeReplace`].
+pub enum Msg {
+    /// Start choosing one of the recipes, or stop choosing.
+    Choose { side: Option<Side> },
+    /// Set one of the recipes.
+    Select { side: Side, id: RecipeId },
+    /// Apply the replacement to the tree.
+    Apply,
+}
+
+/// Lists the buildings using one recipe and switches them all to another.
+#[derive(Default)]
+pub struct RecipeReplace {
+    /// Recipe to find.
+    from: Option<RecipeId>,
+    /// Recipe to replace it with.
+    to: Option<RecipeId>,
+    /// Which recipe is being chosen, if any.
+    choosing: Option<Side>,
+}
+
+impl Component for RecipeReplace {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Default::default()
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Choose { side } => {
+                self.choosing = side;
+                true
+            }
+            Msg::Select { side, id } => {
+                self.choosing = None;
+                match side {
+                    Side::From => {
+                        self.from = Some(id);
+                        self.to = None;
+                    }
+                    Side::To => self.to = Some(id),
+                }
+                true
+            }
+            Msg::Apply => {
+                if let Some(replacement) = self.replacement(ctx) {
+                    ctx.props().replace_root.emit(replacement.root);
+                    self.from = None;
+                    self.to = None;
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let db = ctx.db();
+        let close = ctx.props().close.reform(|_| ());
+        html! {
+            <div class="RecipeReplace">
+                <div class="header">
+                    <span class="title">{"Replace Recipe"}</span>
+                    {self.view_recipe(ctx, &db, Side::From)}
+                    <span class="material-icons">{"arrow_forward"}</span>
+                    {self.view_recipe(ctx, &db, Side::To)}
+                    <button class="close" onclick={close} title="Close">
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                {self.view_preview(ctx, &db)}
+            </div>
+        }
+    }
+}
+
+impl RecipeReplace {
+    /// Compute the result of the replacement, if both recipes are chosen and any building
+    /// would change.
+    fn replacement(&self, ctx: &Context<Self>) -> Option<RecipeReplacement> {
+        let db = ctx.db();
+        replace_recipe(&ctx.props().root, self.from?, self.to?, &db)
+    }
+
+    /// Display one of the chosen recipes, or the chooser if choosing it.
+    fn view_recipe(&self, ctx: &Context<Self>, db: &Database, side: Side) -> Html {
+        let link = ctx.link();
+        let current = match side {
+            Side::From => self.from,
+            Side::To => self.to,
+        };
+        if self.choosing == Some(side) {
+            let ids = match side {
+                Side::From => used_recipes(&ctx.props().root),
+                Side::To => match self.from {
+                    Some(from) => alternatives(db, from),
+                    None => Vec::new(),
+                },
+            };
+            let choices: Vec<_> = ids
+                .into_iter()
+                .filter_map(|id| db.get(id))
+                .map(|recipe| Choice {
+                    id: recipe.id,
+                    name: recipe.name.clone(),
+                    image: html! {
+                        <Icon icon={recipe.image.clone()}/>
+                    },
+                    detail: None,
+                })
+                .collect();
+            let selected = link.callback(move |id| Msg::Select { side, id });
+            let cancelled = link.callback(|()| Msg::Choose { side: None });
+            return html! {
+                <span class="recipe">
+                    <ChooseFromList<RecipeId> {choices} {selected} {cancelled} />
+                </span>
+            };
+        }
+        let onclick = link.callback(move |_| Msg::Choose { side: Some(side) });
+        let disabled = side == Side::To && self.from.is_none();
+        match current.and_then(|id| db.get(id)) {
+            Some(recipe) => html! {
+                <button class="recipe" {onclick} {disabled}>
+                    <Icon icon={recipe.image.clone()} />
+                    <span>{&recipe.name}</span>
+                </button>
+            },
+            None => html! {
+                <button class="recipe" {onclick} {disabled}>
+                    {match side {
+                        Side::From => "find recipe",
+                        Side::To => "replace with",
+                    }}
+                </button>
+            },
+        }
+    }
+
+    /// Show the buildings which would change and the effect on the overall balance.
+    fn view_preview(&self, ctx: &Context<Self>, db: &Database) -> Html {
+        if self.from.is_none() || self.to.is_none() {
+            return html! {};
+        }
+        let replacement = match self.replacement(ctx) {
+            Some(replacement) => replacement,
+            None => {
+                return html! {
+                    <div class="preview empty">{"No unlocked buildings use this recipe."}</div>
+                };
+            }
+        };
+        let global_meta = ctx.global_meta();
+        let rate_unit = global_meta.rate_unit;
+        let old = ctx.props().root.balance();
+        let new = replacement.root.balance();
+        let delta = new.clone() - old;
+        let tolerance = global_meta.balance_tolerance();
+        let items: Vec<_> = delta
+            .balances
+            .iter()
+            .filter(|(_, rate)| rate.abs() > tolerance)
+            .map(|(&item, &rate)| {
+                let was_short =
+                    global_meta.is_deficit(old.balances.get(&item).copied().unwrap_or(0.0));
+                let now_short =
+                    global_meta.is_deficit(new.balances.get(&item).copied().unwrap_or(0.0));
+                (item, rate, now_short && !was_short)
+            })
+            .collect();
+        let apply = ctx.link().callback(|_| Msg::Apply);
+        html! {
+            <div class="preview">
+                <ul class="affected">
+                    { for replacement.changed.iter().map(|path| {
+                        let names = path_names(&ctx.props().root, path, db);
+                        html! { <li>{names.join(" › ")}</li> }
+                    }) }
+                </ul>
+                <div class="deltas">
+                    <span class={classes!("delta", global_meta.rate_style(delta.power))}
+                        title="Power">
+                        <span class="material-icons">{"bolt"}</span>
+                        <span>{global_meta.power_unit.format(delta.power)}</span>
+                    </span>
+                    { for items.into_iter().map(|(item, rate, newly_short)| {
+                        let (title, icon) = match db.get(item) {
+                            Some(item) => (item.name.to_string(), Some(item.image.clone())),
+                            None => ("Unknown Item".to_string(), None),
+                        };
+                        let title = if newly_short {
+                            format!("{} (no longer supplied)", title)
+                        } else {
+                            title
+                        };
+                        html! {
+                            <span class={classes!("delta", global_meta.rate_style(rate),
+                                    newly_short.then(|| "newly-short"))} {title}>
+                                <Icon {icon} />
+                                <span>{format!("{:+.*}", rate_unit.decimals(), rate_unit.scale(rate))}</span>
+                                if newly_short {
+                                    <span class="material-icons">{"warning"}</span>
+                                }
+                            </span>
+                        }
+                    }) }
+                </div>
+                <button class="apply" onclick={apply}>
+                    {format!("Replace in {} buildings", replacement.changed.len())}
+                </button>
+            </div>
+        }
+    }
+}
+
+/// Recipes used by any manufacturer in the tree, in id order.
+fn used_recipes(root: &Node) -> Vec<RecipeId> {
+    let recipes: BTreeSet<RecipeId> = root
+        .iter()
+        .filter_map(|node| match &node.building()?.settings {
+            BuildingSettings::Manufacturer(ms) => ms.recipe,
+            _ => None,
+        })
+        .collect();
+    recipes.into_iter().collect()
+}
+
+/// Recipes other than `from` which make any of the same products.
+fn alternatives(db: &Database, from: RecipeId) -> Vec<RecipeId> {
+    let recipe = match db.get(from) {
+        Some(recipe) => recipe,
+        None => return Vec::new(),
+    };
+    let ids: BTreeSet<RecipeId> = recipe
+        .products
+        .iter()
+        .filter_map(|product| db.get(product.item))
+        .flat_map(|item| item.produced_by.iter().copied())
+        .filter(|&id| id != from)
+        .collect();
+    ids.into_iter().collect()
+}
+
+/// Get the names of the groups containing the node at `path`, followed by the node's own
+/// label.
+fn path_names(root: &Node, path: &[usize], db: &Database) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut node = root;
+    for &idx in path {
+        match node.group().and_then(|group| group.get_child(idx)) {
+            Some(child) => node = child,
+            None => break,
+        }
+        if let Some(group) = node.group() {
+            names.push(if group.name.is_empty() {
+                "Unnamed Group".to_string()
+            } else {
+                group.name.clone()
+            });
+        }
+    }
+    if node.group().is_none() {
+        names.push(node_label(node, db));
+    }
+    names
+}
@@ -0,0 +1,219 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Read-only, print-friendly view of the whole factory.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use satisfactory_accounting::accounting::{Balance, Node, NodeKind};
+use satisfactory_accounting::database::{BuildingId, Database, ItemId};
+use yew::prelude::*;
+
+use crate::app::{GlobalMetadata, PowerUnit, RateUnit};
+use crate::node_display::node_label;
+use crate::root_summary::RootSummary;
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Root node of the factory.
+    pub root: Node,
+    /// Callback to open the browser's print dialog.
+    pub print: Callback<()>,
+    /// Callback to close the report.
+    pub close: Callback<()>,
+}
+
+/// Shows the tree and its balances without any edit controls.
+pub struct Report;
+
+impl Component for Report {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let db = ctx.db();
+        let global_meta = ctx.global_meta();
+        let (power_unit, rate_unit) = (global_meta.power_unit, global_meta.rate_unit);
+        let print = ctx.props().print.reform(|_| ());
+        let close = ctx.props().close.reform(|_| ());
+        html! {
+            <div class="Report">
+                <div class="controls">
+                    <button class="print" onclick={print} title="Print">
+                        <span class="material-icons">{"print"}</span>
+                    </button>
+                    <button class="close" onclick={close} title="Close Report">
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <h1>{"Factory Report"}</h1>
+                <RootSummary root={ctx.props().root.clone()} />
+                {view_node(&db, power_unit, rate_unit, &ctx.props().root)}
+            </div>
+        }
+    }
+}
+
+/// Display a node and, for groups, all of its children.
+fn view_node(db: &Database, power_unit: PowerUnit, rate_unit: RateUnit, node: &Node) -> Html {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            let name = if group.name.is_empty() {
+                "Unnamed Group"
+            } else {
+                group.name.as_str()
+            };
+            html! {
+                <section class="group">
+                    <h2>
+                        {name}
+                        if group.copies != 1 {
+                            {format!(" ×{}", group.copies)}
+                        }
+                    </h2>
+                    {view_balance(db, power_unit, rate_unit, node.balance())}
+                    <div class="children">
+                        { for group.children.iter().map(|child| view_node(db, power_unit, rate_unit, child)) }
+                    </div>
+                </section>
+            }
+        }
+        NodeKind::Building(building) => {
+            let building_name = building
+                .building
+                .and_then(|id| db.get(id))
+                .map_or("No Building", |building| building.name.as_ref());
+            let label = node_label(node, db);
+            html! {
+                <div class="building">
+                    <span class="copies">{format!("{}×", building.copies)}</span>
+                    <span class="name">{building_name}</span>
+                    if label != building_name {
+                        <span class="label">{label}</span>
+                    }
+                    <span class="clock">
+                        {format!("{:.0}%", building.settings.clock_speed() * 100.0)}
+                    </span>
+                    <span class="power">{power_unit.format(node.balance().power)}</span>
+                </div>
+            }
+        }
+    }
+}
+
+/// Display a balance as a table of item rates.
+fn view_balance(
+    db: &Database,
+    power_unit: PowerUnit,
+    rate_unit: RateUnit,
+    balance: &Balance,
+) -> Html {
+    html! {
+        <table class="balance">
+            <tr>
+                <td>{"Power"}</td>
+                <td>{power_unit.format(balance.power)}</td>
+            </tr>
+            { for balance.balances.iter().map(|(&item, &rate)| {
+                let name = db.get(item).map_or("Unknown Item", |item| item.name.as_ref());
+                html! {
+                    <tr>
+                        <td>{name}</td>
+                        <td>{rate_unit.format(rate)}</td>
+                    </tr>
+                }
+            }) }
+        </table>
+    }
+}
+
+/// Number of surpluses and deficits listed in the text summary.
+const SUMMARY_TOP_ITEMS: usize = 5;
+
+/// Build a plain-text summary of the factory, with net power, the largest surpluses and
+/// deficits, and building counts, formatted to read well when pasted into chat or a wiki.
+pub fn text_summary(root: &Node, db: &Database, global_meta: &GlobalMetadata) -> String {
+    let balance = root.balance();
+    let item_name = |item: ItemId| {
+        db.get(item)
+            .map_or("Unknown Item", |item| item.name.as_ref())
+    };
+    let mut surpluses = Vec::new();
+    let mut deficits = Vec::new();
+    for (&item, &rate) in &balance.balances {
+        match global_meta.item_rate_style(item, rate) {
+            "positive" => surpluses.push((item, rate)),
+            "negative" => deficits.push((item, rate)),
+            _ => {}
+        }
+    }
+    surpluses.sort_by(|(_, r1), (_, r2)| r2.total_cmp(r1));
+    deficits.sort_by(|(_, r1), (_, r2)| r1.total_cmp(r2));
+
+    let mut counts = HashMap::new();
+    count_buildings(root, 1, &mut counts);
+    let mut counts: Vec<(&str, u32)> = counts
+        .into_iter()
+        .map(|(building, count)| {
+            let name = db
+                .get(building)
+                .map_or("Unknown Building", |building| building.name.as_ref());
+            (name, count)
+        })
+        .collect();
+    counts.sort_by(|(n1, c1), (n2, c2)| c2.cmp(c1).then(n1.cmp(n2)));
+
+    let mut summary = String::new();
+    // Writing to a String can't fail.
+    let _ = writeln!(summary, "**Factory Summary**");
+    let _ = writeln!(
+        summary,
+        "Net power: {}",
+        global_meta.power_unit.format(balance.power)
+    );
+    for (title, items) in [("Top surpluses", surpluses), ("Top deficits", deficits)] {
+        if items.is_empty() {
+            continue;
+        }
+        let _ = writeln!(summary, "\n**{}**", title);
+        for (item, rate) in items.into_iter().take(SUMMARY_TOP_ITEMS) {
+            let rate = global_meta.rate_unit.format_signed(rate);
+            let _ = writeln!(summary, "- {}: {}", item_name(item), rate);
+        }
+    }
+    if !counts.is_empty() {
+        let total: u32 = counts.iter().map(|(_, count)| count).sum();
+        let _ = writeln!(summary, "\n**Buildings ({})**", total);
+        for (name, count) in counts {
+            let _ = writeln!(summary, "- {}× {}", count, name);
+        }
+    }
+    summary
+}
+
+/// Add the number of each type of building below `node` to `counts`, multiplying by the
+/// copies of the node and all of its ancestors.
+fn count_buildings(node: &Node, multiplier: u32, counts: &mut HashMap<BuildingId, u32>) {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            for child in &group.children {
+                count_buildings(child, multiplier * group.copies, counts);
+            }
+        }
+        NodeKind::Building(building) => {
+            if let Some(id) = building.building {
+                *counts.entry(id).or_default() += multiplier * building.copies;
+            }
+        }
+    }
+}
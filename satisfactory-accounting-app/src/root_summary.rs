@@ -0,0 +1,445 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Headline numbers for the whole factory.
+
+use std::collections::BTreeSet;
+
+use gloo::dialogs::prompt;
+use gloo::timers::callback::Timeout;
+use log::warn;
+use satisfactory_accounting::accounting::{Node, MAX_POWER_SHARDS};
+use satisfactory_accounting::database::{Database, ItemId};
+use yew::prelude::*;
+
+use crate::app::GlobalMetadata;
+use crate::node_display::icon::Icon;
+use crate::CtxHelper;
+
+/// Changes smaller than this are not shown as differences from the baseline.
+const DELTA_EPSILON: f32 = 0.005;
+
+/// How long the root must stay unchanged before its changes are announced to screen
+/// readers, in milliseconds, so tuning a value doesn't announce every step.
+const ANNOUNCE_DELAY: u32 = 1000;
+
+/// Most items named in one part of an announcement.
+const MAX_ANNOUNCED_ITEMS: usize = 3;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Root node of the factory.
+    pub root: Node,
+    /// Callback to replace the global metadata, used to set the power shard budget. If
+    /// not set, the budget can't be edited.
+    #[prop_or_default]
+    pub set_global_metadata: Option<Callback<GlobalMetadata>>,
+    /// Earlier snapshot of the root to show changes against, if any.
+    #[prop_or_default]
+    pub baseline: Option<Node>,
+}
+
+pub enum Msg {
+    /// Announce the changes since the last announcement.
+    Announce,
+}
+
+/// Shows summary metrics computed from the root balance. Stays pinned to the top of the
+/// window while scrolling, collapsing to the headline numbers in the compact layout.
+///
+/// Changes to net power and to which items are in deficit are announced through a live
+/// region once the root stops changing.
+pub struct RootSummary {
+    /// State of the factory as of the last announcement.
+    announced: Headline,
+    /// Text of the latest announcement.
+    announcement: String,
+    /// Timer which announces changes once the root stops changing. Cancelled when
+    /// dropped.
+    pending_announcement: Option<Timeout>,
+}
+
+impl Component for RootSummary {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            announced: Headline::of(&ctx.props().root, &ctx.global_meta()),
+            announcement: String::new(),
+            pending_announcement: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Announce => {
+                self.pending_announcement = None;
+                let global_meta = ctx.global_meta();
+                let current = Headline::of(&ctx.props().root, &global_meta);
+                let announcement =
+                    self.announced
+                        .describe_changes(&current, &global_meta, &ctx.db());
+                self.announced = current;
+                if announcement.is_empty() || announcement == self.announcement {
+                    false
+                } else {
+                    self.announcement = announcement;
+                    true
+                }
+            }
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        // Restarting the timer debounces announcements while values are being tuned.
+        let link = ctx.link().clone();
+        self.pending_announcement = Some(Timeout::new(ANNOUNCE_DELAY, move || {
+            link.send_message(Msg::Announce)
+        }));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let balance = ctx.props().root.balance();
+        let global_meta = ctx.global_meta();
+        let power_unit = global_meta.power_unit;
+        let rate_unit = global_meta.rate_unit;
+        let compact = ctx.compact_layout();
+        let throughput: f32 = balance.balances.values().map(|rate| rate.abs()).sum();
+        let deficits = balance
+            .balances
+            .values()
+            .filter(|&&rate| global_meta.is_deficit(rate))
+            .count();
+        // Items with no producer at all are a missing line rather than a shortfall, so
+        // they are counted separately.
+        let missing = missing_inputs(&ctx.props().root, &global_meta);
+        let short = deficits - missing.len();
+        html! {
+            <div class={classes!("RootSummary", compact.then(|| "compact"))}>
+                <span class={classes!("metric", "net-power", global_meta.is_deficit(balance.power).then(|| "short"))}
+                    title="Net Power">
+                    <span class="material-icons">{"bolt"}</span>
+                    <span class="value">{power_unit.format(balance.power)}</span>
+                </span>
+                <span class={classes!("metric", "deficits", (short > 0).then(|| "short"))}
+                    title="Items which are produced, but consumed faster than they are produced">
+                    <span class="material-icons">{"report_problem"}</span>
+                    <span class="value">{short}</span>
+                    <span class="unit">{"short"}</span>
+                </span>
+                if !missing.is_empty() {
+                    {self.view_missing_inputs(ctx, &missing, compact)}
+                }
+                <span class="metric" title="Sum of the absolute net rates of all items">
+                    <span class="material-icons">{"swap_vert"}</span>
+                    <span class="value">{format_grouped(rate_unit.scale(throughput))}</span>
+                    <span class="unit">{format!("items{}", rate_unit.name())}</span>
+                </span>
+                if !compact {
+                    {self.view_power_shards(ctx)}
+                    {self.view_baseline_deltas(ctx)}
+                }
+                <div class="live-region" role="status" aria-live="polite" aria-atomic="true">
+                    {&self.announcement}
+                </div>
+            </div>
+        }
+    }
+}
+
+impl RootSummary {
+    /// Show the items which are consumed but not produced anywhere in the factory.
+    fn view_missing_inputs(&self, ctx: &Context<Self>, missing: &[ItemId], compact: bool) -> Html {
+        let db = ctx.db();
+        let names: Vec<&str> = missing
+            .iter()
+            .map(|&item| {
+                db.get(item)
+                    .map_or("Unknown Item", |item| item.name.as_ref())
+            })
+            .collect();
+        let title = format!("Not produced anywhere: {}", names.join(", "));
+        html! {
+            <span class="metric missing-inputs short" {title}>
+                <span class="material-icons">{"block"}</span>
+                <span class="value">{missing.len()}</span>
+                <span class="unit">{"never produced"}</span>
+                if !compact {
+                    { for missing.iter().map(|&item| {
+                        let icon = db.get(item).map(|item| item.image.clone());
+                        html! { <Icon {icon} /> }
+                    }) }
+                }
+            </span>
+        }
+    }
+
+    /// Show how net power and item rates have changed since the baseline, largest
+    /// changes first.
+    fn view_baseline_deltas(&self, ctx: &Context<Self>) -> Html {
+        let baseline = match &ctx.props().baseline {
+            Some(baseline) => baseline,
+            None => return html! {},
+        };
+        let db = ctx.db();
+        let global_meta = ctx.global_meta();
+        let power_unit = global_meta.power_unit;
+        let rate_unit = global_meta.rate_unit;
+        let delta = ctx.props().root.balance().clone() - baseline.balance();
+        let mut items: Vec<_> = delta
+            .balances
+            .iter()
+            .filter(|(_, rate)| rate.abs() >= DELTA_EPSILON)
+            .map(|(&item, &rate)| (item, rate))
+            .collect();
+        items.sort_by(|(_, r1), (_, r2)| r2.abs().total_cmp(&r1.abs()));
+        let power_changed = delta.power.abs() >= DELTA_EPSILON;
+        html! {
+            <span class="metric baseline-deltas" title="Changes since the baseline">
+                <span class="material-icons">{"flag"}</span>
+                if !power_changed && items.is_empty() {
+                    <span class="unit">{"No changes"}</span>
+                }
+                if power_changed {
+                    <span class={classes!("delta", delta_style(delta.power))} title="Power">
+                        <span class="material-icons">{"bolt"}</span>
+                        <span>{signed(delta.power, power_unit.format(delta.power))}</span>
+                    </span>
+                }
+                { for items.into_iter().map(|(item, rate)| {
+                    let (title, icon) = match db.get(item) {
+                        Some(item) => (item.name.clone(), Some(item.image.clone())),
+                        None => ("Unknown Item".into(), None),
+                    };
+                    html! {
+                        <span class={classes!("delta", delta_style(rate))} {title}>
+                            <Icon {icon} />
+                            <span>{format!("{:+.*}", rate_unit.decimals(), rate_unit.scale(rate))}</span>
+                        </span>
+                    }
+                }) }
+            </span>
+        }
+    }
+
+    /// Show the number of power shards needed across the factory, compared to the number
+    /// available if that is tracked.
+    fn view_power_shards(&self, ctx: &Context<Self>) -> Html {
+        let global_meta = ctx.global_meta();
+        let needed = ctx.props().root.power_shards();
+        if needed == 0 && global_meta.power_shards_owned.is_none() {
+            return html! {};
+        }
+        let short = matches!(global_meta.power_shards_owned, Some(owned) if needed > owned);
+        let value = match global_meta.power_shards_owned {
+            Some(owned) => format!("{} / {}", needed, owned),
+            None => needed.to_string(),
+        };
+        let set_global_metadata = match &ctx.props().set_global_metadata {
+            Some(set_global_metadata) => set_global_metadata,
+            None => {
+                return html! {
+                    <span class="metric power-shards" title="Power shards needed">
+                        <span class="material-icons">{"diamond"}</span>
+                        <span class="value">{value}</span>
+                    </span>
+                };
+            }
+        };
+        let set_owned = {
+            let set_global_metadata = set_global_metadata.clone();
+            let global_meta = global_meta.clone();
+            Callback::from(move |_: MouseEvent| {
+                let current = global_meta
+                    .power_shards_owned
+                    .map(|owned| owned.to_string());
+                if let Some(owned) = prompt_count(
+                    "Power shards available (leave empty to stop tracking):",
+                    current.as_deref(),
+                ) {
+                    set_global_metadata.emit(GlobalMetadata {
+                        power_shards_owned: owned,
+                        ..global_meta.clone()
+                    });
+                }
+            })
+        };
+        let set_slots = {
+            let set_global_metadata = set_global_metadata.clone();
+            let global_meta = global_meta.clone();
+            Callback::from(move |_: MouseEvent| {
+                let current = global_meta.power_shard_slots.map(|slots| slots.to_string());
+                let message = format!(
+                    "Power shard slots unlocked per building, 0 to {} (leave empty for all):",
+                    MAX_POWER_SHARDS
+                );
+                if let Some(slots) = prompt_count(&message, current.as_deref()) {
+                    set_global_metadata.emit(GlobalMetadata {
+                        power_shard_slots: slots.map(|slots| slots.min(MAX_POWER_SHARDS)),
+                        ..global_meta.clone()
+                    });
+                }
+            })
+        };
+        html! {
+            <span class={classes!("metric", "power-shards", short.then(|| "short"))}>
+                <button class="set-shards-owned" onclick={set_owned}
+                    title="Power shards needed / available">
+                    <span class="material-icons">{"diamond"}</span>
+                    <span class="value">{value}</span>
+                </button>
+                <button class="set-shard-slots" onclick={set_slots}
+                    title="Unlocked Power Shard Slots">
+                    <span class="material-icons">{"tune"}</span>
+                </button>
+            </span>
+        }
+    }
+}
+
+/// Prefix a formatted positive value with a plus sign, so it reads as a change.
+fn signed(value: f32, formatted: String) -> String {
+    if value > 0.0 {
+        format!("+{}", formatted)
+    } else {
+        formatted
+    }
+}
+
+/// Get the style class for a change from the baseline.
+fn delta_style(delta: f32) -> &'static str {
+    if delta < 0.0 {
+        "negative"
+    } else {
+        "positive"
+    }
+}
+
+/// Ask for a whole number, which may be left empty. Returns None if the prompt was
+/// cancelled or the entry was invalid, or Some(None) if it was left empty.
+fn prompt_count(message: &str, current: Option<&str>) -> Option<Option<u32>> {
+    let text = prompt(message, current)?;
+    if text.trim().is_empty() {
+        return Some(None);
+    }
+    match text.trim().parse() {
+        Ok(count) => Some(Some(count)),
+        Err(_) => {
+            warn!("Invalid count {:?}", text);
+            None
+        }
+    }
+}
+
+/// Format a number with one decimal place and thousands separators.
+fn format_grouped(val: f32) -> String {
+    let formatted = format!("{:.1}", val.abs());
+    let (int_part, frac_part) = formatted
+        .split_once('.')
+        .unwrap_or((formatted.as_str(), "0"));
+    let mut grouped = String::new();
+    for (i, digit) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let sign = if val < 0.0 && formatted != "0.0" {
+        "-"
+    } else {
+        ""
+    };
+    format!("{}{}.{}", sign, grouped, frac_part)
+}
+
+/// Headline state of the factory which changes are announced against.
+#[derive(Debug, Clone, PartialEq)]
+struct Headline {
+    /// Net power of the factory.
+    power: f32,
+    /// Items whose net rate is a deficit.
+    deficits: BTreeSet<ItemId>,
+}
+
+impl Headline {
+    /// Get the headline state of the factory.
+    fn of(root: &Node, global_meta: &GlobalMetadata) -> Self {
+        let balance = root.balance();
+        Self {
+            power: balance.power,
+            deficits: balance
+                .balances
+                .iter()
+                .filter(|(_, &rate)| global_meta.is_deficit(rate))
+                .map(|(&item, _)| item)
+                .collect(),
+        }
+    }
+
+    /// Describe how the factory changed from this state to `current` in a few short
+    /// sentences, or an empty string if nothing worth announcing changed.
+    fn describe_changes(
+        &self,
+        current: &Self,
+        global_meta: &GlobalMetadata,
+        db: &Database,
+    ) -> String {
+        let mut sentences = Vec::new();
+        if (current.power - self.power).abs() >= DELTA_EPSILON {
+            let power = global_meta.power_unit.format(current.power.abs());
+            sentences.push(match global_meta.rate_style(current.power) {
+                "negative" => format!("Power deficit now {}.", power),
+                "positive" => format!("Power surplus now {}.", power),
+                _ => "Power now balanced.".to_string(),
+            });
+        }
+        let new_deficits = item_names(db, current.deficits.difference(&self.deficits));
+        let resolved = item_names(db, self.deficits.difference(&current.deficits));
+        sentences.extend(describe_items(&new_deficits, "now in deficit"));
+        sentences.extend(describe_items(&resolved, "no longer in deficit"));
+        sentences.join(" ")
+    }
+}
+
+/// Get the names of the given items.
+fn item_names<'a>(db: &'a Database, items: impl Iterator<Item = &'a ItemId>) -> Vec<&'a str> {
+    items
+        .map(|&item| {
+            db.get(item)
+                .map_or("Unknown Item", |item| item.name.as_ref())
+        })
+        .collect()
+}
+
+/// Describe a change to several items in one sentence, naming only the first few. Returns
+/// None if there are no items.
+fn describe_items(names: &[&str], change: &str) -> Option<String> {
+    match names.len() {
+        0 => None,
+        n if n > MAX_ANNOUNCED_ITEMS => Some(format!(
+            "{} and {} more {}.",
+            names[..MAX_ANNOUNCED_ITEMS].join(", "),
+            n - MAX_ANNOUNCED_ITEMS,
+            change
+        )),
+        _ => Some(format!("{} {}.", names.join(", "), change)),
+    }
+}
+
+/// Get the items which are consumed somewhere in the tree but not produced anywhere in it,
+/// as opposed to items which are produced, just not enough.
+fn missing_inputs(root: &Node, global_meta: &GlobalMetadata) -> Vec<ItemId> {
+    let gross = root.gross();
+    root.balance()
+        .balances
+        .iter()
+        .filter(|&(&item, &rate)| global_meta.is_deficit(rate) && gross.produced(item) <= 0.0)
+        .map(|(&item, _)| item)
+        .collect()
+}
@@ -0,0 +1,275 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Collapsible, resizable panels docked beside the tree.
+
+use std::collections::BTreeSet;
+
+use gloo::events::EventListener;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+use yew::prelude::*;
+
+use crate::CtxHelper;
+
+/// Width of a dock until it is resized, in pixels.
+const DEFAULT_DOCK_WIDTH: f64 = 250.0;
+
+/// Narrowest a dock can be resized to, in pixels.
+const MIN_DOCK_WIDTH: f64 = 150.0;
+
+/// Widest a dock can be resized to, in pixels.
+const MAX_DOCK_WIDTH: f64 = 600.0;
+
+/// Side of the tree a dock of panels is attached to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DockSide {
+    Left,
+    Right,
+}
+
+impl DockSide {
+    /// Get the class used to position the dock.
+    fn class(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Right => "right",
+        }
+    }
+}
+
+/// Analytical views which are shown as side panels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PanelId {
+    Outline,
+    WatchLists,
+}
+
+/// Saved widths of the docks and which panels are collapsed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelLayout {
+    /// Width of the left dock in pixels.
+    left_width: f64,
+    /// Width of the right dock in pixels.
+    right_width: f64,
+    /// Panels which are collapsed to just their header.
+    collapsed: BTreeSet<PanelId>,
+}
+
+impl Default for PanelLayout {
+    fn default() -> Self {
+        Self {
+            left_width: DEFAULT_DOCK_WIDTH,
+            right_width: DEFAULT_DOCK_WIDTH,
+            collapsed: BTreeSet::new(),
+        }
+    }
+}
+
+impl PanelLayout {
+    /// Get the width of the dock on the given side.
+    fn width(&self, side: DockSide) -> f64 {
+        match side {
+            DockSide::Left => self.left_width,
+            DockSide::Right => self.right_width,
+        }
+    }
+
+    /// Build a copy of this layout with the dock on the given side resized.
+    fn with_width(&self, side: DockSide, width: f64) -> Self {
+        let mut updated = self.clone();
+        match side {
+            DockSide::Left => updated.left_width = width,
+            DockSide::Right => updated.right_width = width,
+        }
+        updated
+    }
+
+    /// Build a copy of this layout with the panel collapsed or expanded.
+    fn with_collapse_toggled(&self, id: PanelId) -> Self {
+        let mut updated = self.clone();
+        if !updated.collapsed.remove(&id) {
+            updated.collapsed.insert(id);
+        }
+        updated
+    }
+}
+
+/// A panel shown in a dock.
+#[derive(PartialEq)]
+pub struct Panel {
+    /// Which view this panel shows, used to save whether it is collapsed.
+    pub id: PanelId,
+    /// Title shown in the panel header.
+    pub title: &'static str,
+    /// Material icon shown next to the title.
+    pub icon: &'static str,
+    /// Contents of the panel.
+    pub content: Html,
+    /// Callback to close the panel, if it can be closed.
+    pub close: Option<Callback<()>>,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Side of the tree the dock is on.
+    pub side: DockSide,
+    /// Panels in the dock, top to bottom. The dock isn't shown if there are none.
+    pub panels: Vec<Panel>,
+    /// Saved layout of the docks.
+    pub layout: PanelLayout,
+    /// Callback to save an updated layout.
+    pub set_layout: Callback<PanelLayout>,
+}
+
+pub enum Msg {
+    /// Start resizing the dock by dragging its edge.
+    StartResize,
+    /// Show the dock at the given width while its edge is dragged.
+    Resize { width: f64 },
+    /// Stop dragging the edge and save the width.
+    FinishResize,
+}
+
+/// Column of panels beside the tree. Each panel can be collapsed to its header, and the
+/// column can be resized by dragging its inner edge. In the compact layout the dock is
+/// shown full width above or below the tree instead, and can't be resized.
+pub struct Dock {
+    /// The dock element, used to measure it while resizing.
+    dock: NodeRef,
+    /// Width shown while the edge is being dragged, before it is saved.
+    resizing: Option<f64>,
+    /// Listeners for pointer movement while the edge is being dragged.
+    _drag_listeners: Option<[EventListener; 2]>,
+}
+
+impl Component for Dock {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            dock: NodeRef::default(),
+            resizing: None,
+            _drag_listeners: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::StartResize => {
+                self._drag_listeners = self.listen_for_drag(ctx);
+                false
+            }
+            Msg::Resize { width } => {
+                self.resizing = Some(width);
+                true
+            }
+            Msg::FinishResize => {
+                self._drag_listeners = None;
+                if let Some(width) = self.resizing.take() {
+                    let props = ctx.props();
+                    props
+                        .set_layout
+                        .emit(props.layout.with_width(props.side, width));
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        if props.panels.is_empty() {
+            return html! {};
+        }
+        let compact = ctx.compact_layout();
+        let style = (!compact).then(|| {
+            let width = self
+                .resizing
+                .unwrap_or_else(|| props.layout.width(props.side));
+            format!("width: {}px", width)
+        });
+        let start_resize = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::StartResize
+        });
+        html! {
+            <aside class={classes!("Dock", props.side.class(),
+                    self.resizing.is_some().then(|| "resizing"))}
+                {style} ref={self.dock.clone()}>
+                { for props.panels.iter().map(|panel| self.view_panel(ctx, panel)) }
+                if !compact {
+                    <div class="resize-handle" title="Drag to Resize"
+                        onmousedown={start_resize} />
+                }
+            </aside>
+        }
+    }
+}
+
+impl Dock {
+    /// Display a single panel with its header.
+    fn view_panel(&self, ctx: &Context<Self>, panel: &Panel) -> Html {
+        let layout = &ctx.props().layout;
+        let collapsed = layout.collapsed.contains(&panel.id);
+        let toggle = {
+            let set_layout = ctx.props().set_layout.clone();
+            let updated = layout.with_collapse_toggled(panel.id);
+            Callback::from(move |_| set_layout.emit(updated.clone()))
+        };
+        html! {
+            <section class={classes!("panel", collapsed.then(|| "collapsed"))}>
+                <div class="panel-header">
+                    <span class="material-icons">{panel.icon}</span>
+                    <span class="title">{panel.title}</span>
+                    <button class="collapse" onclick={toggle}
+                        title={if collapsed { "Expand" } else { "Collapse" }}>
+                        <span class="material-icons">
+                            {if collapsed { "expand_more" } else { "expand_less" }}
+                        </span>
+                    </button>
+                    if let Some(close) = &panel.close {
+                        <button class="close" title="Close" onclick={close.reform(|_| ())}>
+                            <span class="material-icons">{"close"}</span>
+                        </button>
+                    }
+                </div>
+                if !collapsed {
+                    <div class="panel-body">{panel.content.clone()}</div>
+                }
+            </section>
+        }
+    }
+
+    /// Listen for the pointer moving and being released anywhere in the window while the
+    /// edge of the dock is dragged.
+    fn listen_for_drag(&self, ctx: &Context<Self>) -> Option<[EventListener; 2]> {
+        let window = web_sys::window()?;
+        let side = ctx.props().side;
+        let dock = self.dock.clone();
+        let resize = ctx.link().callback(|width| Msg::Resize { width });
+        let on_move = EventListener::new(&window, "mousemove", move |e| {
+            let (e, dock) = match (e.dyn_ref::<MouseEvent>(), dock.cast::<Element>()) {
+                (Some(e), Some(dock)) => (e, dock),
+                _ => return,
+            };
+            let rect = dock.get_bounding_client_rect();
+            let x = e.client_x() as f64;
+            let width = match side {
+                DockSide::Left => x - rect.left(),
+                DockSide::Right => rect.right() - x,
+            };
+            resize.emit(width.clamp(MIN_DOCK_WIDTH, MAX_DOCK_WIDTH));
+        });
+        let finish = ctx.link().callback(|()| Msg::FinishResize);
+        let on_up = EventListener::new(&window, "mouseup", move |_| finish.emit(()));
+        Some([on_move, on_up])
+    }
+}
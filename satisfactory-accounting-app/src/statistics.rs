@@ -0,0 +1,186 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Read-only statistics about the structure of the tree.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use satisfactory_accounting::accounting::{BuildingSettings, Node, NodeKind};
+use satisfactory_accounting::database::{Database, RecipeId};
+use yew::prelude::*;
+
+use crate::clipboard;
+use crate::modal::Modal;
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Root node of the factory.
+    pub root: Node,
+    /// Callback to close the statistics.
+    pub close: Callback<()>,
+}
+
+/// Shows counts of nodes and buildings, the depth of the tree, and how often each recipe
+/// is used.
+pub struct Statistics;
+
+impl Component for Statistics {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let db = ctx.db();
+        let stats = TreeStats::collect(&ctx.props().root);
+        let recipes = stats.recipe_histogram(&db);
+        let most_used = recipes.first().map_or(1, |(_, count)| *count).max(1);
+        let copy = {
+            let text = stats.to_text(&recipes);
+            Callback::from(move |_| clipboard::copy_text(&text))
+        };
+        let close = ctx.props().close.reform(|_| ());
+        html! {
+            <Modal class="Statistics" label="Statistics" close={ctx.props().close.clone()}>
+                <div class="header">
+                    <span class="title">{"Statistics"}</span>
+                    <button class="copy" title="Copy Statistics to Clipboard" onclick={copy}>
+                        <span class="material-icons">{"content_copy"}</span>
+                    </button>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <table class="totals">
+                    <tr>
+                        <th>{"Nodes"}</th>
+                        <td>{stats.groups + stats.buildings}</td>
+                    </tr>
+                    <tr>
+                        <th>{"Groups"}</th>
+                        <td>{stats.groups}</td>
+                    </tr>
+                    <tr>
+                        <th>{"Building nodes"}</th>
+                        <td>{stats.buildings}</td>
+                    </tr>
+                    <tr title="Counting the copies of buildings and the groups containing them">
+                        <th>{"Buildings"}</th>
+                        <td>{stats.building_count}</td>
+                    </tr>
+                    <tr title="Number of levels of groups below the root">
+                        <th>{"Max depth"}</th>
+                        <td>{stats.max_depth}</td>
+                    </tr>
+                </table>
+                if recipes.is_empty() {
+                    <div class="empty">{"No recipes in use"}</div>
+                } else {
+                    <table class="recipes">
+                        { for recipes.iter().map(|(name, count)| html! {
+                            <tr>
+                                <th>{name}</th>
+                                <td class="count">{count}</td>
+                                <td class="bar-cell">
+                                    <div class="bar"
+                                        style={format!("width: {:.1}%",
+                                            *count as f32 / most_used as f32 * 100.0)} />
+                                </td>
+                            </tr>
+                        }) }
+                    </table>
+                }
+            </Modal>
+        }
+    }
+}
+
+/// Metrics collected from one walk of the tree.
+#[derive(Debug, Default)]
+struct TreeStats {
+    /// Number of group nodes, including the root.
+    groups: usize,
+    /// Number of building nodes.
+    buildings: usize,
+    /// Number of buildings, including copies of buildings and of their groups.
+    building_count: u32,
+    /// Deepest level of groups below the root.
+    max_depth: usize,
+    /// Number of buildings using each recipe, including copies.
+    recipes: HashMap<RecipeId, u32>,
+}
+
+impl TreeStats {
+    /// Walk the tree collecting statistics.
+    fn collect(root: &Node) -> Self {
+        let mut stats = Self::default();
+        stats.visit(root, 0, 1);
+        stats
+    }
+
+    /// Add the statistics of a node at the given depth, whose ancestors multiply it by
+    /// `multiplier` copies.
+    fn visit(&mut self, node: &Node, depth: usize, multiplier: u32) {
+        match node.kind() {
+            NodeKind::Group(group) => {
+                self.groups += 1;
+                self.max_depth = self.max_depth.max(depth);
+                for child in &group.children {
+                    self.visit(child, depth + 1, multiplier * group.copies);
+                }
+            }
+            NodeKind::Building(building) => {
+                self.buildings += 1;
+                let count = multiplier * building.copies;
+                self.building_count += count;
+                if let BuildingSettings::Manufacturer(ms) = &building.settings {
+                    if let Some(recipe) = ms.recipe {
+                        *self.recipes.entry(recipe).or_default() += count;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get the name and number of uses of each recipe, most used first.
+    fn recipe_histogram(&self, db: &Database) -> Vec<(String, u32)> {
+        let mut recipes: Vec<(String, u32)> = self
+            .recipes
+            .iter()
+            .map(|(&recipe, &count)| {
+                let name = db
+                    .get(recipe)
+                    .map_or("Unknown Recipe", |recipe| recipe.name.as_ref());
+                (name.to_string(), count)
+            })
+            .collect();
+        recipes.sort_by(|(n1, c1), (n2, c2)| c2.cmp(c1).then(n1.cmp(n2)));
+        recipes
+    }
+
+    /// Format the statistics as plain text.
+    fn to_text(&self, recipes: &[(String, u32)]) -> String {
+        let mut text = String::new();
+        // Writing to a String can't fail.
+        let _ = writeln!(text, "Nodes: {}", self.groups + self.buildings);
+        let _ = writeln!(text, "Groups: {}", self.groups);
+        let _ = writeln!(text, "Building nodes: {}", self.buildings);
+        let _ = writeln!(text, "Buildings: {}", self.building_count);
+        let _ = writeln!(text, "Max depth: {}", self.max_depth);
+        if !recipes.is_empty() {
+            let _ = writeln!(text, "\nRecipes:");
+            for (name, count) in recipes {
+                let _ = writeln!(text, "- {}: {}", name, count);
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Tabs for the separate top-level factories of a document.
+
+use satisfactory_accounting::accounting::Node;
+use yew::prelude::*;
+
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Name and root of each tab, in order.
+    pub tabs: Vec<(String, Node)>,
+    /// Index of the tab being edited.
+    pub active: usize,
+    /// Root combining every tab, whose balance is the grand total.
+    pub combined: Node,
+    /// Whether the summary shows the grand total rather than the active tab.
+    pub show_combined: bool,
+    /// Callback to switch to the tab at the given index.
+    pub select: Callback<usize>,
+    /// Callback to add a new, empty tab.
+    pub add: Callback<()>,
+    /// Callback to rename the tab at the given index.
+    pub rename: Callback<usize>,
+    /// Callback to move a tab from the first index to the second.
+    pub move_tab: Callback<(usize, usize)>,
+    /// Callback to close the tab at the given index.
+    pub close: Callback<usize>,
+    /// Callback to switch the summary between the active tab and the grand total.
+    pub toggle_combined: Callback<()>,
+}
+
+/// Bar of tabs above the tree, each showing the net power of its factory. The active tab
+/// can be renamed, moved, or closed, and the last entry shows the grand total across all
+/// tabs, which can be shown in the summary instead of the active tab.
+pub struct TabBar;
+
+impl Component for TabBar {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let global_meta = ctx.global_meta();
+        let add = props.add.reform(|_| ());
+        let toggle_combined = props.toggle_combined.reform(|_| ());
+        let total_power = props.combined.balance().power;
+        html! {
+            <nav class="TabBar" aria-label="Factories">
+                <ul role="tablist">
+                    { for props.tabs.iter().enumerate().map(|(idx, (name, root))| {
+                        self.view_tab(ctx, idx, name, root)
+                    }) }
+                </ul>
+                <button class="add-tab" title="Add Factory Tab" onclick={add}>
+                    <span class="material-icons">{"add"}</span>
+                </button>
+                if props.tabs.len() > 1 {
+                    <button class={classes!("grand-total", props.show_combined.then(|| "active"))}
+                        title="Show the Total of All Tabs in the Summary"
+                        aria-pressed={props.show_combined.to_string()}
+                        onclick={toggle_combined}>
+                        <span class="material-icons">{"functions"}</span>
+                        <span class={classes!("power", global_meta.rate_style(total_power))}>
+                            {global_meta.power_unit.format(total_power)}
+                        </span>
+                    </button>
+                }
+            </nav>
+        }
+    }
+}
+
+impl TabBar {
+    /// Display a single tab, with controls if it is the active one.
+    fn view_tab(&self, ctx: &Context<Self>, idx: usize, name: &str, root: &Node) -> Html {
+        let props = ctx.props();
+        let global_meta = ctx.global_meta();
+        let active = idx == props.active;
+        let power = root.balance().power;
+        let select = props.select.reform(move |_| idx);
+        let label = if name.is_empty() { "Unnamed" } else { name };
+        html! {
+            <li class={classes!("tab", active.then(|| "active"))} role="tab"
+                aria-selected={active.to_string()}>
+                <button class="select-tab" onclick={select}>
+                    <span class="name">{label}</span>
+                    <span class={classes!("power", global_meta.rate_style(power))}>
+                        {global_meta.power_unit.format(power)}
+                    </span>
+                </button>
+                if active {
+                    <button class="rename-tab" title="Rename Tab"
+                        onclick={props.rename.reform(move |_| idx)}>
+                        <span class="material-icons">{"edit"}</span>
+                    </button>
+                    if idx > 0 {
+                        <button class="move-tab" title="Move Tab Left"
+                            onclick={props.move_tab.reform(move |_| (idx, idx - 1))}>
+                            <span class="material-icons">{"chevron_left"}</span>
+                        </button>
+                    }
+                    if idx + 1 < props.tabs.len() {
+                        <button class="move-tab" title="Move Tab Right"
+                            onclick={props.move_tab.reform(move |_| (idx, idx + 1))}>
+                            <span class="material-icons">{"chevron_right"}</span>
+                        </button>
+                    }
+                    if props.tabs.len() > 1 {
+                        <button class="close-tab" title="Close Tab"
+                            onclick={props.close.reform(move |_| idx)}>
+                            <span class="material-icons">{"close"}</span>
+                        </button>
+                    }
+                }
+            </li>
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Floating widget showing the summed net rates of user-defined sets of items.
+
+use satisfactory_accounting::accounting::Node;
+use satisfactory_accounting::database::ItemId;
+use yew::prelude::*;
+
+use crate::app::{GlobalMetadata, WatchList};
+use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
+use crate::node_display::icon::Icon;
+use crate::CtxHelper;
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Root node of the factory, whose balance is summed.
+    pub root: Node,
+    /// Callback to replace the global metadata, which holds the watch lists.
+    pub set_global_metadata: Callback<GlobalMetadata>,
+}
+
+/// Messages for [`WatchLists`].
+pub enum Msg {
+    /// Start or stop choosing an item to add to a list.
+    ChooseItem {
+        /// Index of the list to add to, or None to stop choosing.
+        list: Option<usize>,
+    },
+    /// Add an item to a list.
+    AddItem { list: usize, id: ItemId },
+    /// Remove an item from a list.
+    RemoveItem { list: usize, id: ItemId },
+    /// Delete a whole list.
+    DeleteList { list: usize },
+}
+
+/// Shows the summed net rate of each watch list.
+#[derive(Default)]
+pub struct WatchLists {
+    /// Index of the list an item is being chosen for, if any.
+    choosing: Option<usize>,
+}
+
+impl Component for WatchLists {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Default::default()
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let update_lists = |f: &dyn Fn(&mut Vec<WatchList>)| {
+            let mut global_meta = ctx.global_meta();
+            f(&mut global_meta.watch_lists);
+            ctx.props().set_global_metadata.emit(global_meta);
+        };
+        match msg {
+            Msg::ChooseItem { list } => {
+                self.choosing = list;
+                true
+            }
+            Msg::AddItem { list, id } => {
+                self.choosing = None;
+                update_lists(&|lists| {
+                    if let Some(list) = lists.get_mut(list) {
+                        list.items.insert(id);
+                    }
+                });
+                true
+            }
+            Msg::RemoveItem { list, id } => {
+                update_lists(&|lists| {
+                    if let Some(list) = lists.get_mut(list) {
+                        list.items.remove(&id);
+                    }
+                });
+                false
+            }
+            Msg::DeleteList { list } => {
+                self.choosing = None;
+                update_lists(&|lists| {
+                    if list < lists.len() {
+                        lists.remove(list);
+                    }
+                });
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let global_meta = ctx.global_meta();
+        html! {
+            <div class="WatchLists">
+                { for global_meta.watch_lists.iter().enumerate().map(|(idx, list)| {
+                    self.view_list(ctx, idx, list)
+                }) }
+            </div>
+        }
+    }
+}
+
+impl WatchLists {
+    /// Display a single watch list and its summed net rate.
+    fn view_list(&self, ctx: &Context<Self>, idx: usize, list: &WatchList) -> Html {
+        let db = ctx.db();
+        let link = ctx.link();
+        let balances = &ctx.props().root.balance().balances;
+        let total: f32 = list
+            .items
+            .iter()
+            .filter_map(|item| balances.get(item))
+            .sum();
+        let delete = link.callback(move |_| Msg::DeleteList { list: idx });
+        html! {
+            <div class="watch-list">
+                <span class="name">{&list.name}</span>
+                <span class={classes!("total", ctx.global_meta().rate_style(total))}>
+                    {ctx.global_meta().rate_unit.format_value(total)}
+                </span>
+                { for list.items.iter().map(|&id| {
+                    let (title, icon) = match db.get(id) {
+                        Some(item) => (item.name.clone(), Some(item.image.clone())),
+                        None => ("Unknown Item".into(), None),
+                    };
+                    let onclick = link.callback(move |_| Msg::RemoveItem { list: idx, id });
+                    html! {
+                        <span class="item" {title} {onclick}>
+                            <Icon {icon} />
+                        </span>
+                    }
+                }) }
+                if self.choosing == Some(idx) {
+                    {self.view_chooser(ctx, idx, list)}
+                } else {
+                    <button class="add-item" title="Add Item"
+                        onclick={link.callback(move |_| Msg::ChooseItem { list: Some(idx) })}>
+                        <span class="material-icons">{"add"}</span>
+                    </button>
+                }
+                <button class="delete" title="Delete Watch List" onclick={delete}>
+                    <span class="material-icons">{"delete"}</span>
+                </button>
+            </div>
+        }
+    }
+
+    /// Display the chooser for adding an item to a list.
+    fn view_chooser(&self, ctx: &Context<Self>, idx: usize, list: &WatchList) -> Html {
+        let db = ctx.db();
+        let link = ctx.link();
+        let choices: Vec<_> = db
+            .items
+            .values()
+            .filter(|item| !list.items.contains(&item.id))
+            .map(|item| Choice {
+                id: item.id,
+                name: item.name.clone(),
+                image: html! {
+                    <Icon icon={item.image.clone()}/>
+                },
+                detail: None,
+            })
+            .collect();
+        let selected = link.callback(move |id| Msg::AddItem { list: idx, id });
+        let cancelled = link.callback(|()| Msg::ChooseItem { list: None });
+        html! {
+            <ChooseFromList<ItemId> {choices} {selected} {cancelled} />
+        }
+    }
+}
@@ -5,19 +5,27 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
-use std::{fmt, iter::FusedIterator, rc::Rc};
+use std::{collections::BTreeSet, fmt, iter::FusedIterator, rc::Rc};
 
 use serde::{Deserialize, Deserializer, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
-pub use self::balance::Balance;
+pub use self::balance::{Balance, GrossBalance};
+pub use self::cache::BalanceCache;
 use crate::database::{
     BuildingId, BuildingKind, BuildingKindId, Database, Generator, Geothermal, ItemId,
     Manufacturer, Miner, Pump, RecipeId, Station,
 };
 
 mod balance;
+mod cache;
+
+/// Maximum number of power shards which can be slotted into one building.
+pub const MAX_POWER_SHARDS: u32 = 3;
+
+/// Clock speed increase each power shard allows beyond 100%.
+const POWER_SHARD_STEP: f32 = 0.5;
 
 /// Trait for types which can visit groups when creating copies.
 pub trait GroupCopyVisitor {
@@ -87,9 +95,10 @@ impl<'de> Deserialize<'de> for Node {
     where
         D: Deserializer<'de>,
     {
-        // Recompute children_had_warnings on deserialization.
+        // Recompute children_had_warnings and the gross balance on deserialization.
         let mut node_inner = NodeInner::deserialize(deserializer)?;
         node_inner.children_had_warnings = check_for_child_warnings(&node_inner.kind);
+        node_inner.gross = compute_gross(&node_inner.kind, &node_inner.balance);
         Ok(Node(Rc::new(node_inner)))
     }
 }
@@ -106,14 +115,32 @@ fn check_for_child_warnings(kind: &NodeKind) -> bool {
     }
 }
 
+/// Computes the gross balance of a node. Buildings split their net balance, while groups
+/// sum the *cached* gross balances of their children, multiplied by the group's copies.
+fn compute_gross(kind: &NodeKind, balance: &Balance) -> GrossBalance {
+    match kind {
+        NodeKind::Group(group) => {
+            let mut gross = GrossBalance::default();
+            for child in &group.children {
+                gross += child.gross();
+            }
+            gross *= group.copies as f32;
+            gross
+        }
+        NodeKind::Building(_) => GrossBalance::from_net(balance),
+    }
+}
+
 impl Node {
     /// Create a new tree node.
     fn new(kind: impl Into<NodeKind>, balance: Balance) -> Node {
         let kind = kind.into();
         let children_had_warnings = check_for_child_warnings(&kind);
+        let gross = compute_gross(&kind, &balance);
         Self(Rc::new(NodeInner {
             kind,
             balance,
+            gross,
             warning: None,
             children_had_warnings,
         }))
@@ -126,6 +153,7 @@ impl Node {
         Self(Rc::new(NodeInner {
             kind,
             balance: Balance::empty(),
+            gross: GrossBalance::default(),
             warning: Some(warning),
             children_had_warnings,
         }))
@@ -136,11 +164,21 @@ impl Node {
         &self.0.kind
     }
 
-    /// Get the balance of this node.
+    /// Get the balance of this node. For buildings this accounts for clock speed and
+    /// copies; for groups it is the sum of the children's balances multiplied by the
+    /// group's copies. Balances are computed when the node is built, so this is cheap and
+    /// doesn't require a [`Database`].
     pub fn balance(&self) -> &Balance {
         &self.0.balance
     }
 
+    /// Get the gross production and consumption of each item in this node, before
+    /// production and consumption of the same item by different buildings cancel out.
+    /// Like the net balance, this is computed when the node is built.
+    pub fn gross(&self) -> &GrossBalance {
+        &self.0.gross
+    }
+
     /// Get the warning for this error.
     pub fn warning(&self) -> Option<BuildError> {
         self.0.warning
@@ -157,11 +195,46 @@ impl Node {
         self.kind().group()
     }
 
+    /// Get the primary product of a group: the designated item if one is set, otherwise
+    /// the item with the largest positive net rate. None for buildings and for groups
+    /// which produce nothing.
+    pub fn primary_product(&self) -> Option<ItemId> {
+        let group = self.group()?;
+        group.primary_product.or_else(|| {
+            self.balance()
+                .balances
+                .iter()
+                .filter(|(_, &rate)| rate > 0.0)
+                .max_by(|(_, r1), (_, r2)| r1.total_cmp(r2))
+                .map(|(&item, _)| item)
+        })
+    }
+
     /// Get the Building if this is a Building, otherwise None.
     pub fn building(&self) -> Option<&Building> {
         self.kind().building()
     }
 
+    /// Get the total number of power shards needed to overclock every building in this
+    /// node, accounting for the copies of buildings and groups.
+    pub fn power_shards(&self) -> u32 {
+        match self.kind() {
+            NodeKind::Group(group) => {
+                let children: u32 = group.children.iter().map(Node::power_shards).sum();
+                children * group.copies
+            }
+            NodeKind::Building(building) => building.power_shards(),
+        }
+    }
+
+    /// Get the color tag of this node, if any.
+    pub fn tag(&self) -> Option<ColorTag> {
+        match self.kind() {
+            NodeKind::Group(group) => group.tag,
+            NodeKind::Building(building) => building.tag,
+        }
+    }
+
     /// Create a copy of this node. This is a true copy, with Uuids of Groups changed to
     /// represent newly created, but identical groups.
     pub fn create_copy(&self) -> Self {
@@ -237,6 +310,10 @@ struct NodeInner {
     /// Net balance of this node.
     balance: Balance,
 
+    /// Gross production and consumption of this node.
+    #[serde(skip)]
+    gross: GrossBalance,
+
     /// Warnings generated when building this node.
     warning: Option<BuildError>,
 
@@ -288,6 +365,42 @@ fn default_copies() -> u32 {
     1
 }
 
+/// Color tag which can be assigned to nodes to visually group related parts of a
+/// factory. Tags are purely organizational and don't affect the balance.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ColorTag {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ColorTag {
+    /// All color tags, in display order.
+    pub const ALL: [ColorTag; 6] = [
+        ColorTag::Red,
+        ColorTag::Orange,
+        ColorTag::Yellow,
+        ColorTag::Green,
+        ColorTag::Blue,
+        ColorTag::Purple,
+    ];
+
+    /// Get a string suitable for human display of this tag.
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorTag::Red => "Red",
+            ColorTag::Orange => "Orange",
+            ColorTag::Yellow => "Yellow",
+            ColorTag::Green => "Green",
+            ColorTag::Blue => "Blue",
+            ColorTag::Purple => "Purple",
+        }
+    }
+}
+
 /// A grouping of other nodes. It's balance is based on its child nodes.
 ///
 /// Note that cloning groups is used to update groups. When creating a new a copy of a
@@ -303,6 +416,18 @@ pub struct Group {
     /// Number of virtual copies of this group. This acts as a multiplier on the balance.
     #[serde(default = "default_copies")]
     pub copies: u32,
+    /// Item this group is designated as mainly producing. If not set, the primary product
+    /// is inferred from the balance; see [`Node::primary_product`].
+    #[serde(default)]
+    pub primary_product: Option<ItemId>,
+    /// Color tag of this group, if any.
+    #[serde(default)]
+    pub tag: Option<ColorTag>,
+    /// Items assumed to be supplied from outside this group in whatever quantity it
+    /// needs. Deficits of these items are left out of the group's balance, so they don't
+    /// show up as shortfalls here or in any containing group.
+    #[serde(default)]
+    pub assumed_supplied: BTreeSet<ItemId>,
 
     /// Uniquely identifies a group, even when the node is shared between trees (e.g. when
     /// saving nodes for undo/redo purposes).
@@ -316,6 +441,9 @@ impl Group {
             name: Default::default(),
             children: Default::default(),
             copies: 1,
+            primary_product: None,
+            tag: None,
+            assumed_supplied: BTreeSet::new(),
             id: Uuid::new_v4(),
         }
     }
@@ -330,9 +458,20 @@ impl Group {
     fn compute_balance(&self) -> Balance {
         let mut balance = self.children.iter().map(|node| node.balance()).sum();
         balance *= self.copies as f32;
+        self.apply_assumed_supply(&mut balance);
         balance
     }
 
+    /// Remove deficits of the items this group assumes are supplied externally from a
+    /// balance. Surpluses of those items are kept.
+    pub fn apply_assumed_supply(&self, balance: &mut Balance) {
+        for item in &self.assumed_supplied {
+            if let Some(rate) = balance.balances.get_mut(item) {
+                *rate = rate.max(0.0);
+            }
+        }
+    }
+
     /// Get a child of this node by index.
     pub fn get_child(&self, index: usize) -> Option<&Node> {
         self.children.get(index)
@@ -350,6 +489,9 @@ impl Group {
                 .map(|child| child.create_copy())
                 .collect(),
             copies: self.copies,
+            primary_product: self.primary_product,
+            tag: self.tag,
+            assumed_supplied: self.assumed_supplied.clone(),
             id: Uuid::new_v4(),
         }
     }
@@ -368,6 +510,9 @@ impl Group {
                 .map(|child| child.create_copy_with_visitor(visitor))
                 .collect(),
             copies: self.copies,
+            primary_product: self.primary_product,
+            tag: self.tag,
+            assumed_supplied: self.assumed_supplied.clone(),
             id: Uuid::new_v4(),
         };
         visitor.visit(self, &mut copy);
@@ -407,6 +552,18 @@ pub struct Building {
     /// Number of copies of this building.
     #[serde(default = "default_copies")]
     pub copies: u32,
+    /// Whether bulk operations on containing groups, such as balancing generator clocks,
+    /// should leave this building unchanged. Doesn't affect the balance.
+    #[serde(default)]
+    pub locked: bool,
+    /// Color tag of this building, if any.
+    #[serde(default)]
+    pub tag: Option<ColorTag>,
+    /// Power of one copy of this building in MW (negative is consumption), used in place
+    /// of the power computed from the database. An escape hatch for buildings the
+    /// database doesn't describe, such as modded ones.
+    #[serde(default)]
+    pub power_override: Option<f32>,
 }
 
 impl Building {
@@ -420,6 +577,11 @@ impl Building {
         Node::new(Self::empty(), Balance::empty())
     }
 
+    /// Get the number of power shards needed to overclock all copies of this building.
+    pub fn power_shards(&self) -> u32 {
+        self.settings.power_shards() * self.copies
+    }
+
     /// Rebuild this node with a new database, converting errors to warnings.
     fn rebuild(&self, new_db: &Database) -> Node {
         match self.clone().build_node(new_db) {
@@ -466,6 +628,9 @@ impl BuildNode for Building {
                 }
             }
         }
+        if let Some(power) = self.power_override {
+            balance.power = power;
+        }
         balance *= self.copies as f32;
         Ok(Node::new(self, balance))
     }
@@ -477,6 +642,9 @@ impl Default for Building {
             building: None,
             settings: BuildingSettings::PowerConsumer,
             copies: 1,
+            locked: false,
+            tag: None,
+            power_override: None,
         }
     }
 }
@@ -520,6 +688,15 @@ impl BuildingSettings {
         }
     }
 
+    /// Get the number of power shards one building needs to run at its clock speed.
+    /// Each shard allows another 50% above 100%, up to [`MAX_POWER_SHARDS`].
+    pub fn power_shards(&self) -> u32 {
+        let overclock = self.clock_speed() - 1.0;
+        // Allow for rounding in entered clock speeds, such as 150.00001%.
+        let shards = ((overclock - 1e-4) / POWER_SHARD_STEP).ceil().max(0.0) as u32;
+        shards.min(MAX_POWER_SHARDS)
+    }
+
     /// Set the clock speed of the building if possible.
     pub fn set_clock_speed(&mut self, clock_speed: f32) {
         match self {
@@ -787,12 +964,10 @@ impl MinerSettings {
             }
 
             balance.power = -m.power_consumption.get_consumption_rate(self.clock_speed);
-            let cycles_per_minute =
-                60.0 / m.cycle_time * self.clock_speed * self.purity.speed_multiplier();
-
-            balance
-                .balances
-                .insert(resource_id, m.items_per_cycle * cycles_per_minute);
+            balance.balances.insert(
+                resource_id,
+                m.extraction_rate(self.purity) * self.clock_speed,
+            );
         }
         Ok(balance)
     }
@@ -1056,3 +1231,513 @@ mod private {
     impl Sealed for Group {}
     impl Sealed for Building {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::ItemCategory;
+
+    /// Build a manufacturer running the given recipe.
+    fn manufacturer(building: &str, recipe: &str, clock_speed: f32, copies: u32) -> Building {
+        Building {
+            building: Some(building.into()),
+            settings: BuildingSettings::Manufacturer(ManufacturerSettings {
+                recipe: Some(recipe.into()),
+                clock_speed,
+            }),
+            copies,
+            locked: false,
+            tag: None,
+            power_override: None,
+        }
+    }
+
+    /// Check that the node's power and item rates match exactly the expected values.
+    fn assert_balance(node: &Node, power: f32, expected: &[(&str, f32)]) {
+        let balance = node.balance();
+        assert!(
+            (balance.power - power).abs() < 1e-3,
+            "power: expected {}, got {}",
+            power,
+            balance.power
+        );
+        assert_eq!(
+            balance.balances.len(),
+            expected.len(),
+            "unexpected items in {:?}",
+            balance.balances
+        );
+        for &(item, rate) in expected {
+            let actual = balance
+                .balances
+                .get(&ItemId::from(item))
+                .copied()
+                .unwrap_or_else(|| panic!("{} missing from {:?}", item, balance.balances));
+            assert!(
+                (actual - rate).abs() < 1e-3,
+                "{}: expected {}, got {}",
+                item,
+                rate,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn turbofuel_balance() {
+        let db = Database::load_default();
+        let node = manufacturer("Desc_OilRefinery_C", "Recipe_Alternate_Turbofuel_C", 1.0, 1)
+            .build_node(&db)
+            .unwrap();
+        assert_balance(
+            &node,
+            -30.0,
+            &[
+                ("Desc_LiquidFuel_C", -22.5),
+                ("Desc_CompactedCoal_C", -15.0),
+                ("Desc_LiquidTurboFuel_C", 18.75),
+            ],
+        );
+    }
+
+    #[test]
+    fn aluminum_scrap_balance() {
+        let db = Database::load_default();
+        let node = manufacturer("Desc_OilRefinery_C", "Recipe_AluminumScrap_C", 1.0, 1)
+            .build_node(&db)
+            .unwrap();
+        assert_balance(
+            &node,
+            -30.0,
+            &[
+                ("Desc_AluminaSolution_C", -240.0),
+                ("Desc_Coal_C", -120.0),
+                ("Desc_AluminumScrap_C", 360.0),
+                ("Desc_Water_C", 120.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn aluminum_scrap_scales_with_clock_and_copies() {
+        let db = Database::load_default();
+        let node = manufacturer("Desc_OilRefinery_C", "Recipe_AluminumScrap_C", 0.5, 2)
+            .build_node(&db)
+            .unwrap();
+        let power = -2.0 * 30.0 * 0.5f32.powf(1.6);
+        assert_balance(
+            &node,
+            power,
+            &[
+                ("Desc_AluminaSolution_C", -240.0),
+                ("Desc_Coal_C", -120.0),
+                ("Desc_AluminumScrap_C", 360.0),
+                ("Desc_Water_C", 120.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn battery_balance() {
+        let db = Database::load_default();
+        let node = manufacturer("Desc_Blender_C", "Recipe_Battery_C", 1.0, 1)
+            .build_node(&db)
+            .unwrap();
+        assert_balance(
+            &node,
+            -75.0,
+            &[
+                ("Desc_SulfuricAcid_C", -50.0),
+                ("Desc_AluminaSolution_C", -40.0),
+                ("Desc_AluminumCasing_C", -20.0),
+                ("Desc_Battery_C", 20.0),
+                ("Desc_Water_C", 30.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn item_both_consumed_and_produced_is_netted() {
+        let db = Database::load_default();
+        let node = manufacturer("Desc_Blender_C", "Recipe_Alternate_InstantScrap_C", 1.0, 1)
+            .build_node(&db)
+            .unwrap();
+        assert_balance(
+            &node,
+            -75.0,
+            &[
+                ("Desc_OreBauxite_C", -150.0),
+                ("Desc_Coal_C", -100.0),
+                ("Desc_SulfuricAcid_C", -50.0),
+                ("Desc_Water_C", -10.0),
+                ("Desc_AluminumScrap_C", 300.0),
+            ],
+        );
+    }
+
+    /// Build a group node containing the given children.
+    fn group(children: Vec<Node>, copies: u32) -> Node {
+        let mut group = Group::empty();
+        group.children = children;
+        group.copies = copies;
+        group.into()
+    }
+
+    #[test]
+    fn group_sums_children() {
+        let db = Database::load_default();
+        let smelter = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1);
+        let constructor = manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 1);
+        let node = group(
+            vec![
+                smelter.build_node(&db).unwrap(),
+                constructor.build_node(&db).unwrap(),
+            ],
+            1,
+        );
+        assert_balance(
+            &node,
+            -8.0,
+            &[
+                ("Desc_OreIron_C", -30.0),
+                ("Desc_IronIngot_C", 0.0),
+                ("Desc_IronPlate_C", 20.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn gross_keeps_internal_turnover() {
+        let db = Database::load_default();
+        let smelter = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1);
+        let constructor = manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 1);
+        let inner = group(
+            vec![
+                smelter.build_node(&db).unwrap(),
+                constructor.build_node(&db).unwrap(),
+            ],
+            1,
+        );
+        let node = group(vec![inner], 2);
+        let ingot = ItemId::from("Desc_IronIngot_C");
+        let ore = ItemId::from("Desc_OreIron_C");
+        let gross = node.gross();
+        assert_eq!(gross.produced(ingot), 60.0);
+        assert_eq!(gross.consumed(ingot), 60.0);
+        assert_eq!(gross.produced(ore), 0.0);
+        assert_eq!(gross.consumed(ore), 60.0);
+
+        let stored = serde_json::to_string(&node).unwrap();
+        let loaded: Node = serde_json::from_str(&stored).unwrap();
+        assert_eq!(loaded.gross(), gross);
+    }
+
+    #[test]
+    fn power_shards_follow_clock_speed() {
+        let shards = |clock_speed| {
+            manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", clock_speed, 1)
+                .settings
+                .power_shards()
+        };
+        assert_eq!(shards(0.5), 0);
+        assert_eq!(shards(1.0), 0);
+        assert_eq!(shards(1.01), 1);
+        assert_eq!(shards(1.5), 1);
+        assert_eq!(shards(2.0), 2);
+        assert_eq!(shards(2.5), 3);
+
+        let db = Database::load_default();
+        let overclocked = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.75, 2);
+        assert_eq!(overclocked.power_shards(), 4);
+        let node = group(vec![overclocked.build_node(&db).unwrap()], 3);
+        assert_eq!(node.power_shards(), 12);
+    }
+
+    #[test]
+    fn extraction_presets_match_miner_balance() {
+        let db = Database::load_default();
+        let ore = ItemId::from("Desc_OreIron_C");
+        let presets = db.extraction_presets(ore);
+        // Three miner tiers on three purities.
+        assert_eq!(presets.len(), 9);
+        assert_eq!(presets[0].building, BuildingId::from("Desc_MinerMk1_C"));
+        assert_eq!(presets[0].purity, ResourcePurity::Impure);
+        assert!((presets[0].rate - 30.0).abs() < 1e-3);
+        assert!((presets[8].rate - 480.0).abs() < 1e-3);
+        for preset in presets {
+            let building = Building {
+                building: Some(preset.building),
+                settings: BuildingSettings::Miner(MinerSettings {
+                    resource: Some(ore),
+                    clock_speed: 1.0,
+                    purity: preset.purity,
+                }),
+                copies: 1,
+                locked: false,
+                tag: None,
+                power_override: None,
+            };
+            let node = building.build_node(&db).unwrap();
+            let rate = node.balance().balances[&ore];
+            assert!((rate - preset.rate).abs() < 1e-3, "rate: {}", rate);
+        }
+    }
+
+    #[test]
+    fn item_categories() {
+        let db = Database::load_default();
+        assert_eq!(
+            db.item_category(ItemId::from("Desc_OreIron_C")),
+            ItemCategory::Resource
+        );
+        assert_eq!(
+            db.item_category(ItemId::from("Desc_IronPlate_C")),
+            ItemCategory::MadeIn(BuildingId::from("Desc_ConstructorMk1_C"))
+        );
+        assert_eq!(
+            db.item_category(ItemId::from("Desc_NotARealItem_C")),
+            ItemCategory::Other
+        );
+    }
+
+    /// Build a coal generator burning coal at the given clock speed.
+    fn coal_generator(clock_speed: f32) -> Building {
+        Building {
+            building: Some("Desc_GeneratorCoal_C".into()),
+            settings: BuildingSettings::Generator(GeneratorSettings {
+                fuel: Some("Desc_Coal_C".into()),
+                clock_speed,
+            }),
+            copies: 1,
+            locked: false,
+            tag: None,
+            power_override: None,
+        }
+    }
+
+    #[test]
+    fn coal_generator_balance() {
+        let db = Database::load_default();
+        let node = coal_generator(1.0).build_node(&db).unwrap();
+        // 300 MJ per coal at 75 MW burns one coal every 4 seconds.
+        assert_balance(
+            &node,
+            75.0,
+            &[("Desc_Coal_C", -15.0), ("Desc_Water_C", -45.0)],
+        );
+    }
+
+    #[test]
+    fn underclocked_generator_burns_fuel_in_proportion_to_power() {
+        let db = Database::load_default();
+        for clock_speed in [0.5, 0.25, 0.01] {
+            let node = coal_generator(clock_speed).build_node(&db).unwrap();
+            // Generator power scales with the inverse of the power exponent.
+            let scale = clock_speed.powf(1.0 / 1.3);
+            assert_balance(
+                &node,
+                75.0 * scale,
+                &[
+                    ("Desc_Coal_C", -15.0 * scale),
+                    ("Desc_Water_C", -45.0 * scale),
+                ],
+            );
+            // Fuel per MW stays the same, so underclocking only trades power for fuel.
+            let coal = node.balance().balances[&ItemId::from("Desc_Coal_C")];
+            assert!((coal / node.balance().power - -15.0 / 75.0).abs() < 1e-4);
+        }
+        // Copies scale on top of the clock speed.
+        let mut generators = coal_generator(0.5);
+        generators.copies = 4;
+        let node = generators.build_node(&db).unwrap();
+        let scale = 4.0 * 0.5f32.powf(1.0 / 1.3);
+        assert_balance(
+            &node,
+            75.0 * scale,
+            &[
+                ("Desc_Coal_C", -15.0 * scale),
+                ("Desc_Water_C", -45.0 * scale),
+            ],
+        );
+    }
+
+    #[test]
+    fn power_override_propagates_through_groups() {
+        let db = Database::load_default();
+        let mut smelter = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 2.0, 3);
+        smelter.power_override = Some(-10.0);
+        let constructor = manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 1);
+        let inner = group(
+            vec![
+                smelter.clone().build_node(&db).unwrap(),
+                constructor.build_node(&db).unwrap(),
+            ],
+            1,
+        );
+        let outer = group(vec![inner], 2);
+        // The override replaces the overclocked power, but items still scale.
+        assert_balance(
+            &outer,
+            2.0 * (3.0 * -10.0 - 4.0),
+            &[
+                ("Desc_OreIron_C", -360.0),
+                ("Desc_IronIngot_C", 300.0),
+                ("Desc_IronPlate_C", 40.0),
+            ],
+        );
+        // Clearing the override goes back to the computed power.
+        smelter.power_override = None;
+        let node = smelter.build_node(&db).unwrap();
+        assert!((node.balance().power - -3.0 * 4.0 * 2.0f32.powf(1.6)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn assumed_supply_hides_deficits() {
+        let db = Database::load_default();
+        let constructor = manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 1);
+        let mut inner = Group::empty();
+        inner.children = vec![constructor.build_node(&db).unwrap()];
+        inner.copies = 2;
+        inner.assumed_supplied.insert("Desc_IronIngot_C".into());
+        // The plates themselves are a surplus, so are kept even if assumed supplied.
+        inner.assumed_supplied.insert("Desc_IronPlate_C".into());
+        let outer = group(vec![inner.into()], 1);
+        assert_balance(
+            &outer,
+            -8.0,
+            &[("Desc_IronIngot_C", 0.0), ("Desc_IronPlate_C", 40.0)],
+        );
+    }
+
+    #[test]
+    fn nested_group_copies_multiply() {
+        let db = Database::load_default();
+        let smelter = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1);
+        let inner = group(vec![smelter.build_node(&db).unwrap()], 2);
+        let outer = group(vec![inner], 3);
+        assert_balance(
+            &outer,
+            -24.0,
+            &[("Desc_OreIron_C", -180.0), ("Desc_IronIngot_C", 180.0)],
+        );
+    }
+
+    #[test]
+    fn overclocking_scales_rates_linearly_and_power_exponentially() {
+        let db = Database::load_default();
+        let node = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 2.0, 1)
+            .build_node(&db)
+            .unwrap();
+        assert_balance(
+            &node,
+            -4.0 * 2.0f32.powf(1.6),
+            &[("Desc_OreIron_C", -60.0), ("Desc_IronIngot_C", 60.0)],
+        );
+    }
+
+    #[test]
+    fn multi_io_recipes_net_shared_items_in_group() {
+        let db = Database::load_default();
+        let battery = manufacturer("Desc_Blender_C", "Recipe_Battery_C", 1.0, 1);
+        let scrap = manufacturer("Desc_Blender_C", "Recipe_Alternate_InstantScrap_C", 1.0, 1);
+        let node = group(
+            vec![
+                battery.build_node(&db).unwrap(),
+                scrap.build_node(&db).unwrap(),
+            ],
+            1,
+        );
+        assert_balance(
+            &node,
+            -150.0,
+            &[
+                ("Desc_SulfuricAcid_C", -100.0),
+                ("Desc_AluminaSolution_C", -40.0),
+                ("Desc_AluminumCasing_C", -20.0),
+                ("Desc_OreBauxite_C", -150.0),
+                ("Desc_Coal_C", -100.0),
+                ("Desc_Water_C", 20.0),
+                ("Desc_Battery_C", 20.0),
+                ("Desc_AluminumScrap_C", 300.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn empty_group_has_empty_balance() {
+        let node = group(Vec::new(), 5);
+        assert_balance(&node, 0.0, &[]);
+    }
+
+    #[test]
+    fn primary_product_defaults_to_largest_output() {
+        let db = Database::load_default();
+        let scrap = manufacturer("Desc_OilRefinery_C", "Recipe_AluminumScrap_C", 1.0, 1);
+        let mut group = Group::empty();
+        group.children.push(scrap.build_node(&db).unwrap());
+        let node: Node = group.clone().into();
+        assert_eq!(
+            node.primary_product(),
+            Some(ItemId::from("Desc_AluminumScrap_C"))
+        );
+        group.primary_product = Some("Desc_Water_C".into());
+        let node: Node = group.into();
+        assert_eq!(node.primary_product(), Some(ItemId::from("Desc_Water_C")));
+    }
+
+    /// Build a named group of an iron smelter feeding a plate constructor.
+    fn plate_line(db: &Database, name: &str) -> Node {
+        let smelter = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1);
+        let constructor = manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 1);
+        let mut group = Group::empty();
+        group.name = name.to_string();
+        group.children = vec![
+            smelter.build_node(db).unwrap(),
+            constructor.build_node(db).unwrap(),
+        ];
+        group.into()
+    }
+
+    #[test]
+    fn cache_reuses_identical_groups() {
+        let db = Database::load_default();
+        // Same structure, but different names and ids.
+        let root = group(vec![plate_line(&db, "North"), plate_line(&db, "South")], 1);
+        let mut cache = BalanceCache::new(100);
+        let rebuilt = cache.rebuild(&root, &db);
+        assert_eq!(rebuilt.balance(), root.balance());
+        assert_eq!(rebuilt, root.rebuild(&db));
+        // The second line hits for both of its buildings and itself.
+        assert_eq!(cache.misses(), 4);
+        assert_eq!(cache.hits(), 3);
+
+        // Rebuilding again after an unrelated change only computes what changed.
+        let mut edited = root.group().unwrap().clone();
+        edited.copies = 2;
+        let rebuilt = cache.rebuild(&edited.into(), &db);
+        assert_eq!(cache.misses(), 5);
+        assert_eq!(cache.hits(), 9);
+        assert_balance(
+            &rebuilt,
+            -32.0,
+            &[
+                ("Desc_OreIron_C", -120.0),
+                ("Desc_IronIngot_C", 0.0),
+                ("Desc_IronPlate_C", 80.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn cache_distinguishes_settings_and_empties_when_full() {
+        let db = Database::load_default();
+        let normal = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1);
+        let overclocked = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 2.0, 1);
+        let mut cache = BalanceCache::new(1);
+        let slow = cache.rebuild(&normal.build_node(&db).unwrap(), &db);
+        let fast = cache.rebuild(&overclocked.build_node(&db).unwrap(), &db);
+        assert_eq!(cache.hits(), 0);
+        assert_ne!(slow.balance(), fast.balance());
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -45,6 +45,63 @@ impl Balance {
     }
 }
 
+/// Gross production and consumption of each item in a node, before netting.
+///
+/// Each building contributes its positive rates to `produced` and its negative rates to
+/// `consumed`, so items passed between buildings within a group appear in both even
+/// though their net rate may be zero. Power is not included.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GrossBalance {
+    /// Total rate each item is produced at, in units-per-minute by ID.
+    pub produced: BTreeMap<ItemId, f32>,
+    /// Total rate each item is consumed at, as a positive number of units-per-minute.
+    pub consumed: BTreeMap<ItemId, f32>,
+}
+
+impl GrossBalance {
+    /// Split the net balance of a single building into gross production and consumption.
+    pub fn from_net(balance: &Balance) -> Self {
+        let mut gross = Self::default();
+        for (&item, &rate) in &balance.balances {
+            if rate > 0.0 {
+                gross.produced.insert(item, rate);
+            } else if rate < 0.0 {
+                gross.consumed.insert(item, -rate);
+            }
+        }
+        gross
+    }
+
+    /// Get the rate the item is produced at, or 0 if it isn't produced.
+    pub fn produced(&self, item: ItemId) -> f32 {
+        self.produced.get(&item).copied().unwrap_or_default()
+    }
+
+    /// Get the rate the item is consumed at, or 0 if it isn't consumed.
+    pub fn consumed(&self, item: ItemId) -> f32 {
+        self.consumed.get(&item).copied().unwrap_or_default()
+    }
+}
+
+impl AddAssign<&GrossBalance> for GrossBalance {
+    fn add_assign(&mut self, rhs: &Self) {
+        for (&item, &rate) in &rhs.produced {
+            *self.produced.entry(item).or_default() += rate;
+        }
+        for (&item, &rate) in &rhs.consumed {
+            *self.consumed.entry(item).or_default() += rate;
+        }
+    }
+}
+
+impl MulAssign<f32> for GrossBalance {
+    fn mul_assign(&mut self, rhs: f32) {
+        for rate in self.produced.values_mut().chain(self.consumed.values_mut()) {
+            *rate *= rhs;
+        }
+    }
+}
+
 impl Add for Balance {
     type Output = Self;
 
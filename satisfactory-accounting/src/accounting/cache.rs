@@ -0,0 +1,171 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::{Balance, BuildError, Building, Group, Node, NodeKind};
+use crate::database::Database;
+
+/// Cache of node balances, keyed by a hash of everything in a node which affects its
+/// balance.
+///
+/// Names, ids, tags, and other display-only fields are left out of the hash, so
+/// structurally identical subtrees anywhere in the tree share entries, as does a subtree
+/// which is unchanged between two rebuilds. Balances depend on the database, so the cache
+/// must be cleared whenever the database changes.
+#[derive(Debug, Clone)]
+pub struct BalanceCache {
+    /// Cached balances by content hash.
+    entries: HashMap<u64, CachedBalance>,
+    /// Largest number of entries to keep. The cache is emptied when it fills up.
+    capacity: usize,
+    /// Number of lookups which found a cached balance.
+    hits: usize,
+    /// Number of lookups which had to compute the balance.
+    misses: usize,
+}
+
+/// Balance of a node, or the error building it.
+#[derive(Debug, Clone)]
+struct CachedBalance {
+    balance: Balance,
+    warning: Option<BuildError>,
+}
+
+impl BalanceCache {
+    /// Create an empty cache holding at most `capacity` balances.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Number of balances currently cached.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no balances are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of lookups which found a cached balance.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of lookups which had to compute the balance.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Remove all cached balances, such as when the database changes.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Rebuild a node and all of its descendants with the given database, reusing cached
+    /// balances where possible. Like [`Node::rebuild`], errors are converted to warnings.
+    pub fn rebuild(&mut self, node: &Node, db: &Database) -> Node {
+        self.rebuild_hashed(node, db).0
+    }
+
+    /// Rebuild a node, returning it along with its content hash.
+    fn rebuild_hashed(&mut self, node: &Node, db: &Database) -> (Node, u64) {
+        match node.kind() {
+            NodeKind::Building(building) => {
+                let hash = building_hash(building);
+                match self.lookup(hash) {
+                    Some(CachedBalance {
+                        warning: Some(warning),
+                        ..
+                    }) => (Node::warn(building.clone(), warning), hash),
+                    Some(CachedBalance { balance, .. }) => {
+                        (Node::new(building.clone(), balance), hash)
+                    }
+                    None => {
+                        let rebuilt = building.rebuild(db);
+                        self.insert(hash, &rebuilt);
+                        (rebuilt, hash)
+                    }
+                }
+            }
+            NodeKind::Group(group) => {
+                let mut copy = group.clone();
+                let mut hasher = DefaultHasher::new();
+                hash_group_fields(group, &mut hasher);
+                for child in &mut copy.children {
+                    let (rebuilt, child_hash) = self.rebuild_hashed(child, db);
+                    *child = rebuilt;
+                    child_hash.hash(&mut hasher);
+                }
+                let hash = hasher.finish();
+                match self.lookup(hash) {
+                    Some(cached) => (Node::new(copy, cached.balance), hash),
+                    None => {
+                        let rebuilt: Node = copy.into();
+                        self.insert(hash, &rebuilt);
+                        (rebuilt, hash)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Look up the balance with the given hash, counting the hit or miss.
+    fn lookup(&mut self, hash: u64) -> Option<CachedBalance> {
+        let cached = self.entries.get(&hash).cloned();
+        if cached.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        cached
+    }
+
+    /// Cache the balance of a freshly built node, emptying the cache first if it is full.
+    fn insert(&mut self, hash: u64, node: &Node) {
+        if self.entries.len() >= self.capacity {
+            self.entries.clear();
+        }
+        self.entries.insert(
+            hash,
+            CachedBalance {
+                balance: node.balance().clone(),
+                warning: node.warning(),
+            },
+        );
+    }
+}
+
+/// Compute the content hash of a building from the fields which affect its balance.
+fn building_hash(building: &Building) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    "building".hash(&mut hasher);
+    building.building.hash(&mut hasher);
+    // Settings contain floats, which aren't Hash, so hash their serialized form instead.
+    serde_json::to_string(&building.settings)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    building.copies.hash(&mut hasher);
+    building.power_override.map(f32::to_bits).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash the fields of a group which affect its balance, other than its children.
+fn hash_group_fields(group: &Group, hasher: &mut impl Hasher) {
+    "group".hash(hasher);
+    group.copies.hash(hasher);
+    group.assumed_supplied.hash(hasher);
+    group.children.len().hash(hasher);
+}
@@ -16,7 +16,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::accounting::{
     BuildingSettings, GeneratorSettings, ManufacturerSettings, MinerSettings, PumpSettings,
-    StationSettings,
+    ResourcePurity, StationSettings,
 };
 
 /// Database of satisfactory ... stuff.
@@ -41,6 +41,99 @@ impl Database {
         const SERIALIZED_DB: &str = include_str!("../db.json");
         serde_json::from_str(SERIALIZED_DB).expect("Failed to parse included db.json")
     }
+
+    /// Get the standard ways to extract a resource: every miner which can extract it on
+    /// every node purity, with the rate at 100% clock speed. Ordered from slowest to
+    /// fastest.
+    pub fn extraction_presets(&self, resource: ItemId) -> Vec<ExtractionPreset> {
+        let item = match self.get(resource) {
+            Some(item) => item,
+            None => return Vec::new(),
+        };
+        let mut presets: Vec<_> = item
+            .mined_by
+            .iter()
+            .filter_map(|&id| match &self.get(id)?.kind {
+                BuildingKind::Miner(m) if m.allowed_resources.contains(&resource) => Some((id, m)),
+                _ => None,
+            })
+            .flat_map(|(building, m)| {
+                ResourcePurity::values().map(move |purity| ExtractionPreset {
+                    building,
+                    purity,
+                    rate: m.extraction_rate(purity),
+                })
+            })
+            .collect();
+        presets.sort_by(|p1, p2| p1.rate.total_cmp(&p2.rate));
+        presets
+    }
+
+    /// Get the category an item is listed under when grouping balances. Raw resources and
+    /// fuels get their own categories, and other items are grouped by the building their
+    /// standard recipe is made in.
+    pub fn item_category(&self, item: ItemId) -> ItemCategory {
+        let item = match self.get(item) {
+            Some(item) => item,
+            None => return ItemCategory::Other,
+        };
+        if !item.mined_by.is_empty() {
+            return ItemCategory::Resource;
+        }
+        if item.fuel.is_some() {
+            return ItemCategory::Fuel;
+        }
+        let recipes: Vec<&Recipe> = item
+            .produced_by
+            .iter()
+            .filter_map(|&id| self.get(id))
+            .collect();
+        recipes
+            .iter()
+            .find(|recipe| !recipe.is_alternate)
+            .or_else(|| recipes.first())
+            .and_then(|recipe| recipe.produced_in.first().copied())
+            .map_or(ItemCategory::Other, ItemCategory::MadeIn)
+    }
+}
+
+/// Category used to group related items in a balance.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum ItemCategory {
+    /// Items which can be extracted by a miner.
+    Resource,
+    /// Items which can be burned in a generator.
+    Fuel,
+    /// Items made in the given building.
+    MadeIn(BuildingId),
+    /// Items with no machine recipe.
+    Other,
+}
+
+impl ItemCategory {
+    /// Get a string suitable for human display of this category.
+    pub fn name(self, db: &Database) -> String {
+        match self {
+            Self::Resource => "Resources".to_string(),
+            Self::Fuel => "Fuels".to_string(),
+            Self::MadeIn(building) => match db.get(building) {
+                Some(building) => format!("{} Products", building.name),
+                None => "Unknown Building Products".to_string(),
+            },
+            Self::Other => "Other".to_string(),
+        }
+    }
+}
+
+/// A standard way to extract a resource, combining a miner with a node purity.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ExtractionPreset {
+    /// Miner building used.
+    pub building: BuildingId,
+    /// Purity of the node the miner is built on.
+    pub purity: ResourcePurity,
+    /// Items extracted per minute at 100% clock speed.
+    pub rate: f32,
 }
 
 impl<T: Id> Index<T> for Database {
@@ -166,6 +259,19 @@ pub struct Recipe {
     pub is_alternate: bool,
     /// Buildings which can produce this recipe.
     pub produced_in: Vec<BuildingId>,
+    /// Range of power drawn over the production cycle, for recipes whose power varies.
+    #[serde(default)]
+    pub variable_power: Option<PowerRange>,
+}
+
+/// Range a building's power draw cycles through while running a recipe, in MW at 100%
+/// clock speed.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerRange {
+    /// Lowest power drawn during the cycle.
+    pub min: f32,
+    /// Highest power drawn during the cycle.
+    pub max: f32,
 }
 
 /// An input or output: a certain number of items produced or consumed.
@@ -209,11 +315,32 @@ pub struct Fuel {
     pub byproducts: Vec<ItemAmount>,
 }
 
+/// Items which are carried in pipelines rather than on belts. The database doesn't record
+/// the form of items, so these are listed by id.
+const FLUID_ITEMS: &[&str] = &[
+    "Desc_Water_C",
+    "Desc_LiquidOil_C",
+    "Desc_HeavyOilResidue_C",
+    "Desc_LiquidFuel_C",
+    "Desc_LiquidTurboFuel_C",
+    "Desc_LiquidBiofuel_C",
+    "Desc_AluminaSolution_C",
+    "Desc_SulfuricAcid_C",
+    "Desc_NitricAcid_C",
+    "Desc_NitrogenGas_C",
+];
+
 impl ItemId {
     /// Get the ItemId for water.
     pub fn water() -> Self {
         "Desc_Water_C".into()
     }
+
+    /// Whether this item is a fluid or gas, which is moved in pipelines. Other items are
+    /// solids, moved on belts.
+    pub fn is_fluid(self) -> bool {
+        FLUID_ITEMS.contains(&self.as_str())
+    }
 }
 
 /// A building used to produce or use items.
@@ -229,6 +356,31 @@ pub struct BuildingType {
     pub description: String,
     /// Kind of the building.
     pub kind: BuildingKind,
+    /// Approximate floor space taken up by the building, if known.
+    #[serde(default)]
+    pub footprint: Option<Footprint>,
+}
+
+/// Floor space taken up by a building, in meters.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Footprint {
+    /// Size of the building from side to side.
+    pub width: f32,
+    /// Size of the building from front to back.
+    pub length: f32,
+}
+
+impl Footprint {
+    /// Side length of a foundation in meters.
+    pub const FOUNDATION_SIZE: f32 = 8.0;
+
+    /// Number of foundations needed to place one of this building, rounding each side up
+    /// to a whole number of foundations.
+    pub fn foundations(&self) -> u32 {
+        let across = (self.width / Self::FOUNDATION_SIZE).ceil().max(1.0) as u32;
+        let along = (self.length / Self::FOUNDATION_SIZE).ceil().max(1.0) as u32;
+        across * along
+    }
 }
 
 impl BuildingType {
@@ -383,6 +535,14 @@ pub struct Miner {
     pub power_consumption: Power,
 }
 
+impl Miner {
+    /// Get the number of items extracted per minute at 100% clock speed from a node of the
+    /// given purity.
+    pub fn extraction_rate(&self, purity: ResourcePurity) -> f32 {
+        60.0 / self.cycle_time * self.items_per_cycle * purity.speed_multiplier()
+    }
+}
+
 /// Generator settings of a building.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Generator {
@@ -0,0 +1,361 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Conversion to and from the JSON format of the community production planner at
+//! [Satisfactory Tools](https://www.satisfactorytools.com).
+//!
+//! The planner describes a factory by what it should produce and what is supplied to it,
+//! and chooses the buildings itself, so only the net flows of a tree survive export. See
+//! [`Lost`] for what can't be represented. Both formats identify items and recipes by the
+//! game's class names, so ids are checked against the [`Database`] rather than renamed.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::accounting::{BuildingSettings, Group, Node, NodeKind};
+use crate::database::{Database, ItemId, RecipeId};
+use crate::solver::{self, RecipeStrategy};
+
+/// Rates smaller than this are considered balanced and left out of exports.
+const EPSILON: f32 = 1e-3;
+
+/// Target type the planner uses for a fixed rate per minute.
+const PER_MINUTE: &str = "perMinute";
+
+/// Version of the planner's format which is written on export.
+const SCHEMA_VERSION: u32 = 1;
+
+/// A factory in the planner's format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannerFactory {
+    /// Name and version of the factory.
+    #[serde(default)]
+    pub metadata: PlannerMetadata,
+    /// What the planner should solve for.
+    pub request: PlannerRequest,
+}
+
+/// Descriptive fields of a planner factory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlannerMetadata {
+    /// Name of the factory, if it has one.
+    pub name: Option<String>,
+    /// Icon of the factory, as a planner image name.
+    pub icon: Option<String>,
+    /// Version of the format the factory was saved in.
+    pub schema_version: u32,
+}
+
+/// Targets and constraints of a planner factory. Constraints this crate has no equivalent
+/// for, such as resource limits, are ignored on import and omitted on export.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlannerRequest {
+    /// Alternate recipes the planner may use.
+    pub allowed_alternate_recipes: Vec<RecipeId>,
+    /// Standard recipes the planner may not use.
+    pub blocked_recipes: Vec<RecipeId>,
+    /// Items the factory should produce.
+    pub production: Vec<PlannerTarget>,
+    /// Items supplied to the factory from outside.
+    pub input: Vec<PlannerInput>,
+}
+
+/// An item the planner should produce.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannerTarget {
+    /// Item to produce. The planner leaves this empty for rows which haven't been filled
+    /// in yet.
+    pub item: Option<ItemId>,
+    /// How the amount is measured. Only rates per minute can be imported.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Rate per minute to produce.
+    pub amount: f32,
+    /// Share of the factory given to this target when maximizing, as a percentage.
+    #[serde(default = "default_ratio")]
+    pub ratio: f32,
+}
+
+/// An item supplied to the planner's factory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannerInput {
+    /// Item supplied, empty for rows which haven't been filled in.
+    pub item: Option<ItemId>,
+    /// Rate per minute supplied.
+    pub amount: f32,
+}
+
+fn default_ratio() -> f32 {
+    100.0
+}
+
+/// Parts of a factory which can't be represented in the other format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Error)]
+pub enum Lost {
+    /// Nested groups are flattened into a single factory.
+    #[error("Nested groups are merged into one factory.")]
+    Groups,
+    /// The planner chooses its own buildings, so counts, clock speeds, and miners aren't
+    /// kept.
+    #[error("Building counts, clock speeds, miners, and pumps are chosen by the planner.")]
+    Buildings,
+    /// Only which alternates are allowed is kept, not which recipe makes each item.
+    #[error("The planner may choose different recipes for some items.")]
+    Recipes,
+    /// Generators and other power buildings aren't part of the planner's factories.
+    #[error("Generators and other power buildings are left out.")]
+    Power,
+    /// Deficits hidden by assumed supplies aren't listed as inputs.
+    #[error("Items assumed to be supplied are left out of the inputs.")]
+    AssumedSupplies,
+    /// Targets which aren't fixed rates per minute can't be imported.
+    #[error("Targets which aren't a rate per minute are skipped.")]
+    NonRateTargets,
+    /// Supplied intermediate products are produced inside the group instead of imported.
+    #[error("Supplied intermediate products are produced by the imported group instead.")]
+    SuppliedIntermediates,
+}
+
+/// Result of exporting a node.
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// The exported factory.
+    pub factory: PlannerFactory,
+    /// Parts of the node which weren't exported, for warning the user.
+    pub lost: BTreeSet<Lost>,
+}
+
+impl Export {
+    /// Serialize the exported factory to JSON.
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(&self.factory).expect("planner factories always serialize")
+    }
+}
+
+/// Result of importing a planner factory.
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// Group which produces the planner's targets.
+    pub group: Group,
+    /// Parts of the factory which weren't imported, for warning the user.
+    pub lost: BTreeSet<Lost>,
+}
+
+/// Error importing a planner factory.
+#[derive(Debug, Error)]
+pub enum ImportError {
+    /// The JSON isn't a planner factory.
+    #[error("Not a valid Satisfactory Tools factory: {0}")]
+    Invalid(#[from] serde_json::Error),
+    /// The factory refers to an item which isn't in the database.
+    #[error("Item {0} is not in the database")]
+    UnknownItem(ItemId),
+    /// The factory has no targets which can be imported.
+    #[error("The factory doesn't produce anything")]
+    NoTargets,
+}
+
+/// Check whether a JSON value looks like a planner factory rather than a stored node.
+pub fn is_planner_factory(value: &Value) -> bool {
+    value.get("request").is_some_and(Value::is_object)
+}
+
+/// Export the net flows of a node as a planner factory with the given name. Surplus items
+/// become production targets, and deficits of items which can't be mined become inputs.
+/// The planner mines raw resources itself, so they aren't listed.
+pub fn export(node: &Node, name: &str, db: &Database) -> Export {
+    let mut lost = BTreeSet::new();
+    let mut alternates = BTreeSet::new();
+    // The node itself is visited first, so any later group is nested.
+    for (idx, descendant) in node.iter().enumerate() {
+        match descendant.kind() {
+            NodeKind::Group(group) => {
+                if idx > 0 {
+                    lost.insert(Lost::Groups);
+                }
+                if !group.assumed_supplied.is_empty() {
+                    lost.insert(Lost::AssumedSupplies);
+                }
+            }
+            NodeKind::Building(building) => match &building.settings {
+                BuildingSettings::Manufacturer(settings) => {
+                    lost.insert(Lost::Buildings);
+                    lost.insert(Lost::Recipes);
+                    if let Some(recipe) = settings.recipe.and_then(|id| db.get(id)) {
+                        if recipe.is_alternate {
+                            alternates.insert(recipe.id);
+                        }
+                    }
+                }
+                BuildingSettings::Miner(_) | BuildingSettings::Pump(_) => {
+                    lost.insert(Lost::Buildings);
+                }
+                BuildingSettings::Generator(_)
+                | BuildingSettings::Geothermal(_)
+                | BuildingSettings::PowerConsumer => {
+                    lost.insert(Lost::Power);
+                }
+                BuildingSettings::Station(_) => {
+                    lost.insert(Lost::Buildings);
+                }
+            },
+        }
+    }
+
+    let balances = &node.balance().balances;
+    let production = balances
+        .iter()
+        .filter(|&(_, &rate)| rate > EPSILON)
+        .map(|(&item, &rate)| PlannerTarget {
+            item: Some(item),
+            kind: PER_MINUTE.to_owned(),
+            amount: rate,
+            ratio: default_ratio(),
+        })
+        .collect();
+    let input = balances
+        .iter()
+        .filter(|&(&item, &rate)| {
+            rate < -EPSILON && db.get(item).is_none_or(|info| info.mined_by.is_empty())
+        })
+        .map(|(&item, &rate)| PlannerInput {
+            item: Some(item),
+            amount: -rate,
+        })
+        .collect();
+
+    Export {
+        factory: PlannerFactory {
+            metadata: PlannerMetadata {
+                name: Some(name.to_owned()),
+                icon: None,
+                schema_version: SCHEMA_VERSION,
+            },
+            request: PlannerRequest {
+                allowed_alternate_recipes: alternates.into_iter().collect(),
+                blocked_recipes: Vec::new(),
+                production,
+                input,
+            },
+        },
+        lost,
+    }
+}
+
+/// Import a planner factory as a group, using the [solver][solver::solve] to pick
+/// buildings for its targets. Inputs to the factory are marked as assumed supplied in the
+/// group.
+pub fn import(value: Value, db: &Database) -> Result<Import, ImportError> {
+    let factory: PlannerFactory = serde_json::from_value(value)?;
+    let mut lost = BTreeSet::new();
+    let mut targets = Vec::new();
+    for target in &factory.request.production {
+        let item = match target.item {
+            Some(item) => item,
+            None => continue,
+        };
+        if db.get(item).is_none() {
+            return Err(ImportError::UnknownItem(item));
+        }
+        if target.kind != PER_MINUTE {
+            lost.insert(Lost::NonRateTargets);
+        } else if target.amount > EPSILON {
+            targets.push((item, target.amount));
+        }
+    }
+    if targets.is_empty() {
+        return Err(ImportError::NoTargets);
+    }
+
+    let mut supplied = BTreeSet::new();
+    for item in factory.request.input.iter().filter_map(|input| input.item) {
+        let info = db.get(item).ok_or(ImportError::UnknownItem(item))?;
+        if info.mined_by.is_empty() {
+            lost.insert(Lost::SuppliedIntermediates);
+        }
+        supplied.insert(item);
+    }
+    if !factory.request.allowed_alternate_recipes.is_empty()
+        || !factory.request.blocked_recipes.is_empty()
+    {
+        lost.insert(Lost::Recipes);
+    }
+
+    let mut group = solver::solve(db, &targets, RecipeStrategy::Standard).group;
+    if let Some(name) = factory.metadata.name.filter(|name| !name.is_empty()) {
+        group.name = name;
+    }
+    group.assumed_supplied = supplied;
+    Ok(Import { group, lost })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Factory saved by the planner, producing iron plates and rods from iron ore.
+    const SAMPLE_FACTORY: &str = include_str!("../testdata/satisfactory_tools_iron.json");
+
+    #[test]
+    fn imports_sample_factory() {
+        let db = Database::load_default();
+        let sample: Value = serde_json::from_str(SAMPLE_FACTORY).unwrap();
+        assert!(is_planner_factory(&sample));
+        let import = import(sample, &db).unwrap();
+        assert_eq!(import.group.name, "Iron Basics");
+        assert!(import.lost.contains(&Lost::NonRateTargets));
+
+        let node: Node = import.group.into();
+        let balances = &node.balance().balances;
+        let plates = balances[&ItemId::from("Desc_IronPlate_C")];
+        let rods = balances[&ItemId::from("Desc_IronRod_C")];
+        assert!((plates - 60.0).abs() < 0.01, "plates: {}", plates);
+        assert!((rods - 30.0).abs() < 0.01, "rods: {}", rods);
+    }
+
+    #[test]
+    fn round_trips_sample_targets() {
+        let db = Database::load_default();
+        let sample: PlannerFactory = serde_json::from_str(SAMPLE_FACTORY).unwrap();
+        let node: Node = import(serde_json::to_value(&sample).unwrap(), &db)
+            .unwrap()
+            .group
+            .into();
+        let export = export(&node, "Iron Basics", &db);
+        assert!(export.lost.contains(&Lost::Buildings));
+        assert!(!export.lost.contains(&Lost::Groups));
+        assert!(!export.lost.contains(&Lost::Power));
+        // Iron ore is mined by the planner itself, so it isn't an input.
+        assert!(export.factory.request.input.is_empty());
+
+        // Rows with no item chosen are skipped on import, so they don't come back.
+        let expected: Vec<_> = sample
+            .request
+            .production
+            .iter()
+            .filter(|target| target.item.is_some() && target.kind == PER_MINUTE)
+            .collect();
+        let exported = &export.factory.request.production;
+        assert_eq!(exported.len(), expected.len());
+        for expected in expected {
+            let exported = exported
+                .iter()
+                .find(|target| target.item == expected.item)
+                .expect("target missing from export");
+            assert_eq!(exported.kind, PER_MINUTE);
+            assert!((exported.amount - expected.amount).abs() < 0.01);
+        }
+
+        let reimported: PlannerFactory = serde_json::from_value(export.to_json()).unwrap();
+        assert_eq!(reimported, export.factory);
+    }
+}
@@ -7,3 +7,6 @@
 //       http://www.apache.org/licenses/LICENSE-2.0
 pub mod accounting;
 pub mod database;
+pub mod io;
+pub mod schema;
+pub mod solver;
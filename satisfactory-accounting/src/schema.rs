@@ -0,0 +1,439 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Versioned storage format for accounting trees.
+//!
+//! Schema history:
+//!
+//! 1. A bare serialized [`Node`]. Documents saved before virtual copies were added have
+//!    no `copies` field on groups or buildings.
+//! 2. The root node wrapped in an object with its schema version. Every group and
+//!    building has an explicit `copies` field.
+//! 3. Groups have a `primary_product` field, which is null if not designated.
+//! 4. Buildings have a `locked` field.
+//! 5. Groups and buildings have a `tag` field, which is null if not tagged.
+//! 6. Groups have an `assumed_supplied` field, which lists the ids of items assumed to
+//!    be supplied externally.
+//! 7. Buildings have a `power_override` field, which is null unless the power is set
+//!    manually.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::accounting::Node;
+
+/// Current version of the stored document schema. Bump this and add a step to
+/// [`migrate_step`] whenever the stored format changes.
+pub const SCHEMA_VERSION: u32 = 7;
+
+/// Error which occurs when loading a stored document.
+#[derive(Debug, Error)]
+pub enum LoadError {
+    /// The document was saved by a newer version of the app.
+    #[error(
+        "Document schema version {0} is newer than the supported version {}.",
+        SCHEMA_VERSION
+    )]
+    TooNew(u32),
+    /// The document could not be parsed, either before or after migration.
+    #[error("Invalid document: {0}")]
+    Invalid(#[from] serde_json::Error),
+}
+
+/// A root node along with the schema version it is stored in.
+#[derive(Serialize)]
+struct Versioned<'a> {
+    version: u32,
+    root: &'a Node,
+}
+
+/// Convert a node to the current stored schema.
+pub fn store(root: &Node) -> Value {
+    serde_json::to_value(Versioned {
+        version: SCHEMA_VERSION,
+        root,
+    })
+    .expect("nodes are always serializable")
+}
+
+/// Load a stored document of any known schema version, migrating it to the current
+/// schema.
+pub fn load(mut stored: Value) -> Result<Node, LoadError> {
+    let (mut version, mut root) = match stored.get("version").and_then(Value::as_u64) {
+        Some(version) => (
+            u32::try_from(version).unwrap_or(u32::MAX),
+            stored.get_mut("root").map(Value::take).unwrap_or_default(),
+        ),
+        // Version 1 documents are a bare node with no wrapper.
+        None => (1, stored),
+    };
+    if version > SCHEMA_VERSION {
+        return Err(LoadError::TooNew(version));
+    }
+    while version < SCHEMA_VERSION {
+        migrate_step(version, &mut root);
+        version += 1;
+    }
+    Ok(serde_json::from_value(root)?)
+}
+
+/// Upgrade a root node from the given version to the next version.
+fn migrate_step(version: u32, root: &mut Value) {
+    match version {
+        1 => for_each_node(root, &mut |_, inner| {
+            inner.entry("copies").or_insert_with(|| 1.into());
+        }),
+        2 => for_each_node(root, &mut |kind, inner| {
+            if kind == "Group" {
+                inner.entry("primary_product").or_insert(Value::Null);
+            }
+        }),
+        3 => for_each_node(root, &mut |kind, inner| {
+            if kind == "Building" {
+                inner.entry("locked").or_insert(Value::Bool(false));
+            }
+        }),
+        4 => for_each_node(root, &mut |_, inner| {
+            inner.entry("tag").or_insert(Value::Null);
+        }),
+        5 => for_each_node(root, &mut |kind, inner| {
+            if kind == "Group" {
+                inner
+                    .entry("assumed_supplied")
+                    .or_insert_with(|| Value::Array(Vec::new()));
+            }
+        }),
+        6 => for_each_node(root, &mut |kind, inner| {
+            if kind == "Building" {
+                inner.entry("power_override").or_insert(Value::Null);
+            }
+        }),
+        _ => unreachable!("no migration from schema version {}", version),
+    }
+}
+
+/// Call `f` with the kind name (`Group` or `Building`) and fields of every node in the
+/// tree, recursing into the children of groups after visiting the group itself.
+fn for_each_node(node: &mut Value, f: &mut impl FnMut(&str, &mut Map<String, Value>)) {
+    let kind = match node.get_mut("kind").and_then(Value::as_object_mut) {
+        Some(kind) => kind,
+        None => return,
+    };
+    for (kind_name, inner) in kind.iter_mut() {
+        if let Some(inner) = inner.as_object_mut() {
+            f(kind_name, inner);
+            if let Some(children) = inner.get_mut("children").and_then(Value::as_array_mut) {
+                for child in children {
+                    for_each_node(child, f);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::accounting::{BuildNode, Building, BuildingSettings, ColorTag, Group};
+    use crate::database::{BuildingId, BuildingKind, Database, ItemId};
+
+    /// A version 1 document: a group containing one smelter, with no copies fields.
+    fn v1_document() -> Value {
+        json!({
+            "kind": {"Group": {
+                "name": "Iron",
+                "children": [{
+                    "kind": {"Building": {
+                        "building": "Desc_SmelterMk1_C",
+                        "settings": {"Manufacturer": {
+                            "recipe": "Recipe_IngotIron_C",
+                            "clock_speed": 1.0,
+                        }},
+                    }},
+                    "balance": {
+                        "power": -4.0,
+                        "balances": {"Desc_OreIron_C": -30.0, "Desc_IronIngot_C": 30.0},
+                    },
+                    "warning": null,
+                }],
+                "id": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+            }},
+            "balance": {
+                "power": -4.0,
+                "balances": {"Desc_OreIron_C": -30.0, "Desc_IronIngot_C": 30.0},
+            },
+            "warning": null,
+        })
+    }
+
+    #[test]
+    fn migrates_v1_document() {
+        let root = load(v1_document()).unwrap();
+        let group = root.group().unwrap();
+        assert_eq!(group.name, "Iron");
+        assert_eq!(group.copies, 1);
+        assert_eq!(group.primary_product, None);
+        assert_eq!(group.tag, None);
+        assert!(group.assumed_supplied.is_empty());
+        assert_eq!(group.children.len(), 1);
+        let building = group.children[0].building().unwrap();
+        assert_eq!(building.copies, 1);
+        assert!(!building.locked);
+        assert_eq!(building.tag, None);
+        assert_eq!(building.power_override, None);
+        assert_eq!(building.settings.clock_speed(), 1.0);
+        assert_eq!(
+            root.balance().balances[&ItemId::from("Desc_IronIngot_C")],
+            30.0
+        );
+    }
+
+    #[test]
+    fn migration_fills_in_new_fields() {
+        let mut root = v1_document();
+        for version in 1..SCHEMA_VERSION {
+            migrate_step(version, &mut root);
+        }
+        let group = &root["kind"]["Group"];
+        assert_eq!(group["copies"], 1);
+        assert_eq!(group["primary_product"], Value::Null);
+        assert_eq!(group["tag"], Value::Null);
+        assert_eq!(group["assumed_supplied"], json!([]));
+        let building = &group["children"][0]["kind"]["Building"];
+        assert_eq!(building["copies"], 1);
+        assert_eq!(building["locked"], false);
+        assert_eq!(building["tag"], Value::Null);
+        assert_eq!(building["power_override"], Value::Null);
+        assert!(building.get("primary_product").is_none());
+        assert!(building.get("assumed_supplied").is_none());
+        assert!(group.get("locked").is_none());
+    }
+
+    #[test]
+    fn current_version_round_trips() {
+        let root = load(v1_document()).unwrap();
+        let stored = store(&root);
+        assert_eq!(stored["version"], SCHEMA_VERSION);
+        assert_eq!(load(stored).unwrap(), root);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let stored = json!({"version": SCHEMA_VERSION + 1, "root": null});
+        assert!(matches!(load(stored), Err(LoadError::TooNew(_))));
+    }
+
+    /// Number of random trees checked by the round-trip tests.
+    const RANDOM_TREES: u64 = 50;
+
+    /// Small deterministic random number generator (xorshift64*), so that failures in
+    /// the generated trees are reproducible from the seed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn new(seed: u64) -> Self {
+            // Xorshift gets stuck at zero, so avoid a zero state.
+            Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+        }
+
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        /// Random number in `0..n`.
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        /// True with the given percent probability.
+        fn chance(&mut self, percent: u64) -> bool {
+            self.next() % 100 < percent
+        }
+
+        fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+            &items[self.below(items.len())]
+        }
+    }
+
+    /// Ids which random trees are built from, sorted so generation doesn't depend on
+    /// hash map order.
+    struct Ids {
+        buildings: Vec<BuildingId>,
+        items: Vec<ItemId>,
+    }
+
+    impl Ids {
+        fn new(db: &Database) -> Self {
+            let mut buildings: Vec<_> = db.buildings.keys().copied().collect();
+            buildings.sort();
+            let mut items: Vec<_> = db.items.keys().copied().collect();
+            items.sort();
+            Self { buildings, items }
+        }
+    }
+
+    /// Generate a random building with random settings, copies, lock, and tag.
+    fn random_building(rng: &mut Rng, db: &Database, ids: &Ids) -> Node {
+        let id = *rng.pick(&ids.buildings);
+        let building_type = &db[id];
+        let mut settings = building_type.get_default_settings();
+        if let (BuildingSettings::Manufacturer(ms), BuildingKind::Manufacturer(m)) =
+            (&mut settings, &building_type.kind)
+        {
+            if !m.available_recipes.is_empty() && rng.chance(80) {
+                ms.recipe = Some(*rng.pick(&m.available_recipes));
+            }
+        }
+        // Arbitrary clock speeds between 1% and 250%.
+        settings.set_clock_speed((rng.below(24_900) + 100) as f32 / 10_000.0);
+        let building = Building {
+            building: Some(id),
+            settings,
+            copies: rng.below(5) as u32 + 1,
+            locked: rng.chance(20),
+            tag: rng.chance(30).then(|| *rng.pick(&ColorTag::ALL)),
+            // Quarter megawatts are exact in floating point.
+            power_override: rng.chance(10).then(|| rng.below(2000) as f32 / 4.0 - 250.0),
+        };
+        match building.clone().build_node(db) {
+            Ok(node) => node,
+            Err(e) => e.into_warning_node(building),
+        }
+    }
+
+    /// Generate a random tree of groups and buildings at most `depth` groups deep.
+    fn random_node(rng: &mut Rng, db: &Database, ids: &Ids, depth: u32) -> Node {
+        if depth == 0 || rng.chance(50) {
+            return random_building(rng, db, ids);
+        }
+        let children = (0..rng.below(5))
+            .map(|_| random_node(rng, db, ids, depth - 1))
+            .collect();
+        Group {
+            // Include characters which need escaping.
+            name: format!("Gr\u{f6}up \"{}\" \\ \u{2713}", rng.below(1000)),
+            children,
+            copies: rng.below(4) as u32 + 1,
+            primary_product: rng.chance(30).then(|| *rng.pick(&ids.items)),
+            tag: rng.chance(30).then(|| *rng.pick(&ColorTag::ALL)),
+            assumed_supplied: (0..rng.below(3)).map(|_| *rng.pick(&ids.items)).collect(),
+            id: Uuid::from_u128((rng.next() as u128) << 64 | rng.next() as u128),
+        }
+        .into()
+    }
+
+    /// Generate a random tree whose root is always a group, like a real document.
+    fn random_tree(seed: u64, db: &Database, ids: &Ids) -> Node {
+        let mut rng = Rng::new(seed);
+        let mut root = Group::empty();
+        root.children = (0..rng.below(6) + 1)
+            .map(|_| random_node(&mut rng, db, ids, 3))
+            .collect();
+        root.into()
+    }
+
+    /// Convert a stored document to an older schema version by dropping the fields added
+    /// since then. This undoes the migrations for trees whose dropped fields have their
+    /// default values.
+    fn downgrade(stored: &Value, version: u32) -> Value {
+        let mut root = stored["root"].clone();
+        for_each_node(&mut root, &mut |kind, inner| {
+            if version < 7 && kind == "Building" {
+                inner.remove("power_override");
+            }
+            if version < 6 && kind == "Group" {
+                inner.remove("assumed_supplied");
+            }
+            if version < 5 {
+                inner.remove("tag");
+            }
+            if version < 4 && kind == "Building" {
+                inner.remove("locked");
+            }
+            if version < 3 && kind == "Group" {
+                inner.remove("primary_product");
+            }
+            if version < 2 {
+                inner.remove("copies");
+            }
+        });
+        if version == 1 {
+            root
+        } else {
+            json!({"version": version, "root": root})
+        }
+    }
+
+    /// Get the stored root with the fields added after `version` reset to their defaults,
+    /// which is what loading a document of that version should produce.
+    fn with_defaults_since(stored: &Value, version: u32) -> Value {
+        let mut root = stored["root"].clone();
+        for_each_node(&mut root, &mut |kind, inner| {
+            if version < 7 && kind == "Building" {
+                inner.insert("power_override".into(), Value::Null);
+            }
+            if version < 6 && kind == "Group" {
+                inner.insert("assumed_supplied".into(), Value::Array(Vec::new()));
+            }
+            if version < 5 {
+                inner.insert("tag".into(), Value::Null);
+            }
+            if version < 4 && kind == "Building" {
+                inner.insert("locked".into(), false.into());
+            }
+            if version < 3 && kind == "Group" {
+                inner.insert("primary_product".into(), Value::Null);
+            }
+            if version < 2 {
+                inner.insert("copies".into(), 1.into());
+            }
+        });
+        root
+    }
+
+    #[test]
+    fn random_trees_round_trip() {
+        let db = Database::load_default();
+        let ids = Ids::new(&db);
+        for seed in 0..RANDOM_TREES {
+            let root = random_tree(seed, &db, &ids);
+            let text = serde_json::to_string(&store(&root)).unwrap();
+            let loaded = load(serde_json::from_str(&text).unwrap()).unwrap();
+            assert_eq!(loaded, root, "seed {}", seed);
+            // Plain serialization, as used for copy and paste, round trips too.
+            let text = serde_json::to_string(&root).unwrap();
+            let parsed: Node = serde_json::from_str(&text).unwrap();
+            assert_eq!(parsed, root, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn random_trees_migrate_from_every_version() {
+        let db = Database::load_default();
+        let ids = Ids::new(&db);
+        for seed in 0..RANDOM_TREES {
+            let stored = store(&random_tree(seed, &db, &ids));
+            for version in 1..=SCHEMA_VERSION {
+                let loaded = load(downgrade(&stored, version)).unwrap();
+                assert_eq!(
+                    serde_json::to_value(&loaded).unwrap(),
+                    with_defaults_since(&stored, version),
+                    "seed {}, version {}",
+                    seed,
+                    version
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright 2022 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Greedy solver which builds a production tree for a set of target outputs.
+
+use std::collections::BTreeMap;
+
+use crate::accounting::{BuildNode, Building, BuildingSettings, Group, ManufacturerSettings, Node};
+use crate::database::{BuildingId, BuildingKind, Database, ItemId, Manufacturer, Recipe, RecipeId};
+
+/// Maximum number of times the solver adds production before giving up. Bounds the
+/// work done for cyclic recipes.
+const MAX_ITERATIONS: usize = 1000;
+
+/// Rates smaller than this are considered balanced.
+const EPSILON: f32 = 1e-3;
+
+/// How the solver chooses which recipe to use to produce an item.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum RecipeStrategy {
+    /// Prefer standard recipes, only using alternates when there is no standard recipe.
+    #[default]
+    Standard,
+    /// Prefer alternate recipes when any are available.
+    PreferAlternates,
+}
+
+/// Result of solving for a set of targets.
+#[derive(Debug, Clone)]
+pub struct Solution {
+    /// Group containing one building node per recipe used.
+    pub group: Group,
+    /// Items which must be supplied from outside the group, such as raw resources, with
+    /// their rates per minute.
+    pub imports: BTreeMap<ItemId, f32>,
+    /// False if the solver gave up before balancing every producible item, which can
+    /// happen with cyclic recipes.
+    pub converged: bool,
+}
+
+/// Build a group of buildings which produces the given `(item, rate per minute)`
+/// targets. Raw resources (items which can be mined) and items with no usable recipe
+/// are left as imports.
+pub fn solve(db: &Database, targets: &[(ItemId, f32)], strategy: RecipeStrategy) -> Solution {
+    let mut net: BTreeMap<ItemId, f32> = BTreeMap::new();
+    for &(item, rate) in targets {
+        *net.entry(item).or_default() -= rate;
+    }
+    let mut recipe_choices: BTreeMap<ItemId, Option<&Recipe>> = BTreeMap::new();
+    let mut runs: BTreeMap<RecipeId, f32> = BTreeMap::new();
+
+    let mut converged = false;
+    for _ in 0..MAX_ITERATIONS {
+        let next = net.iter().find_map(|(&item, &rate)| {
+            if rate >= -EPSILON {
+                return None;
+            }
+            let recipe = *recipe_choices
+                .entry(item)
+                .or_insert_with(|| choose_recipe(db, item, strategy));
+            recipe.map(|recipe| (item, rate, recipe))
+        });
+        let (item, rate, recipe) = match next {
+            Some(next) => next,
+            None => {
+                converged = true;
+                break;
+            }
+        };
+        let per_run: f32 = recipe
+            .products
+            .iter()
+            .filter(|product| product.item == item)
+            .map(|product| product.amount)
+            .sum();
+        let new_runs = -rate / per_run;
+        *runs.entry(recipe.id).or_default() += new_runs;
+        for input in &recipe.ingredients {
+            *net.entry(input.item).or_default() -= input.amount * new_runs;
+        }
+        for output in &recipe.products {
+            *net.entry(output.item).or_default() += output.amount * new_runs;
+        }
+    }
+
+    let mut group = Group::empty();
+    group.name = targets
+        .iter()
+        .map(|&(item, _)| {
+            db.get(item)
+                .map_or_else(|| item.to_string(), |i| i.name.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    group.children = runs
+        .iter()
+        .filter_map(|(&recipe_id, &runs)| build_recipe_node(db, recipe_id, runs))
+        .collect();
+    let imports = net
+        .into_iter()
+        .filter(|&(_, rate)| rate < -EPSILON)
+        .map(|(item, rate)| (item, -rate))
+        .collect();
+    Solution {
+        group,
+        imports,
+        converged,
+    }
+}
+
+/// Build one node for each of the given `(item, rate per minute)` requirements which
+/// produces it with its preferred recipe, without adding production for the inputs of
+/// those recipes. Raw resources and items with no usable recipe are skipped.
+pub fn produce_directly(
+    db: &Database,
+    targets: &[(ItemId, f32)],
+    strategy: RecipeStrategy,
+) -> Vec<Node> {
+    targets
+        .iter()
+        .filter_map(|&(item, rate)| {
+            let recipe = choose_recipe(db, item, strategy)?;
+            let per_run: f32 = recipe
+                .products
+                .iter()
+                .filter(|product| product.item == item)
+                .map(|product| product.amount)
+                .sum();
+            build_recipe_node(db, recipe.id, rate / per_run)
+        })
+        .collect()
+}
+
+/// Choose the recipe to produce an item with, or None if the item should be imported.
+fn choose_recipe(db: &Database, item: ItemId, strategy: RecipeStrategy) -> Option<&Recipe> {
+    let item_info = db.get(item)?;
+    if !item_info.mined_by.is_empty() {
+        return None;
+    }
+    let prefer_alternates = strategy == RecipeStrategy::PreferAlternates;
+    let mut candidates: Vec<&Recipe> = item_info
+        .produced_by
+        .iter()
+        .filter_map(|&id| db.get(id))
+        .filter(|recipe| manufacturer_for(db, recipe).is_some())
+        .collect();
+    // Prefer recipes by strategy first, then recipes where the item is the main product
+    // rather than a byproduct. The sort is stable so database order breaks ties.
+    candidates.sort_by_key(|recipe| {
+        let is_primary = recipe.products.first().map(|p| p.item) == Some(item);
+        (recipe.is_alternate != prefer_alternates, !is_primary)
+    });
+    candidates.into_iter().next()
+}
+
+/// Find a manufacturer which can run the given recipe.
+fn manufacturer_for<'a>(
+    db: &'a Database,
+    recipe: &Recipe,
+) -> Option<(BuildingId, &'a Manufacturer)> {
+    recipe
+        .produced_in
+        .iter()
+        .find_map(|&id| match &db.get(id)?.kind {
+            BuildingKind::Manufacturer(m) if m.available_recipes.contains(&recipe.id) => {
+                Some((id, m))
+            }
+            _ => None,
+        })
+}
+
+/// Build a node running the recipe the given number of times per minute, using as few
+/// buildings as possible at equal clock speeds of at most 100%.
+fn build_recipe_node(db: &Database, recipe_id: RecipeId, runs: f32) -> Option<Node> {
+    let recipe = db.get(recipe_id)?;
+    let (building_id, m) = manufacturer_for(db, recipe)?;
+    let runs_per_building = 60.0 / recipe.time * m.manufacturing_speed;
+    let buildings = runs / runs_per_building;
+    let copies = (buildings - EPSILON).ceil().max(1.0);
+    let building = Building {
+        building: Some(building_id),
+        settings: BuildingSettings::Manufacturer(ManufacturerSettings {
+            recipe: Some(recipe_id),
+            clock_speed: (buildings / copies).clamp(0.01, 1.0),
+        }),
+        copies: copies as u32,
+        locked: false,
+        tag: None,
+        power_override: None,
+    };
+    Some(match building.clone().build_node(db) {
+        Ok(node) => node,
+        Err(e) => e.into_warning_node(building),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solves_iron_plates() {
+        let db = Database::load_default();
+        let solution = solve(
+            &db,
+            &[("Desc_IronPlate_C".into(), 30.0)],
+            RecipeStrategy::Standard,
+        );
+        assert!(solution.converged);
+        assert_eq!(solution.group.children.len(), 2);
+        for child in &solution.group.children {
+            let building = child.building().unwrap();
+            assert_eq!(building.copies, 2);
+            assert!((building.settings.clock_speed() - 0.75).abs() < 1e-4);
+        }
+        let node: Node = solution.group.into();
+        let balances = &node.balance().balances;
+        let plates = balances[&ItemId::from("Desc_IronPlate_C")];
+        assert!((plates - 30.0).abs() < 1e-3, "plates: {}", plates);
+        let ingots = balances[&ItemId::from("Desc_IronIngot_C")];
+        assert!(ingots.abs() < 1e-3, "ingots: {}", ingots);
+        assert_eq!(solution.imports.len(), 1);
+        let ore = solution.imports[&ItemId::from("Desc_OreIron_C")];
+        assert!((ore - 45.0).abs() < 1e-3, "ore: {}", ore);
+    }
+
+    #[test]
+    fn produces_directly_without_expanding_inputs() {
+        let db = Database::load_default();
+        let nodes = produce_directly(
+            &db,
+            &[
+                ("Desc_IronIngot_C".into(), 45.0),
+                ("Desc_OreIron_C".into(), 30.0),
+            ],
+            RecipeStrategy::Standard,
+        );
+        // Ore is a raw resource, so only the ingots get a producer.
+        assert_eq!(nodes.len(), 1);
+        let building = nodes[0].building().unwrap();
+        assert_eq!(building.copies, 2);
+        assert!((building.settings.clock_speed() - 0.75).abs() < 1e-4);
+        let ingots = nodes[0].balance().balances[&ItemId::from("Desc_IronIngot_C")];
+        assert!((ingots - 45.0).abs() < 1e-3, "ingots: {}", ingots);
+    }
+
+    #[test]
+    fn terminates_on_cyclic_recipes() {
+        let db = Database::load_default();
+        // Recycled plastic and recycled rubber each consume the other's output.
+        let solution = solve(
+            &db,
+            &[("Desc_Plastic_C".into(), 60.0)],
+            RecipeStrategy::PreferAlternates,
+        );
+        let node: Node = solution.group.into();
+        let plastic = node.balance().balances[&ItemId::from("Desc_Plastic_C")];
+        assert!(plastic >= 60.0 - 1e-2, "plastic: {}", plastic);
+    }
+}
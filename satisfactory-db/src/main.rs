@@ -9,8 +9,8 @@ use std::collections::{HashMap, HashSet};
 
 use regex::Regex;
 use satisfactory_accounting::database::{
-    BuildingKind, BuildingType, Database, Fuel, Generator, Geothermal, Item, ItemAmount, ItemId,
-    Manufacturer, Miner, Power, PowerConsumer, Pump, Recipe, Station,
+    BuildingKind, BuildingType, Database, Footprint, Fuel, Generator, Geothermal, Item, ItemAmount,
+    ItemId, Manufacturer, Miner, Power, PowerConsumer, PowerRange, Pump, Recipe, Station,
 };
 
 mod rawdata;
@@ -150,6 +150,10 @@ fn main() {
                 .iter()
                 .map(|machine| machine.as_str().into())
                 .collect(),
+            variable_power: match (recipe.is_variable_power, recipe.min_power, recipe.max_power) {
+                (true, Some(min), Some(max)) => Some(PowerRange { min, max }),
+                _ => None,
+            },
         })
         // Patch a recipe for water using the water extractor.
         .chain(std::iter::once(Recipe {
@@ -164,6 +168,7 @@ fn main() {
             }],
             is_alternate: false,
             produced_in: vec!["Desc_WaterPump_C".into()],
+            variable_power: None,
         }))
         .map(|recipe| (recipe.id, recipe))
         .collect();
@@ -227,6 +232,36 @@ fn main() {
         .map(|item| (item.id, item))
         .collect();
 
+    // The sizes in the raw data are zero for every production building, so footprints
+    // are patched in from the wiki as (width, length) in meters. Buildings which aren't
+    // placed on foundations in a factory footprint, like lights and pumps, are left out.
+    let footprints: HashMap<&str, Footprint> = [
+        ("Desc_ConstructorMk1_C", 8.0, 10.0),
+        ("Desc_AssemblerMk1_C", 10.0, 15.0),
+        ("Desc_ManufacturerMk1_C", 18.0, 20.0),
+        ("Desc_SmelterMk1_C", 6.0, 9.0),
+        ("Desc_FoundryMk1_C", 10.0, 9.0),
+        ("Desc_OilRefinery_C", 10.0, 20.0),
+        ("Desc_Packager_C", 8.0, 8.0),
+        ("Desc_Blender_C", 18.0, 16.0),
+        ("Desc_HadronCollider_C", 24.0, 38.0),
+        ("Desc_WaterPump_C", 20.0, 19.5),
+        ("Desc_MinerMk1_C", 6.0, 14.0),
+        ("Desc_MinerMk2_C", 6.0, 14.0),
+        ("Desc_MinerMk3_C", 6.0, 14.0),
+        ("Desc_OilPump_C", 8.0, 14.0),
+        ("Desc_FrackingSmasher_C", 10.0, 10.0),
+        ("Desc_GeneratorBiomass_C", 8.0, 8.0),
+        ("Desc_GeneratorCoal_C", 10.0, 26.0),
+        ("Desc_GeneratorFuel_C", 20.0, 20.0),
+        ("Desc_GeneratorNuclear_C", 36.0, 43.0),
+        ("Desc_GeneratorGeoThermal_C", 20.0, 20.0),
+        ("Desc_ResourceSink_C", 16.0, 13.0),
+    ]
+    .into_iter()
+    .map(|(id, width, length)| (id, Footprint { width, length }))
+    .collect();
+
     let mut buildings: HashMap<_, _> = raw
         .buildings
         .values()
@@ -239,6 +274,7 @@ fn main() {
             id: building.class_name.as_str().into(),
             image: building.slug.as_str().into(),
             description: building.description.clone(),
+            footprint: footprints.get(building.class_name.as_str()).copied(),
             kind: if manufacturers.contains(building.class_name.as_str()) {
                 BuildingKind::Manufacturer(Manufacturer {
                     manufacturing_speed: building.metadata.manufacturing_speed.unwrap_or(1.0),